@@ -38,10 +38,13 @@ When adding <i>foo</i> directory recursively, both <i>foo</i> and <i>foo/bar.txt
 using attribute from filesystem metadata (stats on Linux)
 When directly adding <i>foo/bar.txt</i>, <i>foo</i> directory is automatically created using default
 metadata:
-- owner and group: 1000,
-- right: 0x755,
+- owner and group: 1000 (see <i>--implicit-dir-owner</i> and <i>--implicit-dir-group</i>),
+- right: 0x755 (see <i>--implicit-dir-mode</i>),
 - mtime: 0
 
+With <i>--implicit-dir-inherit ancestor</i>, <i>foo</i> is added with the metadata of the directory containing
+<i>bar.txt</i> on the filesystem. With <i>--implicit-dir-inherit child</i>, it takes the metadata of <i>foo/bar.txt</i>.
+
 This is the same thing when using file list. It is preferable to list both directories and files in the listing:
 ```
 foo
@@ -70,6 +73,11 @@ The option <i>--dir_as_root</i> makes arx trim the directory itself. So (if recu
 in the given directory as place at root of the created archive.
 This option has no effect if the input path is a file.
 
+<s>Rewriting path</s>
+
+Paths can be further rewritten with <i>--transform</i> using sed-like expressions, as <i>tar --transform</i> does.
+Transformations are applied after trimming. Two entries transformed to the same path is an error.
+
 <s,u>Compression detection/selection:</>
 
 Arx automatically detect if a content should be compressed or not based on a heuristic using
@@ -78,7 +86,12 @@ You can select which compression algorithm is used using <i>--compression</i> op
 <i>--compression <<algorithm></i> or <i>--compression <<algorithm>=<<level></i>.
 List of available compression algorithms can be obtained using <i>--list-compressions</i> option.
 You can use <i>--compresssion none</i> to deactivate compression.
-It is not possible to force compression of content (patch welcome).
+
+You can force or prevent compression of some files with <i>--compress</i> and <i>--no-compress</i>:
+<i>$ arx create -o my_archive.arx --compress '*.log' --no-compress '*.jpg,*.zst' input_dir</i>
+Rules can also be written <i>--compress '*.log=yes'</i>, <i>--compress 'data/*=auto'</i>, ...
+All compressed contents use the algorithm and level given by <i>--compression</i>: rules
+cannot choose another algorithm or level (<i>--compress '*.log=zstd=19'</i> is an error).
 "
 );
 
@@ -175,6 +188,15 @@ pub struct Options {
     #[arg(short,long, value_parser=jbk::cmd_utils::compression_arg_parser, required=false, default_value = "zstd")]
     compression: jbk::creator::Compression,
 
+    #[command(flatten)]
+    compression_rules: arx::cmd_utils::CompressionRules,
+
+    /// Number of threads used to read the input files
+    ///
+    /// Default to the number of available CPUs. Entries are always added in the same order.
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<usize>,
+
     /// List available compression algorithms
     #[arg(long, default_value_t = false, action)]
     list_compressions: bool,
@@ -197,11 +219,194 @@ pub struct Options {
     )]
     follow_symlink: bool,
 
+    /// Don't walk into directories on another filesystem than the input directory
+    ///
+    /// Mount points (`/proc`, network mounts, bind mounts...) are not added to the archive.
+    #[arg(long, help_heading = "Input options")]
+    one_file_system: bool,
+
+    /// With `--one-file-system`, add mount points as empty directories
+    #[arg(long, requires = "one_file_system", help_heading = "Input options")]
+    keep_mount_points: bool,
+
+    #[command(flatten)]
+    path_transforms: arx::cmd_utils::PathTransforms,
+
+    /// What to do when a file cannot be read (permission denied, vanished file, broken symlink...)
+    ///
+    /// With `skip` or `warn`, the file is not added and the skipped files are listed, with
+    /// their error, at the end.
+    #[arg(long, value_enum, default_value_t = arx::create::OnError::Abort, help_heading = "Input options")]
+    on_error: arx::create::OnError,
+
+    #[command(flatten)]
+    exclude: ExcludeOptions,
+
+    /// Create a reproducible archive
+    ///
+    /// All entries are stored with owner and group 0, a normalized mode (755 or 644) and
+    /// `SOURCE_DATE_EPOCH` (or 0 if not set) as modification time.
+    /// Directories are walked in a sorted order.
+    #[arg(long, action)]
+    reproducible: bool,
+
+    #[command(flatten)]
+    metadata_override: arx::cmd_utils::MetadataOverride,
+
+    /// Mode of the directories created implicitly (`755` by default, chmod-like changes are accepted)
+    #[arg(long, value_name = "MODE", value_parser = arx::create::ModeSpec::parse, conflicts_with = "implicit_dir_inherit", help_heading = "Metadata options")]
+    implicit_dir_mode: Option<arx::create::ModeSpec>,
+
+    /// Owner of the directories created implicitly (`ID`, `NAME` or `NAME:ID`, 1000 by default)
+    #[arg(long, value_name = "USER", value_parser = arx::create::Owner::parse_user, conflicts_with = "implicit_dir_inherit", help_heading = "Metadata options")]
+    implicit_dir_owner: Option<arx::create::Owner>,
+
+    /// Group of the directories created implicitly (`ID`, `NAME` or `NAME:ID`, 1000 by default)
+    #[arg(long, value_name = "GROUP", value_parser = arx::create::Owner::parse_group, conflicts_with = "implicit_dir_inherit", help_heading = "Metadata options")]
+    implicit_dir_group: Option<arx::create::Owner>,
+
+    /// Take the metadata of the directories created implicitly from another entry
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FROM",
+        help_heading = "Metadata options"
+    )]
+    implicit_dir_inherit: Option<ImplicitDirInherit>,
+
+    /// Add an archive-level metadata (can be given several times)
+    ///
+    /// Metadata are free key/value pairs (build id, git commit, description, ...)
+    /// which can be read back with `arx info`.
+    #[arg(long = "meta", value_name = "KEY=VALUE", value_parser = parse_metadata, action = clap::ArgAction::Append)]
+    metadata: Vec<(String, String)>,
+
     #[arg(from_global)]
     verbose: u8,
 }
 
-fn check_input_paths_exist(file_list: &[PathBuf]) -> Result<()> {
+/// Options selecting the files to exclude, shared by the commands walking input directories.
+#[derive(clap::Args, Debug)]
+pub(crate) struct ExcludeOptions {
+    /// Exclude files matching PATTERN (can be given several times)
+    ///
+    /// Patterns use the `.gitignore` syntax: a pattern without `/` matches the file name at any
+    /// depth, other patterns are relative to the input directory. A trailing `/` matches only
+    /// directories and a leading `!` re-includes previously excluded files.
+    #[arg(long, value_name = "PATTERN", action = clap::ArgAction::Append, help_heading = "Input options")]
+    exclude: Vec<String>,
+
+    /// Read exclude patterns from FILE (one per line)
+    ///
+    /// With FILE_LIST, the patterns (and --exclude ones) are matched against the listed paths.
+    /// Ignore files are only read when walking input directories.
+    #[arg(long, value_name = "FILE", value_hint=ValueHint::FilePath, action = clap::ArgAction::Append, help_heading = "Input options")]
+    exclude_from: Vec<PathBuf>,
+
+    /// Exclude version control system directories and files (.git, .svn, .hg, ...)
+    #[arg(long, action, help_heading = "Input options")]
+    exclude_vcs: bool,
+
+    /// Honour `.gitignore` and `.arxignore` files found in the input directories
+    #[arg(long, action, help_heading = "Input options")]
+    ignore_files: bool,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum ImplicitDirInherit {
+    /// From the nearest existing ancestor directory on the filesystem
+    Ancestor,
+    /// From the entry whose addition creates the directory
+    Child,
+}
+
+fn implicit_dirs(options: &Options) -> arx::create::ImplicitDirs {
+    if let Some(ImplicitDirInherit::Child) = options.implicit_dir_inherit {
+        return arx::create::ImplicitDirs::FromChild;
+    }
+    let mut metadata = arx::create::ImplicitDirMetadata::default();
+    if let Some(mode) = &options.implicit_dir_mode {
+        metadata.mode = mode.apply(metadata.mode, true);
+    }
+    if let Some(owner) = &options.implicit_dir_owner {
+        metadata.owner = owner.clone();
+    }
+    if let Some(group) = &options.implicit_dir_group {
+        metadata.group = group.clone();
+    }
+    arx::create::ImplicitDirs::Fixed(metadata)
+}
+
+fn parse_metadata(s: &str) -> Result<(String, String)> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Metadata must be in the form KEY=VALUE"))?;
+    if key.is_empty() {
+        return Err(anyhow!("Metadata key cannot be empty"));
+    }
+    Ok((key.into(), value.into()))
+}
+
+impl ExcludeOptions {
+    pub(crate) fn filter(&self) -> Result<Option<arx::create::ExcludeFilter>> {
+        let mut rules = if self.exclude_vcs {
+            arx::create::ExcludeRules::vcs()
+        } else {
+            arx::create::ExcludeRules::new()
+        };
+        for pattern in &self.exclude {
+            rules.add(pattern)?;
+        }
+        for file in &self.exclude_from {
+            rules
+                .add_from_file(file)
+                .with_context(|| format!("Cannot read exclude patterns from {}", file.display()))?;
+        }
+        if rules.is_empty() && !self.ignore_files {
+            return Ok(None);
+        }
+        let mut filter = arx::create::ExcludeFilter::new(rules);
+        if self.ignore_files {
+            filter = filter.ignore_file(".gitignore").ignore_file(".arxignore");
+        }
+        Ok(Some(filter))
+    }
+}
+
+/// Add `infile` to `adder`, skipping the files excluded by `filter`.
+pub(crate) fn add_from_path(
+    adder: &mut arx::create::FsAdder,
+    infile: &Path,
+    recurse: bool,
+    filter: &Option<arx::create::ExcludeFilter>,
+) -> Result<()> {
+    debug!("Adding file {infile:?}");
+    match filter {
+        None => adder.add_from_path(infile, recurse)?,
+        Some(filter) => {
+            let mut filter = filter.clone();
+            adder.add_from_path_with_filter(infile, recurse, move |e| filter.accept(e))?
+        }
+    }
+    Ok(())
+}
+
+/// The number of reading threads, the number of available CPUs by default.
+pub(crate) fn jobs(jobs: Option<usize>) -> usize {
+    jobs.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+}
+
+/// Print the files skipped because of errors.
+pub(crate) fn print_skipped(skipped: &[arx::create::SkippedEntry]) {
+    if !skipped.is_empty() {
+        eprintln!("{} file(s) skipped because of errors:", skipped.len());
+        for entry in skipped {
+            eprintln!("  {}: {}", entry.path.display(), entry.error);
+        }
+    }
+}
+
+pub(crate) fn check_input_paths_exist(file_list: &[PathBuf]) -> Result<()> {
     // Check that input files actually exists
     for file in file_list.iter() {
         if !file.exists() {
@@ -214,7 +419,7 @@ fn check_input_paths_exist(file_list: &[PathBuf]) -> Result<()> {
     Ok(())
 }
 
-fn check_output_path_writable(out_file: &Path, force: bool) -> Result<()> {
+pub(crate) fn check_output_path_writable(out_file: &Path, force: bool) -> Result<()> {
     let out_file = absolute(out_file)?;
     if !out_file.parent().unwrap().is_dir() {
         Err(anyhow!(
@@ -231,13 +436,13 @@ fn check_output_path_writable(out_file: &Path, force: bool) -> Result<()> {
     }
 }
 
-struct ProgressBar {
+pub(crate) struct ProgressBar {
     comp_clusters: indicatif::ProgressBar,
     uncomp_clusters: indicatif::ProgressBar,
 }
 
 impl ProgressBar {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         let style = indicatif::ProgressStyle::with_template(
             "{prefix} : {wide_bar:.cyan/blue} {pos:4} / {len:4}",
         )
@@ -304,6 +509,9 @@ pub fn create(options: Options) -> Result<()> {
     check_output_path_writable(out_file.as_std_path(), options.force)?;
 
     info!("Creating archive {:?}", out_file);
+    let filter = options.exclude.filter()?;
+    let implicit_dirs = implicit_dirs(&options);
+    let compression_rules = arx::create::CompressionRules::try_from(options.compression_rules)?;
     let file_list = options
         .file_list
         .as_ref()
@@ -329,13 +537,36 @@ pub fn create(options: Options) -> Result<()> {
         cache_progress.clone(),
         options.compression,
     )?;
+    if options.reproducible {
+        creator.set_reproducible(arx::create::Reproducible::from_env()?);
+    }
+    creator.set_implicit_dirs(implicit_dirs);
+    creator.set_metadata_override(options.metadata_override.into());
+    let path_transforms = arx::create::PathTransforms::from(options.path_transforms);
+    if !path_transforms.is_empty() {
+        creator.set_path_transforms(path_transforms);
+    }
 
     let mut adder = arx::create::FsAdder::new(
         &mut creator,
         options.keep_parents,
         options.follow_symlink,
         options.dir_as_root,
+        match (options.one_file_system, options.keep_mount_points) {
+            (false, _) => arx::create::MountPoints::Cross,
+            (true, false) => arx::create::MountPoints::Exclude,
+            (true, true) => arx::create::MountPoints::EmptyDir,
+        },
     );
+    if !compression_rules.is_empty() {
+        adder.set_compression_policy(compression_rules);
+    }
+    adder.set_on_error(options.on_error);
+    adder.set_parents_from_fs(matches!(
+        options.implicit_dir_inherit,
+        Some(ImplicitDirInherit::Ancestor)
+    ));
+    adder.set_jobs(jobs(options.jobs))?;
 
     if let Some(file_list) = file_list {
         let file = File::open(&file_list)
@@ -343,16 +574,25 @@ pub fn create(options: Options) -> Result<()> {
         let files_list = BufReader::new(file)
             .lines()
             .map(|l| -> Result<PathBuf> { Ok(l?.into()) })
+            .filter(|p| match (p, &filter) {
+                (Ok(p), Some(filter)) => filter.accept_path(p),
+                _ => true,
+            })
             .collect::<Result<Vec<_>>>()?;
         adder.add_from_list(files_list.into_iter())?;
     } else {
         check_input_paths_exist(&options.infiles)?;
-        for infile in options.infiles {
-            debug!("Adding file {infile:?}");
-            adder.add_from_path(&infile, options.recurse)?;
+        for infile in &options.infiles {
+            add_from_path(&mut adder, infile, options.recurse, &filter)?;
         }
     };
 
+    print_skipped(adder.skipped());
+
+    for (key, value) in options.metadata {
+        creator.set_metadata(key, value);
+    }
+
     let ret = creator.finalize();
     debug!("Saved place is {}", cache_progress.0.get());
     Ok(ret?)
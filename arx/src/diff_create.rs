@@ -0,0 +1,48 @@
+use anyhow::Result;
+use clap::{Parser, ValueHint};
+use log::{debug, info};
+
+use crate::create::check_output_path_writable;
+use crate::update::InputOptions;
+
+/// Create a patch archive containing only what changed since a base archive.
+///
+/// Added and modified entries are stored as in `arx create`, removed entries are stored
+/// as whiteouts. Directories are always stored.
+/// Use `arx extract --apply-on <BASE> <PATCH>` to extract the base archive patched.
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// File path of the patch archive to create.
+    #[arg(short, long, value_parser, value_hint=ValueHint::FilePath)]
+    outfile: jbk::Utf8PathBuf,
+
+    #[command(flatten)]
+    input: InputOptions,
+
+    #[arg(from_global)]
+    verbose: u8,
+}
+
+pub fn diff_create(mut options: Options) -> Result<()> {
+    check_output_path_writable(options.outfile.as_std_path(), options.input.force)?;
+    info!(
+        "Creating patch {:?} from base {:?}",
+        options.outfile,
+        options.input.base()
+    );
+    let concat_mode = match options.input.concat_mode.take() {
+        None => jbk::creator::ConcatMode::OneFile,
+        Some(e) => e.into(),
+    };
+    let (mut creator, report) = options
+        .input
+        .add_inputs(&options.outfile, concat_mode, true)?;
+    for (path, change) in report.entries {
+        if change == arx::create::Change::Removed {
+            debug!("Adding whiteout {path}");
+            creator.add_whiteout(path)?;
+        }
+    }
+    creator.finalize()?;
+    Ok(())
+}
@@ -18,8 +18,12 @@ impl arx::Builder for FileBuilder {
         }
     }
 
-    fn create_entry(&self, _idx: jbk::EntryIdx, reader: &ByteSlice) -> jbk::Result<Self::Entry> {
-        self.content_address_property.create(reader)
+    fn create_entry(
+        &self,
+        _idx: jbk::EntryIdx,
+        reader: &ByteSlice,
+    ) -> Result<Self::Entry, arx::BaseError> {
+        Ok(self.content_address_property.create(reader)?)
     }
 }
 
@@ -51,6 +55,8 @@ fn dump_entry(
             Ok(())
         }
         arx::Entry::Link(_) => Err(ArxError::IsALink),
+        arx::Entry::Special(_) => Err(ArxError::IsASpecialFile),
+        arx::Entry::Whiteout(_) => Err(ArxError::IsAWhiteout),
     }
 }
 
@@ -0,0 +1,132 @@
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, ValueHint};
+use log::info;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::create::{check_output_path_writable, ProgressBar};
+
+fn rename_parser(s: &str) -> Result<(String, String)> {
+    let (from, to) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Rename must be of the form FROM=TO"))?;
+    Ok((from.into(), to.into()))
+}
+
+fn chmod_parser(s: &str) -> Result<(String, arx::create::ModeSpec)> {
+    let (pattern, mode) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Chmod must be of the form PATTERN=MODE"))?;
+    Ok((pattern.into(), arx::create::ModeSpec::parse(mode)?))
+}
+
+/// Create a new archive with some entries of an existing archive deleted, renamed or
+/// with their mode changed.
+///
+/// Deletions are applied first, then renamings, then mode changes.
+/// PATTERN uses the glob syntax and is matched against the whole path of the entries
+/// (`*` does not match `/`).
+///
+/// Only the entries are stored again: the new archive references the content packs of the
+/// input archive, which must stay at the same location. So the new archive is created in two
+/// files by default, and cannot be created in only one file.
+///
+/// With `--gc`, the content of each kept file is read from the input archive and compressed
+/// again, and the content of deleted files is dropped (as needed to remove a leaked secret).
+/// The new archive then doesn't depend on the input archive.
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// Archive to edit
+    #[arg(value_parser, value_hint=ValueHint::FilePath)]
+    infile: PathBuf,
+
+    /// File path of the archive to create.
+    #[arg(short, long, value_parser, value_hint=ValueHint::FilePath)]
+    outfile: jbk::Utf8PathBuf,
+
+    /// Delete the entries matching PATTERN (and their children)
+    #[arg(long, value_name = "PATTERN")]
+    delete: Vec<String>,
+
+    /// Move the entry at FROM (and its children) to TO, as FROM=TO
+    #[arg(long, value_name = "FROM=TO", value_parser=rename_parser)]
+    rename: Vec<(String, String)>,
+
+    /// Change the mode of the entries matching PATTERN, as PATTERN=MODE
+    ///
+    /// MODE is an octal mode or symbolic clauses, as for chmod (`644`, `u+x,go-w`).
+    #[arg(long, value_name = "PATTERN=MODE", value_parser=chmod_parser)]
+    chmod: Vec<(String, arx::create::ModeSpec)>,
+
+    /// Store again the content of the kept files, dropping the content of the deleted ones
+    #[arg(long)]
+    gc: bool,
+
+    #[command(flatten)]
+    concat_mode: Option<jbk::cmd_utils::ConcatMode>,
+
+    /// Set compression algorithm to use (with `--gc`)
+    #[arg(short,long, value_parser=jbk::cmd_utils::compression_arg_parser, required=false, default_value = "zstd")]
+    compression: jbk::creator::Compression,
+
+    /// Print a progression of the creation
+    #[arg(long, default_value_t = false, action)]
+    progress: bool,
+
+    /// Overwrite existing archive file
+    #[arg(short, long)]
+    force: bool,
+
+    #[arg(from_global)]
+    verbose: u8,
+}
+
+pub fn edit(options: Options) -> Result<()> {
+    check_output_path_writable(options.outfile.as_std_path(), options.force)?;
+    let mut edits = arx::create::Edits::new();
+    for pattern in &options.delete {
+        edits.delete(pattern)?;
+    }
+    for (from, to) in &options.rename {
+        edits.rename(from, to)?;
+    }
+    for (pattern, mode) in &options.chmod {
+        edits.chmod(pattern, mode.clone())?;
+    }
+    let arx =
+        arx::Arx::new(&options.infile).with_context(|| format!("Opening {:?}", options.infile))?;
+    info!("Editing {:?} into {:?}", options.infile, options.outfile);
+
+    let jbk_progress: Arc<dyn jbk::creator::Progress> = if options.progress {
+        Arc::new(ProgressBar::new())
+    } else {
+        Arc::new(())
+    };
+    let concat_mode = match options.concat_mode {
+        None if options.gc => jbk::creator::ConcatMode::OneFile,
+        None => jbk::creator::ConcatMode::TwoFiles,
+        Some(e) => e.into(),
+    };
+    if let (false, jbk::creator::ConcatMode::OneFile) = (options.gc, &concat_mode) {
+        return Err(anyhow!(
+            "arx edit references the content packs of the input archive, it cannot create only one file without --gc"
+        ));
+    }
+    let mut creator = arx::create::SimpleCreator::new(
+        &options.outfile,
+        concat_mode,
+        jbk_progress,
+        Rc::new(()),
+        options.compression,
+    )?;
+    for (key, value) in arx.metadata()? {
+        creator.set_metadata(key, value);
+    }
+    let mut adder = arx::create::ArxAdder::new(&mut creator, arx::create::Conflict::Error);
+    adder.set_edits(&edits);
+    adder.set_reuse_packs(!options.gc);
+    adder.add_arx(&arx, &options.infile, arx::Path::new(""))?;
+    creator.finalize()?;
+    Ok(())
+}
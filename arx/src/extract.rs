@@ -57,6 +57,13 @@ By default, arx extracts from the root directory of the archive.
 <s>--root-dir</> option allow to change the root directory.
 This is equivalent to a (virtual) cd in the root directory before walking the tree and apply filter.
 Extract list and filter are relative to the root filter.
+
+<s,u>Patch archives</>
+
+Archives created with <s>arx diff-create</> contain only the changes from a base archive.
+<s>--apply-on</> extracts the base archive(s) with the archive applied on top of them:
+entries of the archive replace the ones of the base archives and deleted entries are not extracted.
+If several base archives are given, they are applied in order, the first one being the bottom layer.
 ");
 
 /// Extract the content of an archive
@@ -79,6 +86,13 @@ pub struct Options {
     #[arg(long)]
     root_dir: Option<PathBuf>,
 
+    /// Extract the archive applied on top of the given base archive (see `arx diff-create`)
+    ///
+    /// Can be given several times, the first base archive being the bottom layer.
+    /// The whole archives are always extracted.
+    #[arg(long, conflicts_with_all = ["input", "root_dir", "no_recurse"], value_hint=ValueHint::FilePath)]
+    apply_on: Vec<PathBuf>,
+
     /// Print a progress bar of the extraction
     #[arg(short = 'p', long = "progress", default_value_t = false, action)]
     progress: bool,
@@ -112,6 +126,28 @@ pub struct Options {
     #[arg(long, default_value = "warn")]
     overwrite: arx::Overwrite,
 
+    /// Restore extended attributes of the extracted entries
+    #[arg(long, default_value_t = false, action)]
+    xattrs: bool,
+
+    /// Restore the stored permissions (including setuid, setgid and sticky bits)
+    /// instead of applying the umask
+    #[arg(long, default_value_t = false, action)]
+    preserve_permissions: bool,
+
+    /// Restore the stored owner and group of the extracted entries (usually needs to be root)
+    #[arg(long, default_value_t = false, action)]
+    same_owner: bool,
+
+    /// With --same-owner, restore stored numeric ids instead of mapping owner and group
+    /// names to local ids
+    #[arg(long, default_value_t = false, action)]
+    numeric_owner: bool,
+
+    /// Restore the stored modification time (and access time if known) of the extracted entries
+    #[arg(long, default_value_t = false, action)]
+    preserve_mtime: bool,
+
     /// Recursively extract directories
     ///
     /// Default value is true.
@@ -137,8 +173,11 @@ fn get_extract_list(options: &Options) -> anyhow::Result<Vec<arx::PathBuf>> {
     if let Some(file_list) = &options.file_list {
         let file = File::open(file_list)?;
         let mut extract_files: Vec<arx::PathBuf> = Vec::new();
-        for line in BufReader::new(file).lines() {
-            extract_files.push(line?.into());
+        // Entry names may not be valid utf-8, lines are read as raw bytes.
+        for line in BufReader::new(file).split(b'\n') {
+            let line = line?;
+            let line = line.strip_suffix(b"\r").unwrap_or(&line);
+            extract_files.push(arx::path_from_bytes(line));
         }
         Ok(extract_files)
     } else {
@@ -183,11 +222,28 @@ pub fn extract(options: Options) -> anyhow::Result<()> {
     info!("Extract archive {:?} in {:?}", &options.infile, outdir);
 
     let builder = ExtractBuilder::new(&outdir)
-        .items(&extract_list, options.recurse)
-        .filter(filter)
         .progress(options.progress)
-        .overwrite(options.overwrite);
+        .overwrite(options.overwrite)
+        .xattrs(options.xattrs)
+        .preserve_permissions(options.preserve_permissions)
+        .same_owner(options.same_owner)
+        .numeric_owner(options.numeric_owner)
+        .preserve_mtime(options.preserve_mtime);
+
+    if !options.apply_on.is_empty() {
+        let mut layers = options
+            .apply_on
+            .iter()
+            .map(|base| {
+                info!("Apply on base archive {base:?}");
+                arx::Arx::new(base)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        layers.push(arx);
+        return Ok(builder.extract_layers(&arx::Layers::new(layers)?)?);
+    }
 
+    let builder = builder.items(&extract_list, options.recurse).filter(filter);
     Ok(builder.extract(
         &arx,
         options
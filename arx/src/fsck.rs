@@ -0,0 +1,36 @@
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, ValueHint};
+use log::info;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Check the structure of an archive.
+///
+/// Print one line per problem found, in the form `entry <idx> (<path>): <problem>`.
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// Archive to check
+    #[arg(value_parser, value_hint=ValueHint::FilePath)]
+    infile: PathBuf,
+
+    #[arg(from_global)]
+    verbose: u8,
+}
+
+pub fn fsck(options: Options) -> Result<()> {
+    info!("Check archive {:?}", options.infile);
+    let arx =
+        arx::Arx::new(&options.infile).with_context(|| format!("Opening {:?}", options.infile))?;
+    let report = arx.fsck()?;
+    let stdout = std::io::stdout();
+    let mut handle = std::io::BufWriter::new(stdout.lock());
+    for problem in &report.problems {
+        writeln!(handle, "{problem}")?;
+    }
+    handle.flush()?;
+    if report.is_ok() {
+        Ok(())
+    } else {
+        Err(anyhow!("{} problem(s) found", report.problems.len()))
+    }
+}
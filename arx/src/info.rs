@@ -0,0 +1,36 @@
+use anyhow::{Context, Result};
+use clap::{Parser, ValueHint};
+use jbk::reader::Range;
+use log::info;
+use std::path::PathBuf;
+
+/// Print information about an archive (number of entries and archive metadata).
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// Archive to read
+    #[arg(value_parser, value_hint=ValueHint::FilePath)]
+    infile: PathBuf,
+
+    #[arg(from_global)]
+    verbose: u8,
+}
+
+pub fn info(options: Options) -> Result<()> {
+    info!("Info on archive {:?}", options.infile);
+    let arx =
+        arx::Arx::new(&options.infile).with_context(|| format!("Opening {:?}", options.infile))?;
+    if let Some(index) = arx
+        .get_directory_pack()
+        .get_index_from_name("arx_entries")?
+    {
+        println!("Entries: {}", index.count().into_u32());
+    }
+    let metadata = arx.metadata()?;
+    if !metadata.is_empty() {
+        println!("Metadata:");
+        for (key, value) in metadata {
+            println!("  {key}: {value}");
+        }
+    }
+    Ok(())
+}
@@ -17,6 +17,12 @@ impl LightPath {
     }
 
     pub fn println2(&self, component: &[u8], output: &mut impl Write) -> std::io::Result<()> {
+        self.print2(component, output)?;
+        output.write_all(b"\n")
+    }
+
+    /// Write the path joined with `component`, as raw bytes (without end of line).
+    pub fn print2(&self, component: &[u8], output: &mut impl Write) -> std::io::Result<()> {
         let mut parts = self.0.iter();
         if let Some(part) = parts.next() {
             output.write_all(part)?;
@@ -28,9 +34,7 @@ impl LightPath {
                 output.write_all(b"/")?;
             }
         }
-        output.write_all(component)?;
-        output.write_all(b"\n")?;
-        Ok(())
+        output.write_all(component)
     }
 
     pub fn println(&self, output: &mut impl Write) -> std::io::Result<()> {
@@ -57,7 +61,7 @@ impl From<&LightPath> for std::path::PathBuf {
         let size = p.0.iter().map(|v| v.len()).sum();
         let mut s = Self::with_capacity(size);
         for part in &p.0 {
-            s.push(String::from_utf8_lossy(part).as_ref())
+            s.push(arx::bytes_to_os_path(part))
         }
         s
     }
@@ -26,7 +26,11 @@ impl arx::Builder for PathBuilder {
         }
     }
 
-    fn create_entry(&self, _idx: jbk::EntryIdx, reader: &ByteSlice) -> jbk::Result<Self::Entry> {
+    fn create_entry(
+        &self,
+        _idx: jbk::EntryIdx,
+        reader: &ByteSlice,
+    ) -> Result<Self::Entry, arx::BaseError> {
         let path_prop = self.path_property.create(reader)?;
         let mut path = jbk::SmallBytes::new();
         path_prop.resolve_to_vec(&mut path)?;
@@ -34,7 +38,7 @@ impl arx::Builder for PathBuilder {
     }
 }
 
-type LightBuilder = (PathBuilder, PathBuilder, PathBuilder);
+type LightBuilder = (PathBuilder, PathBuilder, PathBuilder, PathBuilder);
 
 struct Lister<W>
 where
@@ -77,6 +81,16 @@ where
     fn on_link(&self, current_path: &mut LightPath, path: &Path) -> Result<(), ArxError> {
         Ok(current_path.println2(path, self.output.borrow_mut().deref_mut())?)
     }
+    fn on_special(&self, current_path: &mut LightPath, path: &Path) -> Result<(), ArxError> {
+        Ok(current_path.println2(path, self.output.borrow_mut().deref_mut())?)
+    }
+    fn on_whiteout(
+        &self,
+        current_path: &mut LightPath,
+        whiteout: &arx::Whiteout,
+    ) -> Result<(), ArxError> {
+        Ok(current_path.println2(whiteout.path(), self.output.borrow_mut().deref_mut())?)
+    }
 }
 
 struct StableLister<W>
@@ -86,67 +100,73 @@ where
     output: RefCell<std::io::BufWriter<W>>,
 }
 
-impl<W> arx::walk::Operator<arx::PathBuf, arx::FullBuilder> for StableLister<W>
+/// Raw bytes of `path`, to be pushed on a `LightPath`.
+fn component(path: &[u8]) -> jbk::SmallBytes {
+    let mut component = jbk::SmallBytes::new();
+    component.extend_from_slice(path);
+    component
+}
+
+impl<W> arx::walk::Operator<LightPath, arx::FullBuilder> for StableLister<W>
 where
     W: std::io::Write,
 {
     type Error = ArxError;
-    fn on_start(&self, _current_path: &mut arx::PathBuf) -> Result<(), ArxError> {
+    fn on_start(&self, _current_path: &mut LightPath) -> Result<(), ArxError> {
         Ok(())
     }
-    fn on_stop(&self, _current_path: &mut arx::PathBuf) -> Result<(), ArxError> {
+    fn on_stop(&self, _current_path: &mut LightPath) -> Result<(), ArxError> {
         Ok(())
     }
     fn on_directory_enter(
         &self,
-        current_path: &mut arx::PathBuf,
+        current_path: &mut LightPath,
         dir: &arx::Dir,
     ) -> Result<bool, ArxError> {
-        current_path.push(String::from_utf8_lossy(dir.path()).as_ref());
-        writeln!(
-            self.output.borrow_mut(),
-            "d {} {}",
-            dir.mtime(),
-            current_path
-        )?;
+        current_path.push(component(dir.path()));
+        let mut output = self.output.borrow_mut();
+        write!(output, "d {} ", dir.mtime())?;
+        current_path.println(output.deref_mut())?;
         Ok(true)
     }
     fn on_directory_exit(
         &self,
-        current_path: &mut arx::PathBuf,
+        current_path: &mut LightPath,
         _dir: &arx::Dir,
     ) -> Result<(), ArxError> {
         current_path.pop();
         Ok(())
     }
-    fn on_file(
+    fn on_file(&self, current_path: &mut LightPath, file: &arx::FileEntry) -> Result<(), ArxError> {
+        let mut output = self.output.borrow_mut();
+        write!(output, "f {} {} ", file.mtime(), file.size().into_u64())?;
+        Ok(current_path.println2(file.path(), output.deref_mut())?)
+    }
+    fn on_link(&self, current_path: &mut LightPath, link: &arx::Link) -> Result<(), ArxError> {
+        let mut output = self.output.borrow_mut();
+        write!(output, "l {} ", link.mtime())?;
+        current_path.print2(link.path(), output.deref_mut())?;
+        output.write_all(b"->")?;
+        output.write_all(link.target())?;
+        Ok(output.write_all(b"\n")?)
+    }
+    fn on_special(
         &self,
-        current_path: &mut arx::PathBuf,
-        file: &arx::FileEntry,
+        current_path: &mut LightPath,
+        special: &arx::Special,
     ) -> Result<(), ArxError> {
-        current_path.push(String::from_utf8_lossy(file.path()).as_ref());
-        writeln!(
-            self.output.borrow_mut(),
-            "f {} {} {}",
-            file.mtime(),
-            file.size().into_u64(),
-            current_path
-        )?;
-        current_path.pop();
-        Ok(())
+        let mut output = self.output.borrow_mut();
+        write!(output, "s {} {} ", special.mtime(), special.kind())?;
+        Ok(current_path.println2(special.path(), output.deref_mut())?)
     }
-    fn on_link(&self, current_path: &mut arx::PathBuf, link: &arx::Link) -> Result<(), ArxError> {
-        current_path.push(String::from_utf8_lossy(link.path()).as_ref());
-        let target: PathBuf = String::from_utf8_lossy(link.target()).as_ref().into();
-        writeln!(
-            self.output.borrow_mut(),
-            "l {} {}->{}",
-            link.mtime(),
-            current_path,
-            target.display()
-        )?;
-        current_path.pop();
-        Ok(())
+    fn on_whiteout(
+        &self,
+        current_path: &mut LightPath,
+        whiteout: &arx::Whiteout,
+    ) -> Result<(), ArxError> {
+        let mut output = self.output.borrow_mut();
+        write!(output, "w {} ", whiteout.mtime())?;
+        Ok(current_path.println2(whiteout.path(), output.deref_mut())?)
     }
 }
 
@@ -1,10 +1,18 @@
 mod create;
+mod diff_create;
 mod dump;
+mod edit;
 mod extract;
+mod fsck;
+mod info;
 mod light_path;
 mod list;
+mod merge;
 #[cfg(all(not(windows), feature = "fuse"))]
 mod mount;
+mod repack;
+mod update;
+mod verify;
 
 use anyhow::Result;
 use clap::{CommandFactory, Parser};
@@ -59,11 +67,19 @@ struct Cli {
         value_parser([
             "",
             "create",
+            "diff-create",
             "list",
+            "merge",
             "dump",
+            "edit",
             "extract",
+            "fsck",
+            "info",
             #[cfg(all(not(windows), feature = "fuse"))]
-            "mount"
+            "mount",
+            "repack",
+            "update",
+            "verify"
         ])
     )]
     generate_man_page: Option<String>,
@@ -80,18 +96,42 @@ enum Commands {
     #[command(arg_required_else_help = true)]
     Create(create::Options),
 
+    #[command(arg_required_else_help = true)]
+    DiffCreate(diff_create::Options),
+
     #[command(arg_required_else_help = true)]
     List(list::Options),
 
+    #[command(arg_required_else_help = true)]
+    Merge(merge::Options),
+
     #[command(arg_required_else_help = true)]
     Dump(dump::Options),
 
+    #[command(arg_required_else_help = true)]
+    Edit(edit::Options),
+
     #[command(arg_required_else_help = true)]
     Extract(extract::Options),
 
+    #[command(arg_required_else_help = true)]
+    Fsck(fsck::Options),
+
+    #[command(arg_required_else_help = true)]
+    Info(info::Options),
+
     #[cfg(all(not(windows), feature = "fuse"))]
     #[command(arg_required_else_help = true)]
     Mount(mount::Options),
+
+    #[command(arg_required_else_help = true)]
+    Repack(repack::Options),
+
+    #[command(arg_required_else_help = true)]
+    Update(update::Options),
+
+    #[command(arg_required_else_help = true)]
+    Verify(verify::Options),
 }
 
 fn configure_log(verbose: u8) {
@@ -130,11 +170,19 @@ fn run() -> Result<()> {
         let command = match what.as_str() {
             "" => Cli::command(),
             "create" => create::Options::command(),
+            "diff-create" => diff_create::Options::command(),
             "list" => list::Options::command(),
+            "merge" => merge::Options::command(),
             "dump" => dump::Options::command(),
+            "edit" => edit::Options::command(),
             "extract" => extract::Options::command(),
+            "fsck" => fsck::Options::command(),
+            "info" => info::Options::command(),
             #[cfg(all(not(windows), feature = "fuse"))]
             "mount" => mount::Options::command(),
+            "repack" => repack::Options::command(),
+            "update" => update::Options::command(),
+            "verify" => verify::Options::command(),
             _ => return Ok(Cli::command().print_help()?),
         };
         let man = clap_mangen::Man::new(command);
@@ -159,11 +207,19 @@ fn run() -> Result<()> {
         None => Ok(Cli::command().print_help()?),
         Some(c) => match c {
             Commands::Create(options) => create::create(options),
+            Commands::DiffCreate(options) => diff_create::diff_create(options),
             Commands::List(options) => Ok(list::list(options)?),
+            Commands::Merge(options) => merge::merge(options),
             Commands::Dump(options) => Ok(dump::dump(options)?),
+            Commands::Edit(options) => edit::edit(options),
             Commands::Extract(options) => Ok(extract::extract(options)?),
+            Commands::Fsck(options) => fsck::fsck(options),
+            Commands::Info(options) => info::info(options),
             #[cfg(all(not(windows), feature = "fuse"))]
             Commands::Mount(options) => Ok(mount::mount(options)?),
+            Commands::Repack(options) => repack::repack(options),
+            Commands::Update(options) => update::update(options),
+            Commands::Verify(options) => verify::verify(options),
         },
     }
 }
@@ -0,0 +1,135 @@
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, ValueHint};
+use log::info;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::create::check_output_path_writable;
+
+/// A prefix given to the entries of an input archive.
+#[derive(Clone, Debug)]
+struct Prefix {
+    input: String,
+    prefix: arx::PathBuf,
+}
+
+impl Prefix {
+    /// Tell if the prefix is given for `infile`, by its path or its name without extension.
+    fn matches(&self, infile: &std::path::Path) -> bool {
+        std::path::Path::new(&self.input) == infile
+            || infile.file_stem() == Some(std::ffi::OsStr::new(&self.input))
+    }
+}
+
+fn prefix_parser(s: &str) -> Result<Prefix> {
+    let (input, prefix) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Prefix must be of the form INPUT=PREFIX"))?;
+    let normalized = arx::Path::new(prefix.trim_matches('/')).normalize();
+    if normalized.starts_with("..") {
+        return Err(anyhow!(
+            "Invalid prefix {prefix:?}, it goes out of the archive"
+        ));
+    }
+    Ok(Prefix {
+        input: input.into(),
+        prefix: normalized,
+    })
+}
+
+/// Merge several archives into one.
+///
+/// Entries are read from the input archives and stored again in the new archive.
+/// Directories present in several archives are merged.
+///
+/// Unless the new archive is created in one file or `--compression` is given, contents are not
+/// stored again: the new archive references the content packs of the input archives, which
+/// must stay at the same location.
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// File path of the archive to create.
+    #[arg(short, long, value_parser, value_hint=ValueHint::FilePath)]
+    outfile: jbk::Utf8PathBuf,
+
+    /// Archives to merge
+    #[arg(value_parser, required = true, value_hint=ValueHint::FilePath)]
+    infiles: Vec<PathBuf>,
+
+    /// Store the entries of an input archive under a prefix, as INPUT=PREFIX
+    ///
+    /// INPUT is the path of the input archive as given, or its name without extension
+    /// (`--prefix a=comp/a` for `a.arx`). Can be given several times.
+    #[arg(long, value_parser=prefix_parser)]
+    prefix: Vec<Prefix>,
+
+    /// How to handle entries present in several archives
+    #[arg(long, value_enum, default_value_t)]
+    on_conflict: arx::create::Conflict,
+
+    #[command(flatten)]
+    concat_mode: Option<jbk::cmd_utils::ConcatMode>,
+
+    /// Set compression algorithm to use (zstd by default)
+    ///
+    /// Contents are then compressed again instead of referencing the input content packs.
+    #[arg(short,long, value_parser=jbk::cmd_utils::compression_arg_parser, required=false)]
+    compression: Option<jbk::creator::Compression>,
+
+    /// Overwrite existing archive file
+    #[arg(short, long)]
+    force: bool,
+
+    #[arg(from_global)]
+    verbose: u8,
+}
+
+impl Options {
+    /// The prefix of the input archive `infile` (empty if none is given).
+    fn prefix_of(&self, infile: &std::path::Path) -> arx::PathBuf {
+        self.prefix
+            .iter()
+            .find(|p| p.matches(infile))
+            .map(|p| p.prefix.clone())
+            .unwrap_or_default()
+    }
+}
+
+pub fn merge(options: Options) -> Result<()> {
+    check_output_path_writable(options.outfile.as_std_path(), options.force)?;
+    for prefix in &options.prefix {
+        if !options.infiles.iter().any(|infile| prefix.matches(infile)) {
+            return Err(anyhow!("Prefix given for unknown input {}", prefix.input));
+        }
+    }
+    let inputs = options
+        .infiles
+        .iter()
+        .map(|infile| {
+            let arx = arx::Arx::new(infile).with_context(|| format!("Opening {infile:?}"))?;
+            Ok((arx, infile.clone(), options.prefix_of(infile)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    info!("Merging {:?} into {:?}", options.infiles, options.outfile);
+
+    // Contents are compressed again only if a compression is given.
+    let reuse_packs = options.compression.is_none();
+    let mut creator = arx::create::SimpleCreator::new(
+        &options.outfile,
+        match options.concat_mode {
+            None => jbk::creator::ConcatMode::OneFile,
+            Some(e) => e.into(),
+        },
+        Arc::new(()),
+        Rc::new(()),
+        options
+            .compression
+            .unwrap_or_else(jbk::creator::Compression::zstd),
+    )?;
+    let mut adder = arx::create::ArxAdder::new(&mut creator, options.on_conflict);
+    // Packs are not referenced if the archive is created in one file.
+    adder.set_reuse_packs(reuse_packs);
+    adder.add_arxs(&inputs)?;
+    creator.finalize()?;
+    Ok(())
+}
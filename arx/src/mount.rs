@@ -14,6 +14,8 @@ pub struct StatCounter {
     nb_opendir: u64,
     nb_readdir: u64,
     nb_releasedir: u64,
+    nb_getxattr: u64,
+    nb_listxattr: u64,
 }
 
 impl StatCounter {
@@ -28,6 +30,8 @@ impl StatCounter {
             nb_opendir: 0,
             nb_readdir: 0,
             nb_releasedir: 0,
+            nb_getxattr: 0,
+            nb_listxattr: 0,
         }
     }
 }
@@ -68,6 +72,14 @@ impl arx::Stats for StatCounter {
     fn releasedir(&mut self) {
         self.nb_releasedir += 1;
     }
+
+    fn getxattr(&mut self) {
+        self.nb_getxattr += 1;
+    }
+
+    fn listxattr(&mut self) {
+        self.nb_listxattr += 1;
+    }
 }
 
 impl Default for StatCounter {
@@ -87,6 +99,8 @@ impl std::fmt::Display for StatCounter {
         writeln!(f, "nb_opendir: {}", self.nb_opendir)?;
         writeln!(f, "nb_readdir: {}", self.nb_readdir)?;
         writeln!(f, "nb_releasedir: {}", self.nb_releasedir)?;
+        writeln!(f, "nb_getxattr: {}", self.nb_getxattr)?;
+        writeln!(f, "nb_listxattr: {}", self.nb_listxattr)?;
         Ok(())
     }
 }
@@ -118,6 +132,14 @@ pub struct Options {
     #[arg(short, long)]
     foreground: bool,
 
+    /// Don't expose setuid, setgid and sticky bits of the entries
+    #[arg(long)]
+    strip_special_bits: bool,
+
+    /// Report stored numeric owner and group ids instead of mapping names to local ids
+    #[arg(long)]
+    numeric_owner: bool,
+
     #[arg(from_global)]
     verbose: u8,
 }
@@ -140,7 +162,9 @@ pub fn mount(options: Options) -> anyhow::Result<()> {
         }
     };
 
-    let arxfs = arx::ArxFs::new_with_stats(arx, root_range, &mut stats)?;
+    let arxfs = arx::ArxFs::new_with_stats(arx, root_range, &mut stats)?
+        .strip_special_bits(options.strip_special_bits)
+        .numeric_owner(options.numeric_owner);
 
     let mut abs_path = std::env::current_dir().unwrap();
     abs_path = abs_path.join(options.infile);
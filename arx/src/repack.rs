@@ -0,0 +1,77 @@
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, ValueHint};
+use log::info;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::create::{check_output_path_writable, ProgressBar};
+
+/// Store an existing archive again, with another compression or layout.
+///
+/// The content of each file is read from the input archive and compressed again.
+/// Entries and their metadata, as the archive-level metadata, are kept unchanged.
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// Archive to repack
+    #[arg(value_parser, value_hint=ValueHint::FilePath)]
+    infile: PathBuf,
+
+    /// File path of the archive to create.
+    #[arg(short, long, value_parser, value_hint=ValueHint::FilePath)]
+    outfile: jbk::Utf8PathBuf,
+
+    #[command(flatten)]
+    concat_mode: Option<jbk::cmd_utils::ConcatMode>,
+
+    /// Set compression algorithm to use
+    #[arg(short,long, value_parser=jbk::cmd_utils::compression_arg_parser, required=false, default_value = "zstd")]
+    compression: jbk::creator::Compression,
+
+    /// Print a progression of the creation
+    #[arg(long, default_value_t = false, action)]
+    progress: bool,
+
+    /// Overwrite existing archive file
+    #[arg(short, long)]
+    force: bool,
+
+    #[arg(from_global)]
+    verbose: u8,
+}
+
+pub fn repack(options: Options) -> Result<()> {
+    check_output_path_writable(options.outfile.as_std_path(), options.force)?;
+    let arx =
+        arx::Arx::new(&options.infile).with_context(|| format!("Opening {:?}", options.infile))?;
+    // The input archive is read while the output one is written.
+    if options.outfile.exists()
+        && std::fs::canonicalize(&options.outfile)? == std::fs::canonicalize(&options.infile)?
+    {
+        return Err(anyhow!("Cannot repack {:?} into itself", options.infile));
+    }
+    info!("Repacking {:?} into {:?}", options.infile, options.outfile);
+
+    let jbk_progress: Arc<dyn jbk::creator::Progress> = if options.progress {
+        Arc::new(ProgressBar::new())
+    } else {
+        Arc::new(())
+    };
+    let mut creator = arx::create::SimpleCreator::new(
+        &options.outfile,
+        match options.concat_mode {
+            None => jbk::creator::ConcatMode::OneFile,
+            Some(e) => e.into(),
+        },
+        jbk_progress,
+        Rc::new(()),
+        options.compression,
+    )?;
+    for (key, value) in arx.metadata()? {
+        creator.set_metadata(key, value);
+    }
+    let mut adder = arx::create::ArxAdder::new(&mut creator, arx::create::Conflict::Error);
+    adder.add_arx(&arx, &options.infile, arx::Path::new(""))?;
+    creator.finalize()?;
+    Ok(())
+}
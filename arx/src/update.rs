@@ -0,0 +1,200 @@
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, ValueHint};
+use log::info;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::create::{
+    add_from_path, check_input_paths_exist, check_output_path_writable, jobs, print_skipped,
+    ExcludeOptions,
+};
+
+/// Options of the commands adding input files compared with a base archive
+/// (`arx update` and `arx diff-create`).
+#[derive(clap::Args, Debug)]
+pub(crate) struct InputOptions {
+    /// The base archive to compare the input files with
+    #[arg(long, value_parser, value_hint=ValueHint::FilePath)]
+    base: PathBuf,
+
+    /// Input files/directories
+    #[arg(value_parser, required = true, value_hint=ValueHint::AnyPath)]
+    infiles: Vec<PathBuf>,
+
+    /// Keep parents of the input paths (as `arx create --keep-parents`)
+    #[arg(short = 'k', long, help_heading = "Input options")]
+    keep_parents: bool,
+
+    /// Input directories are considered as root directory (as `arx create --dir-as-root`)
+    #[arg(long, help_heading = "Input options")]
+    dir_as_root: bool,
+
+    /// Follow symbolic link found in the input files
+    #[arg(long, help_heading = "Input options")]
+    follow_symlink: bool,
+
+    #[command(flatten)]
+    path_transforms: arx::cmd_utils::PathTransforms,
+
+    /// What to do when a file cannot be read (as `arx create --on-error`)
+    #[arg(long, value_enum, default_value_t = arx::create::OnError::Abort, help_heading = "Input options")]
+    on_error: arx::create::OnError,
+
+    #[command(flatten)]
+    exclude: ExcludeOptions,
+
+    /// Number of threads used to read the input files (default to the number of available CPUs)
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Compare the content digest of files instead of their size and modification time
+    #[arg(long)]
+    checksum: bool,
+
+    #[command(flatten)]
+    pub(crate) concat_mode: Option<jbk::cmd_utils::ConcatMode>,
+
+    /// Set compression algorithm to use
+    #[arg(short,long, value_parser=jbk::cmd_utils::compression_arg_parser, required=false, default_value = "zstd")]
+    compression: jbk::creator::Compression,
+
+    /// Overwrite existing archive file
+    #[arg(short, long)]
+    pub(crate) force: bool,
+}
+
+impl InputOptions {
+    pub(crate) fn base(&self) -> &Path {
+        &self.base
+    }
+
+    /// Create `outfile` and add the input files to it, comparing them with the base archive.
+    ///
+    /// With `patch`, unchanged entries are skipped and whiteouts can be added.
+    /// Return the creator, to be finalized by the caller, and the changes since the base archive.
+    pub(crate) fn add_inputs(
+        self,
+        outfile: &jbk::Utf8Path,
+        concat_mode: jbk::creator::ConcatMode,
+        patch: bool,
+    ) -> Result<(arx::create::SimpleCreator, arx::create::ChangeReport)> {
+        check_input_paths_exist(&self.infiles)?;
+        let filter = self.exclude.filter()?;
+        let mut base = arx::create::BaseArchive::open(
+            &self.base,
+            if self.checksum {
+                arx::create::ChangeDetection::Checksum
+            } else {
+                arx::create::ChangeDetection::Metadata
+            },
+        )
+        .with_context(|| format!("Opening {:?}", self.base))?;
+
+        let mut creator = arx::create::SimpleCreator::new(
+            outfile,
+            concat_mode,
+            Arc::new(()),
+            Rc::new(()),
+            self.compression,
+        )?;
+        if patch {
+            creator.enable_whiteouts();
+        }
+        let path_transforms = arx::create::PathTransforms::from(self.path_transforms);
+        if !path_transforms.is_empty() {
+            creator.set_path_transforms(path_transforms);
+        }
+        {
+            let mut adder = arx::create::FsAdder::new(
+                &mut creator,
+                self.keep_parents,
+                self.follow_symlink,
+                self.dir_as_root,
+                arx::create::MountPoints::Cross,
+            );
+            adder.set_base_archive(&mut base);
+            adder.set_skip_unchanged(patch);
+            adder.set_on_error(self.on_error);
+            adder.set_jobs(jobs(self.jobs))?;
+            for infile in &self.infiles {
+                add_from_path(&mut adder, infile, true, &filter)?;
+            }
+            print_skipped(adder.skipped());
+        }
+
+        let report = base.finish()?;
+        Ok((creator, report))
+    }
+}
+
+/// Create a new version of an archive from a directory, comparing it with the previous version.
+///
+/// Files are compared with the base archive using their size and modification time
+/// (or their content digest with `--checksum`).
+/// Print a summary of the changes, or one line per changed entry with `--list`,
+/// in the form `<change>\t<path>` where change is `added`, `modified` or `removed`.
+///
+/// The content of unchanged files is not stored again: the new archive references the content
+/// packs of the base archive, which must stay at the same location. So the new archive
+/// is created in two files by default, and cannot be created in only one file.
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// File path of the archive to create.
+    #[arg(short, long, value_parser, value_hint=ValueHint::FilePath)]
+    outfile: jbk::Utf8PathBuf,
+
+    #[command(flatten)]
+    input: InputOptions,
+
+    /// Print all changed entries instead of a summary
+    #[arg(long)]
+    list: bool,
+
+    #[arg(from_global)]
+    verbose: u8,
+}
+
+pub fn update(mut options: Options) -> Result<()> {
+    check_output_path_writable(options.outfile.as_std_path(), options.input.force)?;
+    info!(
+        "Updating archive {:?} into {:?}",
+        options.input.base(),
+        options.outfile
+    );
+    let concat_mode = match options.input.concat_mode.take() {
+        None => jbk::creator::ConcatMode::TwoFiles,
+        Some(e) => e.into(),
+    };
+    if let jbk::creator::ConcatMode::OneFile = concat_mode {
+        return Err(anyhow!(
+            "arx update references the content packs of the base archive, it cannot create only one file"
+        ));
+    }
+    let (creator, report) = options
+        .input
+        .add_inputs(&options.outfile, concat_mode, false)?;
+    creator.finalize()?;
+
+    let stdout = std::io::stdout();
+    let mut handle = std::io::BufWriter::new(stdout.lock());
+    if options.list {
+        for (path, change) in &report.entries {
+            if *change != arx::create::Change::Unchanged {
+                writeln!(handle, "{change}\t{path}")?;
+            }
+        }
+    } else {
+        writeln!(
+            handle,
+            "{} unchanged, {} modified, {} added, {} removed",
+            report.count(arx::create::Change::Unchanged),
+            report.count(arx::create::Change::Modified),
+            report.count(arx::create::Change::Added),
+            report.count(arx::create::Change::Removed)
+        )?;
+    }
+    handle.flush()?;
+    Ok(())
+}
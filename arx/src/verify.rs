@@ -0,0 +1,47 @@
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, ValueHint};
+use log::info;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Check the content of the files in an archive against their stored digest.
+///
+/// Print one line per problematic file, in the form `<status>\t<path>`.
+/// Status is one of `mismatch` (content is corrupted), `missing` (content pack is not available),
+/// `unchecked` (no digest stored for this file) or `ok`.
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// Archive to verify
+    #[arg(value_parser, value_hint=ValueHint::FilePath)]
+    infile: PathBuf,
+
+    /// Also print the files which are valid or have no digest
+    #[arg(long, action)]
+    all: bool,
+
+    #[arg(from_global)]
+    verbose: u8,
+}
+
+pub fn verify(options: Options) -> Result<()> {
+    info!("Verify archive {:?}", options.infile);
+    let arx =
+        arx::Arx::new(&options.infile).with_context(|| format!("Opening {:?}", options.infile))?;
+    let report = arx.verify()?;
+    let stdout = std::io::stdout();
+    let mut handle = std::io::BufWriter::new(stdout.lock());
+    for (path, status) in &report.entries {
+        if options.all || status.is_error() {
+            writeln!(handle, "{status}\t{path}")?;
+        }
+    }
+    handle.flush()?;
+    if report.is_ok() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{} file(s) failed verification",
+            report.errors().count()
+        ))
+    }
+}
@@ -233,5 +233,991 @@ fn test_crate_existant_output(source_dir: SharedTestDir) -> Result {
     Ok(())
 }
 
+#[test]
+fn test_crate_verify(source_dir: SharedTestDir) -> Result {
+    let source_dir = source_dir.path();
+    temp_arx!(arx_file);
+    cmd!("arx", "create", "--outfile", &arx_file, source_dir).check();
+    cmd!("arx", "verify", &arx_file).check_output(Some("^$"), Some(""));
+    cmd!("arx", "verify", &arx_file, "--all").check_output(Some("^(ok\t.+\n)+$"), Some(""));
+    Ok(())
+}
+
+#[test]
+fn test_crate_fsck(source_dir: SharedTestDir) -> Result {
+    let source_dir = source_dir.path();
+    temp_arx!(arx_file);
+    cmd!("arx", "create", "--outfile", &arx_file, source_dir).check();
+    cmd!("arx", "fsck", &arx_file).check_output(Some("^$"), Some(""));
+    Ok(())
+}
+
+/// Replace all the occurrences of `from` by `to` (of the same size) in the file at `path`.
+fn patch_file(path: &Path, from: &[u8], to: &[u8]) -> std::io::Result<()> {
+    assert_eq!(from.len(), to.len());
+    let mut content = std::fs::read(path)?;
+    let mut found = false;
+    for start in 0..=content.len() - from.len() {
+        if &content[start..start + from.len()] == from {
+            content[start..start + from.len()].copy_from_slice(to);
+            found = true;
+        }
+    }
+    assert!(found, "{from:?} should be in {path:?}");
+    std::fs::write(path, content)
+}
+
+#[test]
+fn test_crate_verify_corrupted() -> Result {
+    let source_dir = temp_tree!(0, {
+        text "file.txt" 10,
+        text "other.txt" 10
+    });
+    std::fs::write(join!(source_dir / "file.txt"), "Some content to corrupt")?;
+    temp_arx!(arx_file);
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        source_dir.path(),
+        "--dir-as-root",
+        "--compression",
+        "none"
+    )
+    .check_output(Some(""), Some(""));
+    patch_file(&arx_file, b"content to corrupt", b"content is corrupt")?;
+    cmd!("arx", "verify", &arx_file).check_fail(
+        "^mismatch\tfile.txt\n$",
+        "1 file\\(s\\) failed verification",
+    );
+    cmd!("arx", "verify", &arx_file, "--all").check_fail(
+        "^mismatch\tfile.txt\nok\tother.txt\n$",
+        "1 file\\(s\\) failed verification",
+    );
+    Ok(())
+}
+
+#[test]
+fn test_crate_verify_missing_pack(source_dir: SharedTestDir) -> Result {
+    let source_dir = source_dir.path();
+    temp_arx!(arx_file);
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        source_dir,
+        "--two-files"
+    )
+    .check_output(Some(""), Some(""));
+    // The content pack is the other file created next to the archive.
+    let mut content_packs = vec![];
+    for entry in std::fs::read_dir(arx_file.parent().unwrap())? {
+        let path = entry?.path();
+        if path != arx_file {
+            content_packs.push(path);
+        }
+    }
+    assert_eq!(content_packs.len(), 1);
+    cmd!("arx", "verify", &arx_file).check_output(Some("^$"), Some(""));
+    std::fs::remove_file(&content_packs[0])?;
+    cmd!("arx", "verify", &arx_file).check_fail(
+        "^(missing\t.+\n)+$",
+        "[0-9]+ file\\(s\\) failed verification",
+    );
+    // A missing content pack is not a structural problem.
+    cmd!("arx", "fsck", &arx_file).check_output(Some("^$"), Some(""));
+    Ok(())
+}
+
+#[test]
+fn test_crate_fsck_broken_entry_store() -> Result {
+    let source_dir = temp_tree!(0, {
+        text "x_q" 10
+    });
+    temp_arx!(arx_file);
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        source_dir.path(),
+        "--dir-as-root",
+        "--compression",
+        "none"
+    )
+    .check_output(Some(""), Some(""));
+    // Rename the entry so it contains a `/`.
+    patch_file(&arx_file, b"x_q", b"x/q")?;
+    cmd!("arx", "fsck", &arx_file).check_fail(
+        "^entry [0-9]+ \\(x/q\\): invalid name\n$",
+        "1 problem\\(s\\) found",
+    );
+    Ok(())
+}
+
+#[test]
+fn test_crate_metadata(source_dir: SharedTestDir) -> Result {
+    let source_dir = source_dir.path();
+    temp_arx!(arx_file);
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        source_dir,
+        "--meta",
+        "build_id=42",
+        "--meta",
+        "description=Some test data"
+    )
+    .check();
+    cmd!("arx", "info", &arx_file).check_output(
+        Some("Metadata:\n  build_id: 42\n  description: Some test data\n$"),
+        Some(""),
+    );
+    Ok(())
+}
+
+#[test]
+fn test_crate_exclude() -> Result {
+    let source_dir = temp_tree!(0, {
+        dir "src" {
+            text "main.rs" 100,
+            text "main.o" 100,
+            text "keep.o" 100,
+            dir "build" {
+                text "out" 100
+            }
+        },
+        dir "build" {
+            text "out" 100
+        },
+        dir ".git" {
+            text "HEAD" 10
+        },
+        text "notes.tmp" 100
+    });
+    std::fs::write(join!(source_dir / ".arxignore"), "*.o\n!keep.o\n")?;
+    // Ignore files may not be valid utf-8.
+    std::fs::write(
+        join!(source_dir / "src" / ".arxignore"),
+        b"# caf\xe9\r\n/build/\n",
+    )?;
+    temp_arx!(arx_file);
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        source_dir.path(),
+        "--dir-as-root",
+        "--exclude-vcs",
+        "--exclude",
+        "*.tmp",
+        "--ignore-files"
+    )
+    .check_output(Some(""), Some(""));
+    let arx_content = run!(output, "arx", "list", &arx_file);
+    let arx_content = String::from_utf8_lossy(&arx_content.stdout);
+    let mut arx_content = arx_content.lines().collect::<Vec<_>>();
+    arx_content.sort();
+    assert_eq!(
+        arx_content,
+        [
+            ".arxignore",
+            "build",
+            "build/out",
+            "src",
+            "src/.arxignore",
+            "src/keep.o",
+            "src/main.rs"
+        ]
+    );
+
+    // Exclude patterns also apply to the file lists.
+    let file_list = join!(source_dir / "file_list");
+    std::fs::write(
+        &file_list,
+        ["src", "src/main.rs", "src/main.o", "notes.tmp"]
+            .map(|p| format!("{}\n", join!(source_dir / p).display()))
+            .concat(),
+    )?;
+    temp_arx!(arx_file);
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-L",
+        &file_list,
+        "--exclude",
+        "*.tmp",
+        "--exclude",
+        "*.o"
+    )
+    .check_output(Some(""), Some(""));
+    let arx_content = run!(output, "arx", "list", &arx_file);
+    let arx_content = String::from_utf8_lossy(&arx_content.stdout);
+    assert!(arx_content.lines().any(|l| l.ends_with("src/main.rs")));
+    assert!(!arx_content
+        .lines()
+        .any(|l| l.ends_with("main.o") || l.ends_with("notes.tmp")));
+    Ok(())
+}
+
+#[test]
+fn test_crate_transform() -> Result {
+    let source_dir = temp_tree!(0, {
+        dir "src" {
+            text "main.rs" 100,
+            text "lib.rs" 100
+        },
+        text "README.TXT" 100
+    });
+    temp_arx!(arx_file);
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        source_dir.path(),
+        "--dir-as-root",
+        "--transform",
+        "s/^src/source/",
+        "--transform",
+        r"s/\.txt$/.md/i"
+    )
+    .check_output(Some(""), Some(""));
+    let arx_content = run!(output, "arx", "list", &arx_file);
+    let arx_content = String::from_utf8_lossy(&arx_content.stdout);
+    let mut arx_content = arx_content.lines().collect::<Vec<_>>();
+    arx_content.sort();
+    assert_eq!(
+        arx_content,
+        ["README.md", "source", "source/lib.rs", "source/main.rs"]
+    );
+
+    temp_arx!(conflict_arx_file, "conflict.arx");
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &conflict_arx_file,
+        source_dir.path(),
+        "--dir-as-root",
+        "--transform",
+        "s/(main|lib)/code/"
+    )
+    .check_fail("", ".*are both transformed to src/code.rs.*");
+    Ok(())
+}
+
+#[test]
+fn test_crate_no_compress() -> Result {
+    let source_dir = temp_tree!(0, {
+        text "big.txt" 10_000
+    });
+    temp_arx!(arx_file);
+    cmd!("arx", "create", "--outfile", &arx_file, source_dir.path())
+        .check_output(Some(""), Some(""));
+    temp_arx!(stored_arx_file, "stored.arx");
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &stored_arx_file,
+        source_dir.path(),
+        "--no-compress",
+        "*.txt"
+    )
+    .check_output(Some(""), Some(""));
+    let compressed_size = std::fs::metadata(&arx_file)?.len();
+    let stored_size = std::fs::metadata(&stored_arx_file)?.len();
+    assert!(compressed_size < stored_size);
+    Ok(())
+}
+
+#[test]
+fn test_crate_jobs(source_dir: SharedTestDir) -> Result {
+    temp_arx!(arx_file);
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        source_dir.path(),
+        "--jobs",
+        "1"
+    )
+    .check_output(Some(""), Some(""));
+    temp_arx!(parallel_arx_file, "parallel.arx");
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &parallel_arx_file,
+        source_dir.path(),
+        "--jobs",
+        "4"
+    )
+    .check_output(Some(""), Some(""));
+    let arx_content = run!(output, "arx", "list", &arx_file);
+    let parallel_arx_content = run!(output, "arx", "list", &parallel_arx_file);
+    assert_eq!(arx_content.stdout, parallel_arx_content.stdout);
+    cmd!("arx", "verify", &parallel_arx_file).check();
+    Ok(())
+}
+
+#[test]
+fn test_crate_one_file_system(source_dir: SharedTestDir) -> Result {
+    temp_arx!(arx_file);
+    cmd!("arx", "create", "--outfile", &arx_file, source_dir.path()).check();
+    temp_arx!(one_fs_arx_file, "one_fs.arx");
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &one_fs_arx_file,
+        source_dir.path(),
+        "--one-file-system",
+        "--keep-mount-points"
+    )
+    .check_output(Some(""), Some(""));
+    let arx_content = run!(output, "arx", "list", &arx_file);
+    let one_fs_arx_content = run!(output, "arx", "list", &one_fs_arx_file);
+    assert_eq!(arx_content.stdout, one_fs_arx_content.stdout);
+
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &one_fs_arx_file,
+        source_dir.path(),
+        "--force",
+        "--keep-mount-points"
+    )
+    .check_fail("", ".*--one-file-system.*");
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_crate_on_error() -> Result {
+    let source_dir = temp_tree!(0, {
+        text "file.txt" 100
+    });
+    std::os::unix::fs::symlink("missing", join!(source_dir / "broken"))?;
+    temp_arx!(arx_file);
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        source_dir.path(),
+        "--dir-as-root",
+        "--follow-symlink"
+    )
+    .check_fail("", "No such file or directory");
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        source_dir.path(),
+        "--dir-as-root",
+        "--follow-symlink",
+        "--force",
+        "--on-error",
+        "skip"
+    )
+    .check_output(
+        Some(""),
+        Some("1 file\\(s\\) skipped because of errors:\n  .*broken: No such file or directory.*\n"),
+    );
+    cmd!("arx", "list", &arx_file).check_output(Some("file.txt\n"), Some(""));
+    Ok(())
+}
+
+#[test]
+fn test_update() -> Result {
+    let source_dir = temp_tree!(0, {
+        text "same.txt" 100,
+        text "modified.txt" 100,
+        text "removed.txt" 100
+    });
+    temp_arx!(base_arx_file, "base.arx");
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &base_arx_file,
+        source_dir.path(),
+        "--dir-as-root"
+    )
+    .check();
+    std::fs::write(join!(source_dir / "modified.txt"), "new content")?;
+    std::fs::remove_file(join!(source_dir / "removed.txt"))?;
+    std::fs::write(join!(source_dir / "added.txt"), "added content")?;
+    temp_arx!(arx_file);
+    cmd!(
+        "arx",
+        "update",
+        "--outfile",
+        &arx_file,
+        "--base",
+        &base_arx_file,
+        source_dir.path(),
+        "--dir-as-root"
+    )
+    .check_output(
+        Some("^\\d+ unchanged, 1 modified, 1 added, 1 removed\n$"),
+        Some(""),
+    );
+    let list = run!(
+        output,
+        "arx",
+        "update",
+        "--outfile",
+        &arx_file,
+        "--base",
+        &base_arx_file,
+        source_dir.path(),
+        "--dir-as-root",
+        "--force",
+        "--list"
+    );
+    let list = String::from_utf8_lossy(&list.stdout);
+    let mut changes = list.lines().collect::<Vec<_>>();
+    changes.sort();
+    assert_eq!(
+        changes,
+        [
+            "added\tadded.txt",
+            "modified\tmodified.txt",
+            "removed\tremoved.txt"
+        ]
+    );
+    cmd!("arx", "list", &arx_file)
+        .check_output(Some("^added.txt\nmodified.txt\nsame.txt\n$"), Some(""));
+
+    // Unchanged content is read from the content pack of the base archive.
+    let extract_dir = tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR"))?;
+    cmd!("arx", "extract", &arx_file, "-C", extract_dir.path()).check_output(Some(""), Some(""));
+    for name in ["same.txt", "modified.txt", "added.txt"] {
+        assert_eq!(
+            std::fs::read(join!(extract_dir / name))?,
+            std::fs::read(join!(source_dir / name))?,
+            "{name}"
+        );
+    }
+
+    cmd!(
+        "arx",
+        "update",
+        "--outfile",
+        &arx_file,
+        "--base",
+        &base_arx_file,
+        source_dir.path(),
+        "--dir-as-root",
+        "--force",
+        "-1"
+    )
+    .check_fail("", ".*references the content packs of the base archive.*");
+
+    cmd!(
+        "arx",
+        "update",
+        "--outfile",
+        &arx_file,
+        "--base",
+        &base_arx_file,
+        source_dir.path(),
+        "--dir-as-root",
+        "--force",
+        "--exclude",
+        "same.txt"
+    )
+    .check_output(
+        Some("^\\d+ unchanged, 1 modified, 1 added, 2 removed\n$"),
+        Some(""),
+    );
+    cmd!("arx", "list", &arx_file).check_output(Some("^added.txt\nmodified.txt\n$"), Some(""));
+    Ok(())
+}
+
+#[test]
+fn test_update_transform() -> Result {
+    let source_dir = temp_tree!(0, {
+        dir "src" {
+            text "same.rs" 100,
+            text "modified.rs" 100,
+            text "removed.rs" 100
+        }
+    });
+    temp_arx!(base_arx_file, "base.arx");
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &base_arx_file,
+        source_dir.path(),
+        "--dir-as-root",
+        "--transform",
+        "s,^,root/,"
+    )
+    .check();
+    std::fs::write(join!(source_dir / "src" / "modified.rs"), "new content")?;
+    std::fs::remove_file(join!(source_dir / "src" / "removed.rs"))?;
+
+    // Entries are compared with the base entries at their transformed path.
+    temp_arx!(arx_file);
+    let list = run!(
+        output,
+        "arx",
+        "update",
+        "--outfile",
+        &arx_file,
+        "--base",
+        &base_arx_file,
+        source_dir.path(),
+        "--dir-as-root",
+        "--transform",
+        "s,^,root/,",
+        "--list"
+    );
+    let list = String::from_utf8_lossy(&list.stdout);
+    let mut changes = list.lines().collect::<Vec<_>>();
+    changes.sort();
+    assert_eq!(
+        changes,
+        [
+            "modified\troot/src",
+            "modified\troot/src/modified.rs",
+            "removed\troot/src/removed.rs"
+        ]
+    );
+
+    // Whiteouts are stored at the path of the base entries, without being transformed again.
+    temp_arx!(patch_arx_file, "patch.arx");
+    cmd!(
+        "arx",
+        "diff-create",
+        "--outfile",
+        &patch_arx_file,
+        "--base",
+        &base_arx_file,
+        source_dir.path(),
+        "--dir-as-root",
+        "--transform",
+        "s,^,root/,"
+    )
+    .check_output(Some(""), Some(""));
+    let extract_dir = tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR"))?;
+    cmd!(
+        "arx",
+        "extract",
+        &patch_arx_file,
+        "-C",
+        extract_dir.path(),
+        "--apply-on",
+        &base_arx_file
+    )
+    .check_output(Some(""), Some(""));
+    assert!(!join!(extract_dir / "root" / "src" / "removed.rs").exists());
+    assert_eq!(
+        std::fs::read(join!(extract_dir / "root" / "src" / "modified.rs"))?,
+        b"new content"
+    );
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_update_metadata() -> Result {
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, SystemTime};
+    let source_dir = temp_tree!(0, {
+        text "same.txt" 100,
+        text "touched.txt" 100,
+        dir "sub" {
+            text "file.txt" 100
+        }
+    });
+    temp_arx!(base_arx_file, "base.arx");
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &base_arx_file,
+        source_dir.path(),
+        "--dir-as-root"
+    )
+    .check();
+    // Only the metadata change, the content is the same.
+    std::fs::set_permissions(
+        join!(source_dir / "sub"),
+        std::fs::Permissions::from_mode(0o700),
+    )?;
+    std::fs::File::options()
+        .write(true)
+        .open(join!(source_dir / "touched.txt"))?
+        .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000))?;
+    temp_arx!(arx_file);
+    let list = run!(
+        output,
+        "arx",
+        "update",
+        "--outfile",
+        &arx_file,
+        "--base",
+        &base_arx_file,
+        source_dir.path(),
+        "--dir-as-root",
+        "--checksum",
+        "--list"
+    );
+    let list = String::from_utf8_lossy(&list.stdout);
+    let mut changes = list.lines().collect::<Vec<_>>();
+    changes.sort();
+    assert_eq!(changes, ["modified\tsub", "modified\ttouched.txt"]);
+    Ok(())
+}
+
+#[test]
+fn test_merge() -> Result {
+    let dir_a = temp_tree!(0, {
+        dir "common" {
+            text "a.txt" 100
+        }
+    });
+    std::fs::write(join!(dir_a / "conflict.txt"), "from a")?;
+    let dir_b = temp_tree!(1, {
+        dir "common" {
+            text "b.txt" 100
+        }
+    });
+    std::fs::write(join!(dir_b / "conflict.txt"), "from b")?;
+    temp_arx!(arx_a, "a.arx");
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_a,
+        dir_a.path(),
+        "--dir-as-root"
+    )
+    .check();
+    temp_arx!(arx_b, "b.arx");
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_b,
+        dir_b.path(),
+        "--dir-as-root"
+    )
+    .check();
+
+    temp_arx!(arx_file);
+    cmd!("arx", "merge", "--outfile", &arx_file, &arx_a, &arx_b)
+        .check_fail("", "conflict.txt is present in several archives");
+    for (policy, content) in [("first-wins", "from a"), ("last-wins", "from b")] {
+        cmd!(
+            "arx",
+            "merge",
+            "--outfile",
+            &arx_file,
+            &arx_a,
+            &arx_b,
+            "--on-conflict",
+            policy,
+            "--force"
+        )
+        .check_output(Some(""), Some(""));
+        let arx_content = run!(output, "arx", "list", &arx_file);
+        let arx_content = String::from_utf8_lossy(&arx_content.stdout);
+        let mut arx_content = arx_content.lines().collect::<Vec<_>>();
+        arx_content.sort();
+        assert_eq!(
+            arx_content,
+            ["common", "common/a.txt", "common/b.txt", "conflict.txt"]
+        );
+        cmd!("arx", "dump", &arx_file, "conflict.txt")
+            .check_output(Some(&format!("^{content}$")), Some(""));
+    }
+
+    cmd!(
+        "arx",
+        "merge",
+        "--outfile",
+        &arx_file,
+        &arx_a,
+        &arx_b,
+        "--prefix",
+        "a=comp/a",
+        "--prefix",
+        "b=comp/b",
+        "--force"
+    )
+    .check_output(Some(""), Some(""));
+    let arx_content = run!(output, "arx", "list", &arx_file);
+    let arx_content = String::from_utf8_lossy(&arx_content.stdout);
+    let mut arx_content = arx_content.lines().collect::<Vec<_>>();
+    arx_content.sort();
+    assert_eq!(
+        arx_content,
+        [
+            "comp",
+            "comp/a",
+            "comp/a/common",
+            "comp/a/common/a.txt",
+            "comp/a/conflict.txt",
+            "comp/b",
+            "comp/b/common",
+            "comp/b/common/b.txt",
+            "comp/b/conflict.txt"
+        ]
+    );
+    cmd!("arx", "verify", &arx_file).check();
+
+    cmd!(
+        "arx",
+        "merge",
+        "--outfile",
+        &arx_file,
+        &arx_a,
+        &arx_b,
+        "--prefix",
+        "a=comp/../../a",
+        "--force"
+    )
+    .check_fail("", ".*Invalid prefix.*");
+
+    // In two files, the content packs of the inputs are referenced.
+    cmd!(
+        "arx",
+        "merge",
+        "--outfile",
+        &arx_file,
+        &arx_a,
+        &arx_b,
+        "--on-conflict",
+        "first-wins",
+        "-2",
+        "--force"
+    )
+    .check_output(Some(""), Some(""));
+    cmd!("arx", "dump", &arx_file, "conflict.txt").check_output(Some("^from a$"), Some(""));
+    cmd!("arx", "verify", &arx_file).check();
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_create_reproducible() -> Result {
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, SystemTime};
+    let source_dir = temp_tree!(0, {
+        dir "sub" {
+            text "file" 100,
+            text "script" 100
+        },
+        text "other" 100
+    });
+    let script = join!(source_dir / "sub" / "script");
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o700))?;
+    let mut archives = vec![];
+    for build in 0..2 {
+        // Change the metadata between the two builds
+        let mtime = SystemTime::UNIX_EPOCH + Duration::new(1_000_000 * build, 42);
+        std::fs::File::options()
+            .write(true)
+            .open(join!(source_dir / "other"))?
+            .set_modified(mtime)?;
+        std::fs::set_permissions(
+            join!(source_dir / "sub" / "file"),
+            std::fs::Permissions::from_mode(0o600 + 0o40 * build as u32),
+        )?;
+        let arx_file =
+            tempfile::NamedTempFile::with_suffix_in(".arx", env!("CARGO_TARGET_TMPDIR"))?
+                .into_temp_path();
+        cmd!(
+            "arx",
+            "create",
+            "--force",
+            "--outfile",
+            &arx_file,
+            source_dir.path(),
+            "--dir-as-root",
+            "--reproducible"
+        )
+        .env("SOURCE_DATE_EPOCH", "1000000000")
+        .check_output(Some(""), Some(""));
+        archives.push(arx_file);
+    }
+
+    let mut extracted = vec![];
+    for arx_file in &archives {
+        let extract_dir =
+            tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR"))?;
+        cmd!(
+            "arx",
+            "extract",
+            arx_file,
+            "-C",
+            extract_dir.path(),
+            "--preserve-permissions",
+            "--preserve-mtime"
+        )
+        .check_output(Some(""), Some(""));
+        extracted.push(extract_dir);
+    }
+
+    let expected_mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    for extract_dir in &extracted {
+        for (path, mode) in [
+            (join!(extract_dir / "sub"), 0o755),
+            (join!(extract_dir / "sub" / "file"), 0o644),
+            (join!(extract_dir / "sub" / "script"), 0o755),
+            (join!(extract_dir / "other"), 0o644),
+        ] {
+            let metadata = std::fs::metadata(&path)?;
+            assert_eq!(metadata.permissions().mode() & 0o7777, mode, "{path:?}");
+            assert_eq!(metadata.modified()?, expected_mtime, "{path:?}");
+        }
+    }
+    assert_eq!(std::fs::read(&archives[0])?, std::fs::read(&archives[1])?);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_create_metadata_override() -> Result {
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, SystemTime};
+    let source_dir = temp_tree!(0, {
+        dir "sub" {
+            text "file" 100
+        }
+    });
+    std::fs::set_permissions(
+        join!(source_dir / "sub" / "file"),
+        std::fs::Permissions::from_mode(0o666),
+    )?;
+    temp_arx!(arx_file);
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        source_dir.path(),
+        "--dir-as-root",
+        "--owner",
+        "0",
+        "--group",
+        "root:0",
+        "--mode",
+        "go-w,o=rX",
+        "--mtime",
+        "@1000000000"
+    )
+    .check_output(Some(""), Some(""));
+
+    let extract_dir = tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR"))?;
+    cmd!(
+        "arx",
+        "extract",
+        &arx_file,
+        "-C",
+        extract_dir.path(),
+        "--preserve-permissions",
+        "--preserve-mtime"
+    )
+    .check_output(Some(""), Some(""));
+
+    let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    let file = std::fs::metadata(join!(extract_dir / "sub" / "file"))?;
+    assert_eq!(file.permissions().mode() & 0o7777, 0o644);
+    assert_eq!(file.modified()?, mtime);
+    let dir = std::fs::metadata(join!(extract_dir / "sub"))?;
+    assert_eq!(dir.permissions().mode() & 0o7, 0o5);
+    assert_eq!(dir.modified()?, mtime);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_create_implicit_dirs() -> Result {
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, SystemTime};
+    let source_dir = temp_tree!(0, {
+        dir "sub" {
+            text "file" 100
+        }
+    });
+    let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    let file_path = join!(source_dir / "sub" / "file");
+    std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o640))?;
+    std::fs::File::options()
+        .write(true)
+        .open(&file_path)?
+        .set_modified(mtime)?;
+    std::fs::set_permissions(
+        join!(source_dir / "sub"),
+        std::fs::Permissions::from_mode(0o710),
+    )?;
+
+    for (options, expected_mode) in [
+        (&["--implicit-dir-mode", "700"][..], 0o700),
+        (&["--implicit-dir-inherit", "child"][..], 0o750),
+        (&["--implicit-dir-inherit", "ancestor"][..], 0o710),
+        // Implicit dirs are overridden and normalized as other entries.
+        (&["--mode", "750"][..], 0o750),
+        (&["--reproducible"][..], 0o755),
+    ] {
+        temp_arx!(arx_file);
+        cmd!(
+            "arx",
+            "create",
+            "--outfile",
+            &arx_file,
+            "-C",
+            source_dir.path(),
+            "--keep-parents",
+            "sub/file",
+            "--no-recurse"
+        )
+        .args(options)
+        .env("SOURCE_DATE_EPOCH", "1000000000")
+        .check_output(Some(""), Some(""));
+
+        let extract_dir =
+            tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR"))?;
+        cmd!(
+            "arx",
+            "extract",
+            &arx_file,
+            "-C",
+            extract_dir.path(),
+            "--preserve-permissions",
+            "--preserve-mtime"
+        )
+        .check_output(Some(""), Some(""));
+        let dir = std::fs::metadata(join!(extract_dir / "sub"))?;
+        assert_eq!(
+            dir.permissions().mode() & 0o7777,
+            expected_mode,
+            "{options:?}"
+        );
+        if options.contains(&"child") || options.contains(&"--reproducible") {
+            assert_eq!(dir.modified()?, mtime, "{options:?}");
+        }
+    }
+    Ok(())
+}
+
 #[rustest::main]
 fn main() {}
@@ -494,5 +494,583 @@ fn test_extract_subdir_filter(source_dir: SharedTestDir, arx_file: BaseArxFile)
     Ok(())
 }
 
+#[cfg(unix)]
+#[test]
+fn test_extract_hard_links() -> Result {
+    use std::os::unix::fs::MetadataExt;
+    let source_dir = temp_tree!(0, {
+        dir "sub_dir" {
+            text "file" 100
+        }
+    });
+    std::fs::hard_link(
+        join!(source_dir / "sub_dir" / "file"),
+        join!(source_dir / "hard_link"),
+    )?;
+    temp_arx!(arx_file);
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        source_dir.path(),
+        "--dir-as-root"
+    )
+    .check_output(Some(""), Some(""));
+
+    let extract_dir = tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR"))?;
+    cmd!("arx", "extract", &arx_file, "-C", extract_dir.path()).check_output(Some(""), Some(""));
+
+    let file = std::fs::metadata(join!(extract_dir / "sub_dir" / "file"))?;
+    let hard_link = std::fs::metadata(join!(extract_dir / "hard_link"))?;
+    assert_eq!(file.ino(), hard_link.ino());
+    assert_eq!(file.nlink(), 2);
+    assert!(tree_diff(
+        extract_dir,
+        source_dir.path(),
+        SimpleDiffer::new()
+    )?);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extract_fifo() -> Result {
+    use std::os::unix::fs::FileTypeExt;
+    let source_dir = temp_tree!(0, {
+        text "file" 100
+    });
+    cmd!("mkfifo", join!(source_dir / "fifo")).check();
+    temp_arx!(arx_file);
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        source_dir.path(),
+        "--dir-as-root"
+    )
+    .check_output(Some(""), Some(""));
+    cmd!("arx", "list", &arx_file, "--stable-output", "1")
+        .check_output(Some(r"(?m)^s \d+ fifo fifo$"), Some(""));
+
+    let extract_dir = tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR"))?;
+    cmd!("arx", "extract", &arx_file, "-C", extract_dir.path()).check_output(Some(""), Some(""));
+
+    let fifo = std::fs::symlink_metadata(join!(extract_dir / "fifo"))?;
+    assert!(fifo.file_type().is_fifo());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extract_mode() -> Result {
+    use std::os::unix::fs::PermissionsExt;
+    let source_dir = temp_tree!(0, {
+        text "file" 100
+    });
+    std::fs::set_permissions(
+        join!(source_dir / "file"),
+        std::fs::Permissions::from_mode(0o700),
+    )?;
+    temp_arx!(arx_file);
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        source_dir.path(),
+        "--dir-as-root"
+    )
+    .check_output(Some(""), Some(""));
+
+    let extract_dir = tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR"))?;
+    cmd!("arx", "extract", &arx_file, "-C", extract_dir.path()).check_output(Some(""), Some(""));
+
+    let file = std::fs::metadata(join!(extract_dir / "file"))?;
+    assert_eq!(file.permissions().mode() & 0o7777, 0o700);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extract_preserve_metadata() -> Result {
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, SystemTime};
+    let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    let source_dir = temp_tree!(0, {
+        dir "sub" {
+            text "file" 100
+        }
+    });
+    std::fs::set_permissions(
+        join!(source_dir / "sub" / "file"),
+        std::fs::Permissions::from_mode(0o4640),
+    )?;
+    std::fs::File::options()
+        .write(true)
+        .open(join!(source_dir / "sub" / "file"))?
+        .set_modified(mtime)?;
+    std::fs::File::open(join!(source_dir / "sub"))?.set_modified(mtime)?;
+    temp_arx!(arx_file);
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        source_dir.path(),
+        "--dir-as-root"
+    )
+    .check_output(Some(""), Some(""));
+
+    let extract_dir = tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR"))?;
+    cmd!(
+        "arx",
+        "extract",
+        &arx_file,
+        "-C",
+        extract_dir.path(),
+        "--preserve-permissions",
+        "--preserve-mtime"
+    )
+    .check_output(Some(""), Some(""));
+
+    let file = std::fs::metadata(join!(extract_dir / "sub" / "file"))?;
+    assert_eq!(file.permissions().mode() & 0o7777, 0o4640);
+    assert_eq!(file.modified()?, mtime);
+    let dir = std::fs::metadata(join!(extract_dir / "sub"))?;
+    assert_eq!(dir.modified()?, mtime);
+    Ok(())
+}
+
+#[test]
+fn test_repack(arx_file: BaseArxFile) -> Result {
+    temp_arx!(repacked_arx_file, "repacked.arx");
+    cmd!(
+        "arx",
+        "repack",
+        arx_file.path(),
+        "--outfile",
+        &repacked_arx_file,
+        "--compression",
+        "none",
+        "--two-files"
+    )
+    .check_output(Some(""), Some(""));
+    let arx_content = run!(
+        output,
+        "arx",
+        "list",
+        arx_file.path(),
+        "--stable-output",
+        "1"
+    );
+    let repacked_content = run!(
+        output,
+        "arx",
+        "list",
+        &repacked_arx_file,
+        "--stable-output",
+        "1"
+    );
+    assert_eq!(arx_content.stdout, repacked_content.stdout);
+    cmd!("arx", "verify", &repacked_arx_file).check();
+
+    cmd!(
+        "arx",
+        "repack",
+        arx_file.path(),
+        "--outfile",
+        arx_file.path(),
+        "--force"
+    )
+    .check_fail("", ".*into itself.*");
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_edit() -> Result {
+    use std::os::unix::fs::PermissionsExt;
+    let source_dir = temp_tree!(0, {
+        text "keep.txt" 100,
+        text "remove.tmp" 100,
+        dir "src" {
+            text "run.sh" 100,
+            text "remove.tmp" 100,
+            dir "old" {
+                text "file.txt" 100
+            }
+        }
+    });
+    temp_arx!(arx_file);
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        source_dir.path(),
+        "--dir-as-root"
+    )
+    .check_output(Some(""), Some(""));
+
+    temp_arx!(edited_arx_file, "edited.arx");
+    cmd!(
+        "arx",
+        "edit",
+        &arx_file,
+        "--outfile",
+        &edited_arx_file,
+        "--delete",
+        "*.tmp",
+        "--delete",
+        "src/old",
+        "--rename",
+        "src=source",
+        "--chmod",
+        "source/*.sh=755"
+    )
+    .check_output(Some(""), Some(""));
+    let arx_content = run!(output, "arx", "list", &edited_arx_file);
+    let arx_content = String::from_utf8_lossy(&arx_content.stdout);
+    let mut arx_content = arx_content.lines().collect::<Vec<_>>();
+    arx_content.sort();
+    assert_eq!(
+        arx_content,
+        ["keep.txt", "source", "source/remove.tmp", "source/run.sh"]
+    );
+    cmd!("arx", "verify", &edited_arx_file).check();
+
+    let extract_dir = tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR"))?;
+    cmd!("arx", "extract", &edited_arx_file, "-C", extract_dir.path())
+        .check_output(Some(""), Some(""));
+    let file = std::fs::metadata(join!(extract_dir / "source" / "run.sh"))?;
+    assert_eq!(file.permissions().mode() & 0o7777, 0o755);
+    assert_eq!(
+        std::fs::read(join!(extract_dir / "keep.txt"))?,
+        std::fs::read(join!(source_dir / "keep.txt"))?
+    );
+
+    // The edited archive references the content packs of the input archive.
+    cmd!(
+        "arx",
+        "edit",
+        &arx_file,
+        "--outfile",
+        &edited_arx_file,
+        "--delete",
+        "*.tmp",
+        "--force",
+        "-1"
+    )
+    .check_fail("", ".*without --gc.*");
+
+    // With --gc, the edited archive doesn't depend on the input archive.
+    temp_arx!(gc_arx_file, "gc.arx");
+    cmd!(
+        "arx",
+        "edit",
+        &arx_file,
+        "--outfile",
+        &gc_arx_file,
+        "--delete",
+        "*.tmp",
+        "--gc",
+        "-1"
+    )
+    .check_output(Some(""), Some(""));
+    std::fs::remove_file(&arx_file)?;
+    let extract_dir = tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR"))?;
+    cmd!("arx", "extract", &gc_arx_file, "-C", extract_dir.path()).check_output(Some(""), Some(""));
+    assert_eq!(
+        std::fs::read(join!(extract_dir / "src" / "old" / "file.txt"))?,
+        std::fs::read(join!(source_dir / "src" / "old" / "file.txt"))?
+    );
+    assert!(!join!(extract_dir / "remove.tmp").exists());
+    Ok(())
+}
+
+#[test]
+fn test_diff_create_apply_on() -> Result {
+    let source_dir = temp_tree!(0, {
+        text "same.txt" 100,
+        text "modified.txt" 100,
+        text "removed.txt" 100,
+        dir "removed_dir" {
+            text "file.txt" 100
+        },
+        dir "sub" {
+            text "file.txt" 100
+        }
+    });
+    temp_arx!(base_arx_file, "base.arx");
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &base_arx_file,
+        source_dir.path(),
+        "--dir-as-root"
+    )
+    .check();
+    std::fs::write(join!(source_dir / "modified.txt"), "new content")?;
+    std::fs::remove_file(join!(source_dir / "removed.txt"))?;
+    std::fs::remove_dir_all(join!(source_dir / "removed_dir"))?;
+    std::fs::write(join!(source_dir / "added.txt"), "added content")?;
+    std::fs::write(join!(source_dir / "sub" / "added.txt"), "added content")?;
+    temp_arx!(patch_arx_file, "patch.arx");
+    cmd!(
+        "arx",
+        "diff-create",
+        "--outfile",
+        &patch_arx_file,
+        "--base",
+        &base_arx_file,
+        source_dir.path(),
+        "--dir-as-root"
+    )
+    .check_output(Some(""), Some(""));
+    let patch_content = run!(output, "arx", "list", &patch_arx_file);
+    let patch_content = String::from_utf8_lossy(&patch_content.stdout);
+    let mut patch_content = patch_content.lines().collect::<Vec<_>>();
+    patch_content.sort();
+    assert_eq!(
+        patch_content,
+        [
+            "added.txt",
+            "modified.txt",
+            "removed.txt",
+            "removed_dir",
+            "sub",
+            "sub/added.txt"
+        ]
+    );
+
+    let extract_dir = tempfile::TempDir::new_in(env!("CARGO_TARGET_TMPDIR"))?;
+    // Layers are always extracted recursively.
+    cmd!(
+        "arx",
+        "extract",
+        &patch_arx_file,
+        "-C",
+        extract_dir.path(),
+        "--apply-on",
+        &base_arx_file,
+        "--no-recurse"
+    )
+    .check_fail("", ".*cannot be used with.*");
+    cmd!(
+        "arx",
+        "extract",
+        &patch_arx_file,
+        "-C",
+        extract_dir.path(),
+        "--apply-on",
+        &base_arx_file
+    )
+    .check_output(Some(""), Some(""));
+    assert!(tree_diff(
+        extract_dir.path(),
+        source_dir.path(),
+        SimpleDiffer::new()
+    )?);
+    Ok(())
+}
+
+#[test]
+fn test_diff_create_apply_on_three_layers() -> Result {
+    let source_dir = temp_tree!(0, {
+        text "keep.txt" 100,
+        dir "d" {
+            text "x" 100
+        }
+    });
+    temp_arx!(base_arx_file, "base.arx");
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &base_arx_file,
+        source_dir.path(),
+        "--dir-as-root"
+    )
+    .check();
+
+    // The first patch removes `d`.
+    std::fs::remove_dir_all(join!(source_dir / "d"))?;
+    temp_arx!(patch1_arx_file, "patch1.arx");
+    cmd!(
+        "arx",
+        "diff-create",
+        "--outfile",
+        &patch1_arx_file,
+        "--base",
+        &base_arx_file,
+        source_dir.path(),
+        "--dir-as-root"
+    )
+    .check_output(Some(""), Some(""));
+    temp_arx!(state1_arx_file, "state1.arx");
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &state1_arx_file,
+        source_dir.path(),
+        "--dir-as-root"
+    )
+    .check();
+
+    // The second patch creates `d` again, with another content.
+    std::fs::create_dir(join!(source_dir / "d"))?;
+    std::fs::write(join!(source_dir / "d" / "y"), "new file")?;
+    temp_arx!(patch2_arx_file, "patch2.arx");
+    cmd!(
+        "arx",
+        "diff-create",
+        "--outfile",
+        &patch2_arx_file,
+        "--base",
+        &state1_arx_file,
+        source_dir.path(),
+        "--dir-as-root"
+    )
+    .check_output(Some(""), Some(""));
+
+    let extract_dir = tempfile::TempDir::new_in(env!("CARGO_TARGET_TMPDIR"))?;
+    cmd!(
+        "arx",
+        "extract",
+        &patch2_arx_file,
+        "-C",
+        extract_dir.path(),
+        "--apply-on",
+        &base_arx_file,
+        "--apply-on",
+        &patch1_arx_file
+    )
+    .check_output(Some(""), Some(""));
+    assert!(!join!(extract_dir / "d" / "x").exists());
+    assert_eq!(std::fs::read(join!(extract_dir / "d" / "y"))?, b"new file");
+    assert!(tree_diff(
+        extract_dir.path(),
+        source_dir.path(),
+        SimpleDiffer::new()
+    )?);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extract_nanosecond_mtime() -> Result {
+    use std::time::{Duration, SystemTime};
+    let mtime = SystemTime::UNIX_EPOCH + Duration::new(1_000_000_000, 123_456_789);
+    let source_dir = temp_tree!(0, {
+        text "file" 100
+    });
+    std::fs::File::options()
+        .write(true)
+        .open(join!(source_dir / "file"))?
+        .set_modified(mtime)?;
+    temp_arx!(arx_file);
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        source_dir.path(),
+        "--dir-as-root"
+    )
+    .check_output(Some(""), Some(""));
+
+    let extract_dir = tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR"))?;
+    cmd!(
+        "arx",
+        "extract",
+        &arx_file,
+        "-C",
+        extract_dir.path(),
+        "--preserve-mtime"
+    )
+    .check_output(Some(""), Some(""));
+
+    let file = std::fs::metadata(join!(extract_dir / "file"))?;
+    assert_eq!(file.modified()?, mtime);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extract_non_utf8_names() -> Result {
+    use std::os::unix::ffi::OsStrExt;
+    let name = OsStr::from_bytes(b"caf\xe9");
+    let source_dir = tempfile::TempDir::with_prefix_in("source_", env!("CARGO_TARGET_TMPDIR"))?;
+    std::fs::create_dir(source_dir.path().join(name))?;
+    std::fs::write(source_dir.path().join(name).join(name), b"Latin-1")?;
+    std::os::unix::fs::symlink(name, source_dir.path().join("link"))?;
+    // A valid name using the chars used to escape invalid bytes.
+    std::fs::write(source_dir.path().join("\u{10FF80}"), b"Escape")?;
+    temp_arx!(arx_file);
+    cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        source_dir.path(),
+        "--dir-as-root"
+    )
+    .check_output(Some(""), Some(""));
+
+    let list = cmd!("arx", "list", &arx_file).output()?;
+    assert!(list
+        .stdout
+        .split(|c| *c == b'\n')
+        .any(|l| l == b"caf\xe9/caf\xe9"));
+
+    let list = cmd!("arx", "list", &arx_file, "--stable-output", "1").output()?;
+    let lines = list.stdout.split(|c| *c == b'\n').collect::<Vec<_>>();
+    assert!(lines.iter().any(|l| l.ends_with(b" 7 caf\xe9/caf\xe9")));
+    assert!(lines.iter().any(|l| l.ends_with(b" link->caf\xe9")));
+    assert!(lines
+        .iter()
+        .any(|l| l.ends_with(" 6 \u{10FF80}".as_bytes())));
+
+    let extract_dir = tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR"))?;
+    cmd!("arx", "extract", &arx_file, "-C", extract_dir.path()).check_output(Some(""), Some(""));
+    assert_eq!(
+        std::fs::read(extract_dir.path().join(name).join(name))?,
+        b"Latin-1"
+    );
+    assert_eq!(
+        std::fs::read_link(extract_dir.path().join("link"))?,
+        Path::new(name)
+    );
+    assert_eq!(
+        std::fs::read(extract_dir.path().join("\u{10FF80}"))?,
+        b"Escape"
+    );
+
+    // Names in a file list are raw bytes too.
+    let file_list = join!(source_dir / "file_list");
+    std::fs::write(&file_list, b"caf\xe9/caf\xe9\n")?;
+    let extract_dir = tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR"))?;
+    cmd!(
+        "arx",
+        "extract",
+        &arx_file,
+        "-C",
+        extract_dir.path(),
+        "--file-list",
+        &file_list
+    )
+    .check_output(Some(""), Some(""));
+    assert_eq!(
+        std::fs::read(extract_dir.path().join(name).join(name))?,
+        b"Latin-1"
+    );
+    assert!(std::fs::symlink_metadata(extract_dir.path().join("link")).is_err());
+    Ok(())
+}
+
 #[rustest::main]
 fn main() {}
@@ -1,7 +1,10 @@
-use super::common::{AllProperties, Comparator, Entry, FullBuilderTrait, RealBuilder};
+use super::common::{
+    unescape_str, AllProperties, Comparator, Entry, FullBuilderTrait, Property, RealBuilder,
+};
 use super::error::{ArxError, ArxFormatError, PathNotFound, QueryError};
-use jbk::reader::builder::BuilderTrait;
+use jbk::reader::builder::{BuilderTrait, PropertyBuilderTrait};
 use jbk::{reader::Range, EntryIdx};
+use std::collections::BTreeMap;
 use std::path::Path;
 
 pub struct Arx {
@@ -71,7 +74,8 @@ impl Arx {
             // All children of a parent are stored concatened.
             // So if parent_id is different than current_parent,
             // we know we are out of the directory
-            let comparator = comparator.compare_with(component.as_bytes());
+            let component = unescape_str(component);
+            let comparator = comparator.compare_with(&component);
             let found = current_range.find(&comparator)?;
             match found {
                 None => return Err(PathNotFound(path.into()).into()),
@@ -93,6 +97,52 @@ impl Arx {
         unreachable!();
     }
 
+    /// The archive-level metadata (key/value pairs set at creation).
+    ///
+    /// Archives without metadata return an empty map.
+    pub fn metadata(&self) -> Result<BTreeMap<String, String>, ArxError> {
+        let mut metadata = BTreeMap::new();
+        let Some(index) = self
+            .container
+            .get_directory_pack()
+            .get_index_from_name("arx_metadata")?
+        else {
+            return Ok(metadata);
+        };
+        let store = index.get_store(self.container.get_entry_storage())?;
+        let layout = store.layout();
+        let value_storage = self.container.get_value_storage();
+        let key_property = jbk::layout_builder!(
+            layout[common][Property::MetaKey],
+            value_storage,
+            ArxFormatError
+        );
+        let value_property = jbk::layout_builder!(
+            layout[common][Property::MetaValue],
+            value_storage,
+            ArxFormatError
+        );
+        let read_string = |property: &jbk::reader::builder::ArrayProperty,
+                           reader: &jbk::reader::ByteSlice|
+         -> jbk::Result<String> {
+            let mut value = jbk::SmallBytes::new();
+            property.create(reader)?.resolve_to_vec(&mut value)?;
+            Ok(String::from_utf8_lossy(&value).into_owned())
+        };
+        let begin = index.offset().into_u32();
+        let end = begin + index.count().into_u32();
+        for idx in begin..end {
+            let reader = store
+                .get_entry_reader(idx.into())
+                .ok_or(ArxFormatError("Invalid metadata entry"))?;
+            metadata.insert(
+                read_string(&key_property, &reader)?,
+                read_string(&value_property, &reader)?,
+            );
+        }
+        Ok(metadata)
+    }
+
     pub fn get_entry_at_idx<B>(&self, idx: EntryIdx) -> Result<Option<Entry<B::Entry>>, ArxError>
     where
         B: FullBuilderTrait,
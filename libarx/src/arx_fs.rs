@@ -1,9 +1,12 @@
 use super::Arx;
-use crate::common::{AllProperties, Comparator, EntryType, ReadEntry};
+use crate::common::{
+    decode_rdev, read_mtime_nsec, read_optional_array, read_time, AllProperties, Comparator,
+    EntryType, LocalNames, ReadEntry, SpecialKind, Xattrs,
+};
 use crate::{ArxError, ArxFormatError, BaseError, FsError, WrongType};
 use fxhash::FxBuildHasher;
 use jbk::reader::builder::PropertyBuilderTrait;
-use jbk::reader::{MayMissPack, Range};
+use jbk::reader::{ByteSlice, MayMissPack, Range};
 use jbk::EntryRange;
 use libc::ENOENT;
 use lru::LruCache;
@@ -29,6 +32,8 @@ pub trait Stats {
     fn opendir(&mut self) {}
     fn readdir(&mut self) {}
     fn releasedir(&mut self) {}
+    fn getxattr(&mut self) {}
+    fn listxattr(&mut self) {}
 }
 
 impl Stats for () {}
@@ -76,6 +81,29 @@ impl TryInto<jbk::EntryIdx> for Ino {
     }
 }
 
+/// The fuse file type of an entry, special files being refined by their kind.
+///
+/// Whiteouts (and unknown variants) have no file type, they are hidden.
+fn file_type(
+    entry_type: Option<EntryType>,
+    special_kind_property: &Option<jbk::reader::builder::IntProperty>,
+    reader: &ByteSlice,
+) -> Result<Option<fuser::FileType>, BaseError> {
+    Ok(match entry_type {
+        None | Some(EntryType::Whiteout) => None,
+        Some(EntryType::File) => Some(fuser::FileType::RegularFile),
+        Some(EntryType::Dir) => Some(fuser::FileType::Directory),
+        Some(EntryType::Link) => Some(fuser::FileType::Symlink),
+        Some(EntryType::Special) => {
+            let kind = special_kind_property
+                .as_ref()
+                .expect("Special variant has a kind property")
+                .create(reader)?;
+            Some(SpecialKind::try_from(kind)?.into())
+        }
+    })
+}
+
 struct LightLinkBuilder {
     store: jbk::reader::EntryStore,
     variant_id_property: jbk::reader::builder::VariantIdBuilder<EntryType>,
@@ -198,7 +226,7 @@ impl jbk::reader::builder::BuilderTrait for LightDirBuilder {
 }
 
 struct LightCommonPath {
-    file_type: Option<EntryType>,
+    file_type: Option<fuser::FileType>,
     path: jbk::SmallBytes,
 }
 
@@ -206,6 +234,7 @@ struct LightCommonPathBuilder {
     store: jbk::reader::EntryStore,
     variant_id_property: jbk::reader::builder::VariantIdBuilder<EntryType>,
     path_property: jbk::reader::builder::ArrayProperty,
+    special_kind_property: Option<jbk::reader::builder::IntProperty>,
 }
 
 impl LightCommonPathBuilder {
@@ -214,6 +243,7 @@ impl LightCommonPathBuilder {
             store: properties.store.clone(),
             variant_id_property: properties.variant_id_property.clone(),
             path_property: properties.path_property.clone(),
+            special_kind_property: properties.special_kind_property.clone(),
         }
     }
 }
@@ -229,7 +259,11 @@ impl jbk::reader::builder::BuilderTrait for LightCommonPathBuilder {
                 let path_prop = self.path_property.create(&reader)?;
                 let mut path = jbk::SmallBytes::new();
                 path_prop.resolve_to_vec(&mut path)?;
-                let file_type = self.variant_id_property.create(&reader)?;
+                let file_type = file_type(
+                    self.variant_id_property.create(&reader)?,
+                    &self.special_kind_property,
+                    &reader,
+                )?;
                 Ok(LightCommonPath { file_type, path })
             })
             .transpose()
@@ -270,6 +304,35 @@ impl jbk::reader::builder::BuilderTrait for LightCommonParentBuilder {
     }
 }
 
+struct XattrsBuilder {
+    store: jbk::reader::EntryStore,
+    xattrs_property: Option<jbk::reader::builder::ArrayProperty>,
+}
+
+impl XattrsBuilder {
+    fn new(properties: &AllProperties) -> Self {
+        Self {
+            store: properties.store.clone(),
+            xattrs_property: properties.xattrs_property.clone(),
+        }
+    }
+}
+
+impl jbk::reader::builder::BuilderTrait for XattrsBuilder {
+    type Entry = Xattrs;
+    type Error = FsError;
+
+    fn create_entry(&self, idx: jbk::EntryIdx) -> Result<Option<Self::Entry>, FsError> {
+        self.store
+            .get_entry_reader(idx)
+            .map(|reader| match &self.xattrs_property {
+                Some(property) => Ok(Xattrs::read(property, &reader)?),
+                None => Ok(Xattrs::new()),
+            })
+            .transpose()
+    }
+}
+
 struct AttrBuilder {
     store: jbk::reader::EntryStore,
     variant_id_property: jbk::reader::builder::VariantIdBuilder<EntryType>,
@@ -280,6 +343,19 @@ struct AttrBuilder {
     file_size_property: jbk::reader::builder::IntProperty,
     dir_nb_children_property: jbk::reader::builder::IntProperty,
     link_target_property: jbk::reader::builder::ArrayProperty,
+    file_inode_property: Option<jbk::reader::builder::IntProperty>,
+    file_nlink_property: Option<jbk::reader::builder::IntProperty>,
+    special_kind_property: Option<jbk::reader::builder::IntProperty>,
+    special_rdev_property: Option<jbk::reader::builder::IntProperty>,
+    mtime_nsec_property: Option<jbk::reader::builder::IntProperty>,
+    atime_property: Option<jbk::reader::builder::IntProperty>,
+    ctime_property: Option<jbk::reader::builder::IntProperty>,
+    btime_property: Option<jbk::reader::builder::IntProperty>,
+    owner_name_property: Option<jbk::reader::builder::ArrayProperty>,
+    group_name_property: Option<jbk::reader::builder::ArrayProperty>,
+    rights_mask: u16,
+    numeric_owner: bool,
+    names: LocalNames,
 }
 
 impl AttrBuilder {
@@ -294,10 +370,31 @@ impl AttrBuilder {
             file_size_property: properties.file_size_property.clone(),
             dir_nb_children_property: properties.dir_nb_children_property.clone(),
             link_target_property: properties.link_target_property.clone(),
+            file_inode_property: properties.file_inode_property.clone(),
+            file_nlink_property: properties.file_nlink_property.clone(),
+            special_kind_property: properties.special_kind_property.clone(),
+            special_rdev_property: properties.special_rdev_property.clone(),
+            mtime_nsec_property: properties.mtime_nsec_property.clone(),
+            atime_property: properties.atime_property.clone(),
+            ctime_property: properties.ctime_property.clone(),
+            btime_property: properties.btime_property.clone(),
+            owner_name_property: properties.owner_name_property.clone(),
+            group_name_property: properties.group_name_property.clone(),
+            rights_mask: 0o7777,
+            numeric_owner: false,
+            names: Default::default(),
         }
     }
 }
 
+/// Convert a time in nanoseconds since epoch, using `default` if the time is unknown.
+fn time_or(ns: Option<u64>, default: std::time::SystemTime) -> std::time::SystemTime {
+    match ns {
+        Some(ns) => std::time::UNIX_EPOCH + std::time::Duration::from_nanos(ns),
+        None => default,
+    }
+}
+
 fn div_ceil(value: u64, rhs: u64) -> u64 {
     let mut ret = value / rhs;
     if (value % rhs) != 0 {
@@ -317,6 +414,8 @@ impl jbk::reader::builder::BuilderTrait for AttrBuilder {
                 let kind = self.variant_id_property.create(&reader)?;
 
                 let size = match &kind {
+                    // Whiteouts are hidden, as if they were not present.
+                    Some(EntryType::Whiteout) => return Ok(None),
                     Some(EntryType::File) => self.file_size_property.create(&reader)?,
                     Some(EntryType::Dir) => {
                         (self.dir_nb_children_property.create(&reader)? + 1) * 10
@@ -332,36 +431,70 @@ impl jbk::reader::builder::BuilderTrait for AttrBuilder {
                             }
                         }
                     }
+                    Some(EntryType::Special) => 0,
                     None => {
                         return Err(BaseError::ArxFormatError(ArxFormatError("Unknown variant")))
                     }
                 };
-                let rigths = (self.rights_property.create(&reader)? as u16) & 0b1111_1111_0110_1101;
+                let rdev = match (&kind, &self.special_rdev_property) {
+                    (Some(EntryType::Special), Some(rdev_property)) => {
+                        let (major, minor) = decode_rdev(rdev_property.create(&reader)?);
+                        libc::makedev(major as _, minor as _) as u32
+                    }
+                    _ => 0,
+                };
+                // Hard links share the ino of the first entry of the inode.
+                let (inode_idx, nlink) =
+                    match (&kind, &self.file_inode_property, &self.file_nlink_property) {
+                        (Some(EntryType::File), Some(inode_property), Some(nlink_property)) => (
+                            jbk::EntryIdx::from(inode_property.create(&reader)? as u32),
+                            nlink_property.create(&reader)? as u32,
+                        ),
+                        _ => (idx, 1),
+                    };
+                let rigths = (self.rights_property.create(&reader)? as u16) & self.rights_mask;
+                let mut uid = self.owner_property.create(&reader)? as u32;
+                let mut gid = self.group_property.create(&reader)? as u32;
+                // Map stored names to local ids, keeping stored ids for unknown names.
+                if !self.numeric_owner {
+                    if let Some(name) = read_optional_array(&self.owner_name_property, &reader)? {
+                        uid = self.names.uid(&name).unwrap_or(uid);
+                    }
+                    if let Some(name) = read_optional_array(&self.group_name_property, &reader)? {
+                        gid = self.names.gid(&name).unwrap_or(gid);
+                    }
+                }
+                let mtime = std::time::UNIX_EPOCH
+                    + std::time::Duration::new(
+                        self.mtime_property.create(&reader)?,
+                        read_mtime_nsec(&self.mtime_nsec_property, &reader)?,
+                    );
                 // Make kernel think we allocate by block of 4KB.
                 let allocated_size = match &kind {
                     Some(EntryType::Dir) => 0,
                     _ => div_ceil(size, 4 * 1024) * (4 * 1024),
                 };
-                Ok(fuser::FileAttr {
-                    ino: Ino::from(idx).get(),
+                Ok(Some(fuser::FileAttr {
+                    ino: Ino::from(inode_idx).get(),
                     size,
-                    kind: kind.unwrap().into(),
+                    kind: file_type(kind, &self.special_kind_property, &reader)?
+                        .expect("Unknown variant and whiteout are already handled"),
                     blocks: div_ceil(allocated_size, BLOCK_SIZE as u64),
-                    atime: std::time::UNIX_EPOCH,
-                    mtime: std::time::UNIX_EPOCH
-                        + std::time::Duration::from_secs(self.mtime_property.create(&reader)?),
-                    ctime: std::time::UNIX_EPOCH,
-                    crtime: std::time::UNIX_EPOCH,
+                    atime: time_or(read_time(&self.atime_property, &reader)?, mtime),
+                    mtime,
+                    ctime: time_or(read_time(&self.ctime_property, &reader)?, mtime),
+                    crtime: time_or(read_time(&self.btime_property, &reader)?, mtime),
                     perm: rigths,
-                    nlink: 1,
-                    uid: self.owner_property.create(&reader)? as u32,
-                    gid: self.group_property.create(&reader)? as u32,
-                    rdev: 0,
+                    nlink,
+                    uid,
+                    gid,
+                    rdev,
                     blksize: BLOCK_SIZE,
                     flags: 0,
-                })
+                }))
             })
             .transpose()
+            .map(Option::flatten)
     }
 }
 
@@ -378,6 +511,7 @@ pub struct ArxFs<'a, S: Stats> {
     light_common_path_builder: LightCommonPathBuilder,
     light_common_parent_builder: LightCommonParentBuilder,
     attr_builder: AttrBuilder,
+    xattrs_builder: XattrsBuilder,
     resolve_cache: LruCache<(Ino, OsString), Option<jbk::EntryIdx>, FxBuildHasher>,
     attr_cache: LruCache<jbk::EntryIdx, fuser::FileAttr, FxBuildHasher>,
     region_cache: HashMap<Ino, (jbk::reader::ByteRegion, u64), FxBuildHasher>,
@@ -415,6 +549,7 @@ impl<'a, S: Stats> ArxFs<'a, S> {
         let light_common_path_builder = LightCommonPathBuilder::new(&properties);
         let light_common_parent_builder = LightCommonParentBuilder::new(&properties);
         let attr_builder = AttrBuilder::new(&properties);
+        let xattrs_builder = XattrsBuilder::new(&properties);
         Ok(Self {
             arx,
             entry_index,
@@ -426,6 +561,7 @@ impl<'a, S: Stats> ArxFs<'a, S> {
             light_common_path_builder,
             light_common_parent_builder,
             attr_builder,
+            xattrs_builder,
             resolve_cache: LruCache::with_hasher(
                 NonZeroUsize::new(4 * 1024).unwrap(),
                 FxBuildHasher::default(),
@@ -439,6 +575,20 @@ impl<'a, S: Stats> ArxFs<'a, S> {
         })
     }
 
+    /// Don't report setuid, setgid and sticky bits of the entries.
+    ///
+    /// Default is to report the rights as stored in the archive.
+    pub fn strip_special_bits(mut self, strip: bool) -> Self {
+        self.attr_builder.rights_mask = if strip { 0o777 } else { 0o7777 };
+        self
+    }
+
+    /// Report the stored numeric ids instead of mapping owner and group names to local ids.
+    pub fn numeric_owner(mut self, numeric_owner: bool) -> Self {
+        self.attr_builder.numeric_owner = numeric_owner;
+        self
+    }
+
     fn get_entry_range(&self, ino: Ino) -> Result<jbk::EntryRange, FsError> {
         match ino.try_into() {
             Err(_) => Ok(self.root_range),
@@ -449,6 +599,17 @@ impl<'a, S: Stats> ArxFs<'a, S> {
         }
     }
 
+    fn get_xattrs(&self, ino: Ino) -> Result<Xattrs, FsError> {
+        match ino.try_into() {
+            // Root directory has no extended attributes
+            Err(_) => Ok(Xattrs::new()),
+            Ok(idx) => self
+                .entry_index
+                .get_entry(&self.xattrs_builder, idx)?
+                .ok_or(FsError::NotFound),
+        }
+    }
+
     fn mount_options(
         &self,
         name: String,
@@ -793,7 +954,7 @@ impl<S: Stats> fuser::Filesystem for ArxFs<'_, S> {
                     Some(entry) => {
                         let entry = entry.unwrap();
                         if entry.file_type.is_none() {
-                            // We don't know the type of the entry. Skip it
+                            // Unknown entry or whiteout. Skip it
                             continue;
                         }
                         // We remove "." and ".."
@@ -803,7 +964,7 @@ impl<S: Stats> fuser::Filesystem for ArxFs<'_, S> {
                         let should_break = reply.add(
                             entry_ino.get(),
                             /* offset =*/ i,
-                            entry.file_type.unwrap().into(),
+                            entry.file_type.unwrap(),
                             &entry_path,
                         );
                         self.resolve_cache.put((ino, entry_path), Some(entry_idx));
@@ -828,4 +989,59 @@ impl<S: Stats> fuser::Filesystem for ArxFs<'_, S> {
         self.stats.releasedir();
         reply.ok()
     }
+
+    fn getxattr(
+        &mut self,
+        _req: &fuser::Request,
+        ino: u64,
+        name: &OsStr,
+        size: u32,
+        reply: fuser::ReplyXattr,
+    ) {
+        self.stats.getxattr();
+        match self.get_xattrs(Ino::from(ino)) {
+            Ok(xattrs) => match xattrs.get(name.as_bytes()) {
+                None => reply.error(
+                    #[cfg(not(target_os = "linux"))]
+                    libc::ENOATTR,
+                    #[cfg(target_os = "linux")]
+                    libc::ENODATA,
+                ),
+                Some(value) => {
+                    if size == 0 {
+                        reply.size(value.len() as u32)
+                    } else if value.len() <= size as usize {
+                        reply.data(value)
+                    } else {
+                        reply.error(libc::ERANGE)
+                    }
+                }
+            },
+            Err(FsError::NotFound) => reply.error(ENOENT),
+            _ => reply.error(libc::ENOTRECOVERABLE),
+        }
+    }
+
+    fn listxattr(&mut self, _req: &fuser::Request, ino: u64, size: u32, reply: fuser::ReplyXattr) {
+        self.stats.listxattr();
+        match self.get_xattrs(Ino::from(ino)) {
+            Ok(xattrs) => {
+                // Names are sent as a list of nul terminated strings.
+                let mut names = Vec::new();
+                for name in xattrs.names() {
+                    names.extend_from_slice(name);
+                    names.push(0);
+                }
+                if size == 0 {
+                    reply.size(names.len() as u32)
+                } else if names.len() <= size as usize {
+                    reply.data(&names)
+                } else {
+                    reply.error(libc::ERANGE)
+                }
+            }
+            Err(FsError::NotFound) => reply.error(ENOENT),
+            _ => reply.error(libc::ENOTRECOVERABLE),
+        }
+    }
 }
@@ -114,3 +114,95 @@ impl From<ConcatMode> for jbk::creator::ConcatMode {
         }
     }
 }
+
+fn parse_mtime(s: &str) -> Result<u64, crate::InputError> {
+    s.strip_prefix('@').unwrap_or(s).parse().map_err(|_| {
+        crate::InputError(format!(
+            "Invalid mtime {s:?} (expecting seconds since epoch)"
+        ))
+    })
+}
+
+/// Options to force the metadata of the added entries
+#[derive(clap::Args, Debug)]
+pub struct MetadataOverride {
+    /// Force the owner of entries (`ID`, `NAME` or `NAME:ID`)
+    #[arg(long, value_name = "USER", value_parser = crate::create::Owner::parse_user, help_heading = "Metadata options")]
+    owner: Option<crate::create::Owner>,
+
+    /// Force the group of entries (`ID`, `NAME` or `NAME:ID`)
+    #[arg(long, value_name = "GROUP", value_parser = crate::create::Owner::parse_group, help_heading = "Metadata options")]
+    group: Option<crate::create::Owner>,
+
+    /// Change the mode of entries, as chmod does (`644`, `u+rwX,go-w`, ...)
+    #[arg(long, value_name = "MODE", value_parser = crate::create::ModeSpec::parse, help_heading = "Metadata options")]
+    mode: Option<crate::create::ModeSpec>,
+
+    /// Force the modification time of entries (seconds since epoch, optionally prefixed by `@`)
+    #[arg(long, value_name = "SECONDS", value_parser = parse_mtime, help_heading = "Metadata options")]
+    mtime: Option<u64>,
+}
+
+impl From<MetadataOverride> for crate::create::MetadataOverride {
+    fn from(opt: MetadataOverride) -> Self {
+        Self {
+            owner: opt.owner,
+            group: opt.group,
+            mode: opt.mode,
+            mtime: opt.mtime,
+        }
+    }
+}
+
+/// Options to force or prevent compression of contents
+#[derive(clap::Args, Debug)]
+pub struct CompressionRules {
+    /// Compress (or not) the files matching the patterns
+    ///
+    /// Rule is `PATTERN[,PATTERN...][=yes|no|auto]` (`yes` if not given).
+    /// A pattern without `/` matches the file name, others match the path in the archive.
+    /// The last matching rule wins, `--no-compress` rules being evaluated after `--compress` ones.
+    /// Files matching no rule are compressed if it is worth it.
+    /// The algorithm and level are the ones of `--compression`, they cannot be chosen per rule.
+    #[arg(long, value_name = "RULE", action = clap::ArgAction::Append, help_heading = "Compression options")]
+    compress: Vec<String>,
+
+    /// Don't compress the files matching the patterns (`PATTERN[,PATTERN...]`)
+    #[arg(long, value_name = "PATTERNS", action = clap::ArgAction::Append, help_heading = "Compression options")]
+    no_compress: Vec<String>,
+}
+
+impl TryFrom<CompressionRules> for crate::create::CompressionRules {
+    type Error = crate::InputError;
+    fn try_from(opt: CompressionRules) -> Result<Self, Self::Error> {
+        use crate::create::Compress;
+        let mut rules = Self::new();
+        for rule in &opt.compress {
+            rules.add_rule(rule, Compress::Yes)?;
+        }
+        for patterns in &opt.no_compress {
+            for pattern in patterns.split(',').filter(|p| !p.is_empty()) {
+                rules.add(pattern, Compress::No)?;
+            }
+        }
+        Ok(rules)
+    }
+}
+
+/// Options to rewrite the paths of the added entries
+#[derive(clap::Args, Debug)]
+pub struct PathTransforms {
+    /// Rewrite the archive paths with a sed-like expression (can be given several times)
+    ///
+    /// The expression is `s/REGEX/REPLACEMENT/FLAGS`. In REPLACEMENT, `&` is the whole match and
+    /// `\1`..`\9` are the captured groups. FLAGS can be `g` (replace all matches) and
+    /// `i` (case insensitive). Expressions are applied in order.
+    #[arg(long, value_name = "EXPR", value_parser = crate::create::PathTransform::parse, action = clap::ArgAction::Append, help_heading = "Input options")]
+    transform: Vec<crate::create::PathTransform>,
+}
+
+impl From<PathTransforms> for crate::create::PathTransforms {
+    fn from(opt: PathTransforms) -> Self {
+        opt.transform.into_iter().collect()
+    }
+}
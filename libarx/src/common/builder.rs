@@ -1,6 +1,7 @@
 use super::entry::*;
 use super::entry_type::EntryType;
 use super::AllProperties;
+use crate::entry::WhiteoutBuilder;
 use crate::{ArxFormatError, BaseError};
 use jbk::reader::builder::PropertyBuilderTrait;
 use jbk::reader::ByteSlice;
@@ -9,13 +10,21 @@ pub trait Builder {
     type Entry;
 
     fn new(properties: &AllProperties) -> Self;
-    fn create_entry(&self, idx: jbk::EntryIdx, reader: &ByteSlice) -> jbk::Result<Self::Entry>;
+    fn create_entry(
+        &self,
+        idx: jbk::EntryIdx,
+        reader: &ByteSlice,
+    ) -> Result<Self::Entry, BaseError>;
 }
 
 impl Builder for () {
     type Entry = ();
     fn new(_properties: &AllProperties) -> Self {}
-    fn create_entry(&self, _idx: jbk::EntryIdx, _reader: &ByteSlice) -> jbk::Result<Self::Entry> {
+    fn create_entry(
+        &self,
+        _idx: jbk::EntryIdx,
+        _reader: &ByteSlice,
+    ) -> Result<Self::Entry, BaseError> {
         Ok(())
     }
 }
@@ -28,17 +37,22 @@ pub trait FullBuilderTrait {
         &self,
         idx: jbk::EntryIdx,
         reader: &ByteSlice,
-    ) -> jbk::Result<<Self::Entry as EntryDef>::File>;
+    ) -> Result<<Self::Entry as EntryDef>::File, BaseError>;
     fn create_link(
         &self,
         idx: jbk::EntryIdx,
         reader: &ByteSlice,
-    ) -> jbk::Result<<Self::Entry as EntryDef>::Link>;
+    ) -> Result<<Self::Entry as EntryDef>::Link, BaseError>;
     fn create_dir(
         &self,
         idx: jbk::EntryIdx,
         reader: &ByteSlice,
-    ) -> jbk::Result<<Self::Entry as EntryDef>::Dir>;
+    ) -> Result<<Self::Entry as EntryDef>::Dir, BaseError>;
+    fn create_special(
+        &self,
+        idx: jbk::EntryIdx,
+        reader: &ByteSlice,
+    ) -> Result<<Self::Entry as EntryDef>::Special, BaseError>;
 }
 
 impl<F, L, D> FullBuilderTrait for (F, L, D)
@@ -60,7 +74,57 @@ where
         &self,
         idx: jbk::EntryIdx,
         reader: &ByteSlice,
-    ) -> jbk::Result<<Self::Entry as EntryDef>::File> {
+    ) -> Result<<Self::Entry as EntryDef>::File, BaseError> {
+        self.0.create_entry(idx, reader)
+    }
+
+    fn create_link(
+        &self,
+        idx: jbk::EntryIdx,
+        reader: &ByteSlice,
+    ) -> Result<<Self::Entry as EntryDef>::Link, BaseError> {
+        self.1.create_entry(idx, reader)
+    }
+
+    fn create_dir(
+        &self,
+        idx: jbk::EntryIdx,
+        reader: &ByteSlice,
+    ) -> Result<<Self::Entry as EntryDef>::Dir, BaseError> {
+        self.2.create_entry(idx, reader)
+    }
+
+    fn create_special(
+        &self,
+        _idx: jbk::EntryIdx,
+        _reader: &ByteSlice,
+    ) -> Result<<Self::Entry as EntryDef>::Special, BaseError> {
+        Ok(())
+    }
+}
+
+impl<F, L, D, S> FullBuilderTrait for (F, L, D, S)
+where
+    F: Builder,
+    L: Builder,
+    D: Builder,
+    S: Builder,
+{
+    type Entry = (F::Entry, L::Entry, D::Entry, S::Entry);
+
+    fn new(properties: &AllProperties) -> Self {
+        let file_builder = F::new(properties);
+        let link_builder = L::new(properties);
+        let dir_builder = D::new(properties);
+        let special_builder = S::new(properties);
+        (file_builder, link_builder, dir_builder, special_builder)
+    }
+
+    fn create_file(
+        &self,
+        idx: jbk::EntryIdx,
+        reader: &ByteSlice,
+    ) -> Result<<Self::Entry as EntryDef>::File, BaseError> {
         self.0.create_entry(idx, reader)
     }
 
@@ -68,7 +132,7 @@ where
         &self,
         idx: jbk::EntryIdx,
         reader: &ByteSlice,
-    ) -> jbk::Result<<Self::Entry as EntryDef>::Link> {
+    ) -> Result<<Self::Entry as EntryDef>::Link, BaseError> {
         self.1.create_entry(idx, reader)
     }
 
@@ -76,9 +140,17 @@ where
         &self,
         idx: jbk::EntryIdx,
         reader: &ByteSlice,
-    ) -> jbk::Result<<Self::Entry as EntryDef>::Dir> {
+    ) -> Result<<Self::Entry as EntryDef>::Dir, BaseError> {
         self.2.create_entry(idx, reader)
     }
+
+    fn create_special(
+        &self,
+        idx: jbk::EntryIdx,
+        reader: &ByteSlice,
+    ) -> Result<<Self::Entry as EntryDef>::Special, BaseError> {
+        self.3.create_entry(idx, reader)
+    }
 }
 
 pub(crate) struct RealBuilder<B: FullBuilderTrait> {
@@ -87,6 +159,7 @@ pub(crate) struct RealBuilder<B: FullBuilderTrait> {
     first_child_property: jbk::reader::builder::IntProperty,
     nb_children_property: jbk::reader::builder::IntProperty,
     builder: B,
+    whiteout_builder: WhiteoutBuilder,
 }
 
 impl<B> RealBuilder<B>
@@ -101,6 +174,7 @@ where
             first_child_property: properties.dir_first_child_property.clone(),
             nb_children_property: properties.dir_nb_children_property.clone(),
             builder,
+            whiteout_builder: WhiteoutBuilder::new(properties),
         }
     }
 }
@@ -135,6 +209,14 @@ where
                         let entry = self.builder.create_dir(idx, &reader)?;
                         Entry::Dir(range, entry)
                     }
+                    Some(EntryType::Special) => {
+                        let entry = self.builder.create_special(idx, &reader)?;
+                        Entry::Special(entry)
+                    }
+                    Some(EntryType::Whiteout) => {
+                        let entry = self.whiteout_builder.create_entry(idx, &reader)?;
+                        Entry::Whiteout(entry)
+                    }
                     None => return Err(ArxFormatError("Unknown variant").into()),
                 })
             })
@@ -1,10 +1,14 @@
 use std::fmt::Display;
 
+use crate::ArxFormatError;
+
 jbk::variants! {
     EntryType {
         File => "file",
         Dir => "dir",
-        Link => "link"
+        Link => "link",
+        Special => "special",
+        Whiteout => "whiteout"
     }
 }
 
@@ -14,17 +18,78 @@ impl Display for EntryType {
             EntryType::File => write!(f, "file"),
             EntryType::Dir => write!(f, "dir"),
             EntryType::Link => write!(f, "link"),
+            EntryType::Special => write!(f, "special"),
+            EntryType::Whiteout => write!(f, "whiteout"),
+        }
+    }
+}
+
+/// The kind of a special file entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecialKind {
+    CharDevice,
+    BlockDevice,
+    Fifo,
+    Socket,
+}
+
+impl SpecialKind {
+    /// The value stored in the `kind` property.
+    pub(crate) fn to_u64(self) -> u64 {
+        match self {
+            SpecialKind::CharDevice => 0,
+            SpecialKind::BlockDevice => 1,
+            SpecialKind::Fifo => 2,
+            SpecialKind::Socket => 3,
+        }
+    }
+}
+
+impl TryFrom<u64> for SpecialKind {
+    type Error = ArxFormatError;
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(SpecialKind::CharDevice),
+            1 => Ok(SpecialKind::BlockDevice),
+            2 => Ok(SpecialKind::Fifo),
+            3 => Ok(SpecialKind::Socket),
+            _ => Err(ArxFormatError("Unknown special file kind")),
+        }
+    }
+}
+
+impl Display for SpecialKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpecialKind::CharDevice => write!(f, "char"),
+            SpecialKind::BlockDevice => write!(f, "block"),
+            SpecialKind::Fifo => write!(f, "fifo"),
+            SpecialKind::Socket => write!(f, "socket"),
         }
     }
 }
 
 #[cfg(all(not(windows), feature = "fuse"))]
-impl From<EntryType> for fuser::FileType {
-    fn from(t: EntryType) -> Self {
-        match t {
-            EntryType::File => fuser::FileType::RegularFile,
-            EntryType::Dir => fuser::FileType::Directory,
-            EntryType::Link => fuser::FileType::Symlink,
+impl From<SpecialKind> for fuser::FileType {
+    fn from(k: SpecialKind) -> Self {
+        match k {
+            SpecialKind::CharDevice => fuser::FileType::CharDevice,
+            SpecialKind::BlockDevice => fuser::FileType::BlockDevice,
+            SpecialKind::Fifo => fuser::FileType::NamedPipe,
+            SpecialKind::Socket => fuser::FileType::Socket,
         }
     }
 }
+
+/// Encode a device number as stored in the `rdev` property.
+///
+/// Major and minor numbers are stored separately (and not using the `dev_t`
+/// encoding of the platform), so the archive doesn't depend on the system it
+/// has been created on.
+pub(crate) fn encode_rdev(major: u32, minor: u32) -> u64 {
+    ((major as u64) << 32) | minor as u64
+}
+
+pub(crate) fn decode_rdev(rdev: u64) -> (u32, u32) {
+    ((rdev >> 32) as u32, rdev as u32)
+}
@@ -1,15 +1,25 @@
 mod builder;
 mod entry;
 mod entry_type;
+mod names;
 mod properties;
+mod raw_path;
+mod xattrs;
 
 pub(crate) use builder::RealBuilder;
 pub use builder::{Builder, FullBuilderTrait};
 pub use entry::{Entry, EntryDef};
-pub use entry_type::EntryType;
+pub(crate) use entry_type::{decode_rdev, encode_rdev};
+pub use entry_type::{EntryType, SpecialKind};
 use jbk::reader::builder::{BuilderTrait, PropertyBuilderTrait};
 use jbk::reader::Range;
+pub(crate) use names::LocalNames;
+pub(crate) use properties::{read_mtime_nsec, read_optional_array, read_time};
 pub use properties::{AllProperties, Property};
+pub use raw_path::{
+    bytes_to_os_path, escape_bytes, path_from_bytes, path_to_bytes, to_os_path, unescape_str,
+};
+pub use xattrs::Xattrs;
 
 pub const VENDOR_ID: jbk::VendorId = jbk::VendorId::new([0x41, 0x52, 0x58, 0x00]);
 
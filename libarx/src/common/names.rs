@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Lookup (and cache) user and group names in the local passwd and group databases.
+#[derive(Default)]
+pub(crate) struct LocalNames {
+    user_names: Mutex<HashMap<u32, Option<Vec<u8>>>>,
+    group_names: Mutex<HashMap<u32, Option<Vec<u8>>>>,
+    uids: Mutex<HashMap<Vec<u8>, Option<u32>>>,
+    gids: Mutex<HashMap<Vec<u8>, Option<u32>>>,
+}
+
+impl LocalNames {
+    /// The name of the local user `uid`.
+    pub fn user_name(&self, uid: u32) -> Option<Vec<u8>> {
+        self.user_names
+            .lock()
+            .unwrap()
+            .entry(uid)
+            .or_insert_with(|| sys::user_name(uid))
+            .clone()
+    }
+
+    /// The name of the local group `gid`.
+    pub fn group_name(&self, gid: u32) -> Option<Vec<u8>> {
+        self.group_names
+            .lock()
+            .unwrap()
+            .entry(gid)
+            .or_insert_with(|| sys::group_name(gid))
+            .clone()
+    }
+
+    /// The uid of the local user `name`.
+    pub fn uid(&self, name: &[u8]) -> Option<u32> {
+        *self
+            .uids
+            .lock()
+            .unwrap()
+            .entry(name.to_vec())
+            .or_insert_with(|| sys::uid(name))
+    }
+
+    /// The gid of the local group `name`.
+    pub fn gid(&self, name: &[u8]) -> Option<u32> {
+        *self
+            .gids
+            .lock()
+            .unwrap()
+            .entry(name.to_vec())
+            .or_insert_with(|| sys::gid(name))
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::ffi::{CStr, CString};
+    use std::mem::MaybeUninit;
+
+    /// Call a `get*_r` function, growing the buffer as needed, and extract data from the result.
+    fn lookup<T, R>(
+        call: impl Fn(*mut T, *mut libc::c_char, usize, *mut *mut T) -> libc::c_int,
+        extract: impl Fn(&T) -> R,
+    ) -> Option<R> {
+        let mut buf = vec![0 as libc::c_char; 1024];
+        loop {
+            let mut entry = MaybeUninit::<T>::uninit();
+            let mut result = std::ptr::null_mut();
+            let ret = call(entry.as_mut_ptr(), buf.as_mut_ptr(), buf.len(), &mut result);
+            if ret == libc::ERANGE && buf.len() < 1024 * 1024 {
+                buf.resize(buf.len() * 2, 0);
+                continue;
+            }
+            if ret != 0 || result.is_null() {
+                return None;
+            }
+            // SAFETY: `result` points to `entry` which has been filled by `call`.
+            return Some(extract(unsafe { &*result }));
+        }
+    }
+
+    pub fn user_name(uid: u32) -> Option<Vec<u8>> {
+        lookup(
+            |pwd, buf, len, result| unsafe { libc::getpwuid_r(uid, pwd, buf, len, result) },
+            |pwd: &libc::passwd| unsafe { CStr::from_ptr(pwd.pw_name) }.to_bytes().to_vec(),
+        )
+    }
+
+    pub fn group_name(gid: u32) -> Option<Vec<u8>> {
+        lookup(
+            |grp, buf, len, result| unsafe { libc::getgrgid_r(gid, grp, buf, len, result) },
+            |grp: &libc::group| unsafe { CStr::from_ptr(grp.gr_name) }.to_bytes().to_vec(),
+        )
+    }
+
+    pub fn uid(name: &[u8]) -> Option<u32> {
+        let name = CString::new(name).ok()?;
+        lookup(
+            |pwd, buf, len, result| unsafe {
+                libc::getpwnam_r(name.as_ptr(), pwd, buf, len, result)
+            },
+            |pwd: &libc::passwd| pwd.pw_uid,
+        )
+    }
+
+    pub fn gid(name: &[u8]) -> Option<u32> {
+        let name = CString::new(name).ok()?;
+        lookup(
+            |grp, buf, len, result| unsafe {
+                libc::getgrnam_r(name.as_ptr(), grp, buf, len, result)
+            },
+            |grp: &libc::group| grp.gr_gid,
+        )
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    pub fn user_name(_uid: u32) -> Option<Vec<u8>> {
+        None
+    }
+
+    pub fn group_name(_gid: u32) -> Option<Vec<u8>> {
+        None
+    }
+
+    pub fn uid(_name: &[u8]) -> Option<u32> {
+        None
+    }
+
+    pub fn gid(_name: &[u8]) -> Option<u32> {
+        None
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use rustest::{test, *};
+
+    #[test]
+    fn test_root() -> Result {
+        let names = LocalNames::default();
+        assert_eq!(names.user_name(0), Some(b"root".to_vec()));
+        assert_eq!(names.uid(b"root"), Some(0));
+        assert_eq!(names.uid(b"no such user in arx tests"), None);
+        Ok(())
+    }
+}
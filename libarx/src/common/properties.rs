@@ -1,5 +1,6 @@
 use super::EntryType;
 use crate::{ArxFormatError, BaseError};
+use jbk::reader::builder::PropertyBuilderTrait;
 
 jbk::properties! {
     Property {
@@ -14,9 +15,74 @@ jbk::properties! {
         FirstChild:"int" => "first_child",
         NbChildren:"int" => "nb_children",
         Target:"array" => "target",
+        Xattrs:"array" => "xattrs",
+        Inode:"int" => "inode",
+        Nlink:"int" => "nlink",
+        Kind:"int" => "kind",
+        Rdev:"int" => "rdev",
+        MtimeNsec:"int" => "mtime_nsec",
+        Atime:"int" => "atime",
+        Ctime:"int" => "ctime",
+        Btime:"int" => "btime",
+        OwnerName:"array" => "owner_name",
+        GroupName:"array" => "group_name",
+        Digest:"array" => "digest",
+        MetaKey:"array" => "meta_key",
+        MetaValue:"array" => "meta_value",
     }
 }
 
+/// Build the property builder of a property which may not be present in the layout.
+///
+/// Properties added after the initial format are optional, so archives created
+/// before their introduction can still be read.
+macro_rules! optional_layout_builder {
+    ($($args:tt)*) => {
+        (|| -> Result<_, BaseError> { Ok(jbk::layout_builder!($($args)*)) })().ok()
+    };
+}
+
+/// Read an optional time property, stored in nanoseconds since epoch.
+///
+/// Return `None` if the property is not present or if the time is unknown (stored as 0).
+pub(crate) fn read_time(
+    property: &Option<jbk::reader::builder::IntProperty>,
+    reader: &jbk::reader::ByteSlice,
+) -> jbk::Result<Option<u64>> {
+    Ok(match property {
+        Some(property) => Some(property.create(reader)?).filter(|t| *t != 0),
+        None => None,
+    })
+}
+
+/// Read the sub-second part of the modification time (0 if not present).
+pub(crate) fn read_mtime_nsec(
+    property: &Option<jbk::reader::builder::IntProperty>,
+    reader: &jbk::reader::ByteSlice,
+) -> jbk::Result<u32> {
+    Ok(match property {
+        Some(property) => property.create(reader)? as u32,
+        None => 0,
+    })
+}
+
+/// Read an optional array property.
+///
+/// Return `None` if the property is not present or if the value is unknown (stored empty).
+pub(crate) fn read_optional_array(
+    property: &Option<jbk::reader::builder::ArrayProperty>,
+    reader: &jbk::reader::ByteSlice,
+) -> jbk::Result<Option<Vec<u8>>> {
+    Ok(match property {
+        Some(property) => {
+            let mut name = jbk::SmallBytes::new();
+            property.create(reader)?.resolve_to_vec(&mut name)?;
+            Some(name.to_vec()).filter(|n| !n.is_empty())
+        }
+        None => None,
+    })
+}
+
 pub struct AllProperties {
     pub store: jbk::reader::EntryStore,
     pub path_property: jbk::reader::builder::ArrayProperty,
@@ -31,6 +97,18 @@ pub struct AllProperties {
     pub dir_first_child_property: jbk::reader::builder::IntProperty,
     pub dir_nb_children_property: jbk::reader::builder::IntProperty,
     pub link_target_property: jbk::reader::builder::ArrayProperty,
+    pub xattrs_property: Option<jbk::reader::builder::ArrayProperty>,
+    pub file_inode_property: Option<jbk::reader::builder::IntProperty>,
+    pub file_nlink_property: Option<jbk::reader::builder::IntProperty>,
+    pub special_kind_property: Option<jbk::reader::builder::IntProperty>,
+    pub special_rdev_property: Option<jbk::reader::builder::IntProperty>,
+    pub mtime_nsec_property: Option<jbk::reader::builder::IntProperty>,
+    pub atime_property: Option<jbk::reader::builder::IntProperty>,
+    pub ctime_property: Option<jbk::reader::builder::IntProperty>,
+    pub btime_property: Option<jbk::reader::builder::IntProperty>,
+    pub owner_name_property: Option<jbk::reader::builder::ArrayProperty>,
+    pub group_name_property: Option<jbk::reader::builder::ArrayProperty>,
+    pub file_digest_property: Option<jbk::reader::builder::ArrayProperty>,
 }
 
 impl AllProperties {
@@ -39,9 +117,13 @@ impl AllProperties {
         value_storage: &jbk::reader::ValueStorage,
     ) -> Result<Self, BaseError> {
         let layout = store.layout();
-        if layout.variant_len() != 3 {
-            return Err(ArxFormatError("Layout must contain 3 variants").into());
-        }
+        // Archives created before the introduction of special files have only 3 variants.
+        // Only patch archives have the whiteout variant.
+        let has_special = match layout.variant_len() {
+            3 => false,
+            4 | 5 => true,
+            _ => return Err(ArxFormatError("Layout must contain 3, 4 or 5 variants").into()),
+        };
         let path_property = jbk::layout_builder!(
             layout[common][Property::Name],
             value_storage,
@@ -98,6 +180,72 @@ impl AllProperties {
             value_storage,
             ArxFormatError
         );
+        let xattrs_property = optional_layout_builder!(
+            layout[common][Property::Xattrs],
+            value_storage,
+            ArxFormatError
+        );
+        let file_inode_property = optional_layout_builder!(
+            layout[EntryType::File][Property::Inode],
+            value_storage,
+            ArxFormatError
+        );
+        let file_nlink_property = optional_layout_builder!(
+            layout[EntryType::File][Property::Nlink],
+            value_storage,
+            ArxFormatError
+        );
+        let (special_kind_property, special_rdev_property) = if has_special {
+            (
+                Some(jbk::layout_builder!(
+                    layout[EntryType::Special][Property::Kind],
+                    value_storage,
+                    ArxFormatError
+                )),
+                Some(jbk::layout_builder!(
+                    layout[EntryType::Special][Property::Rdev],
+                    value_storage,
+                    ArxFormatError
+                )),
+            )
+        } else {
+            (None, None)
+        };
+        let mtime_nsec_property = optional_layout_builder!(
+            layout[common][Property::MtimeNsec],
+            value_storage,
+            ArxFormatError
+        );
+        let atime_property = optional_layout_builder!(
+            layout[common][Property::Atime],
+            value_storage,
+            ArxFormatError
+        );
+        let ctime_property = optional_layout_builder!(
+            layout[common][Property::Ctime],
+            value_storage,
+            ArxFormatError
+        );
+        let btime_property = optional_layout_builder!(
+            layout[common][Property::Btime],
+            value_storage,
+            ArxFormatError
+        );
+        let owner_name_property = optional_layout_builder!(
+            layout[common][Property::OwnerName],
+            value_storage,
+            ArxFormatError
+        );
+        let group_name_property = optional_layout_builder!(
+            layout[common][Property::GroupName],
+            value_storage,
+            ArxFormatError
+        );
+        let file_digest_property = optional_layout_builder!(
+            layout[EntryType::File][Property::Digest],
+            value_storage,
+            ArxFormatError
+        );
         Ok(Self {
             store,
             path_property,
@@ -112,6 +260,18 @@ impl AllProperties {
             dir_first_child_property,
             dir_nb_children_property,
             link_target_property,
+            xattrs_property,
+            file_inode_property,
+            file_nlink_property,
+            special_kind_property,
+            special_rdev_property,
+            mtime_nsec_property,
+            atime_property,
+            ctime_property,
+            btime_property,
+            owner_name_property,
+            group_name_property,
+            file_digest_property,
         })
     }
 }
@@ -0,0 +1,136 @@
+//! Lossless conversion between raw (non UTF-8) names and `crate::Path`.
+//!
+//! Names are stored as raw bytes in the archive but `crate::Path` is UTF-8 only.
+//! Bytes which are not part of valid UTF-8 sequences are mapped to the code points
+//! `U+10FF80..=U+10FFFF` (at the end of the last private use plane).
+//! Characters already in this range are escaped the same way (byte per byte), so
+//! the mapping is bijective and any byte string round-trips.
+
+use std::borrow::Cow;
+
+const ESCAPE_BASE: u32 = 0x10FF00;
+
+fn is_escape_char(c: char) -> bool {
+    (ESCAPE_BASE + 0x80..=ESCAPE_BASE + 0xFF).contains(&(c as u32))
+}
+
+fn escape_byte(byte: u8, output: &mut String) {
+    output.push(char::from_u32(ESCAPE_BASE + byte as u32).expect("Escape char is valid"));
+}
+
+/// Convert raw bytes to a string, escaping invalid UTF-8 bytes.
+pub fn escape_bytes(bytes: &[u8]) -> Cow<'_, str> {
+    if let Ok(s) = std::str::from_utf8(bytes) {
+        if !s.chars().any(is_escape_char) {
+            return Cow::Borrowed(s);
+        }
+    }
+    let mut output = String::with_capacity(bytes.len() + 8);
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if is_escape_char(c) {
+                let mut buf = [0; 4];
+                for byte in c.encode_utf8(&mut buf).bytes() {
+                    escape_byte(byte, &mut output);
+                }
+            } else {
+                output.push(c);
+            }
+        }
+        for byte in chunk.invalid() {
+            escape_byte(*byte, &mut output);
+        }
+    }
+    Cow::Owned(output)
+}
+
+/// Convert a string produced by `escape_bytes` back to the raw bytes.
+pub fn unescape_str(s: &str) -> Cow<'_, [u8]> {
+    if !s.chars().any(is_escape_char) {
+        return Cow::Borrowed(s.as_bytes());
+    }
+    let mut output = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        if is_escape_char(c) {
+            output.push((c as u32 - ESCAPE_BASE) as u8);
+        } else {
+            let mut buf = [0; 4];
+            output.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+        }
+    }
+    Cow::Owned(output)
+}
+
+/// Build a `crate::PathBuf` from a raw path (as stored in the archive).
+pub fn path_from_bytes(bytes: &[u8]) -> crate::PathBuf {
+    escape_bytes(bytes).into_owned().into()
+}
+
+/// The raw bytes of `path` (as stored in the archive).
+pub fn path_to_bytes(path: &crate::Path) -> Cow<'_, [u8]> {
+    unescape_str(path.as_str())
+}
+
+/// Convert raw bytes to a file system path.
+///
+/// This is lossless on unix. On other platforms, invalid UTF-8 is replaced.
+pub fn bytes_to_os_path(bytes: &[u8]) -> std::path::PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        std::ffi::OsStr::from_bytes(bytes).into()
+    }
+    #[cfg(not(unix))]
+    {
+        String::from_utf8_lossy(bytes).as_ref().into()
+    }
+}
+
+/// Convert `path` to a file system path relative to `base`, restoring the raw names.
+pub fn to_os_path(path: &crate::Path, base: &std::path::Path) -> std::path::PathBuf {
+    #[cfg(unix)]
+    {
+        if path.as_str().is_empty() {
+            base.to_path_buf()
+        } else {
+            base.join(bytes_to_os_path(&path_to_bytes(path)))
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        path.to_path(base)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustest::{test, *};
+
+    #[test]
+    fn test_utf8_is_unchanged() -> Result {
+        assert!(matches!(
+            escape_bytes(b"foo/bar.txt"),
+            Cow::Borrowed("foo/bar.txt")
+        ));
+        assert!(matches!(unescape_str("été"), Cow::Borrowed(b) if b == "été".as_bytes()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip() -> Result {
+        let names: [&[u8]; 5] = [
+            b"caf\xe9",
+            b"\xff\xfe/\x80",
+            "\u{10FF80}\u{10FFFF}".as_bytes(),
+            b"mixed \xe9t\xc3\xa9",
+            b"\xf4\x8f\xbe",
+        ];
+        for name in names {
+            let escaped = escape_bytes(name);
+            assert!(!escaped.is_empty());
+            assert_eq!(unescape_str(&escaped).as_ref(), name);
+        }
+        Ok(())
+    }
+}
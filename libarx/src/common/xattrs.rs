@@ -0,0 +1,120 @@
+use jbk::reader::builder::PropertyBuilderTrait;
+use jbk::reader::ByteSlice;
+use std::collections::BTreeMap;
+
+/// The extended attributes of an entry.
+///
+/// Attributes are kept sorted by name, so the serialized form of a set of
+/// attributes doesn't depend on the order they have been read.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Xattrs(BTreeMap<Vec<u8>, Vec<u8>>);
+
+impl Xattrs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn insert(&mut self, name: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) {
+        self.0.insert(name.into(), value.into());
+    }
+
+    pub fn get(&self, name: &[u8]) -> Option<&[u8]> {
+        self.0.get(name).map(|v| v.as_slice())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &[u8])> {
+        self.0.iter().map(|(k, v)| (k.as_slice(), v.as_slice()))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &[u8]> {
+        self.0.keys().map(|k| k.as_slice())
+    }
+
+    /// Serialize the attributes as stored in the `xattrs` property.
+    ///
+    /// Each attribute is stored as its name, a nul byte, the size of the value
+    /// (u32, little endian) and the value itself.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        for (name, value) in &self.0 {
+            data.extend_from_slice(name);
+            data.push(0);
+            data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            data.extend_from_slice(value);
+        }
+        data
+    }
+
+    pub(crate) fn from_bytes(mut data: &[u8]) -> Option<Self> {
+        let mut xattrs = Self::new();
+        while !data.is_empty() {
+            let name_end = data.iter().position(|c| *c == 0)?;
+            let (name, left) = data.split_at(name_end);
+            let left = &left[1..];
+            if left.len() < 4 {
+                return None;
+            }
+            let (size, left) = left.split_at(4);
+            let size = u32::from_le_bytes(size.try_into().unwrap()) as usize;
+            if left.len() < size {
+                return None;
+            }
+            let (value, left) = left.split_at(size);
+            xattrs.insert(name, value);
+            data = left;
+        }
+        Some(xattrs)
+    }
+
+    /// Read the attributes of the entry from the `xattrs` property.
+    ///
+    /// Malformed attributes are reported and ignored as they should not prevent
+    /// to read the entry itself.
+    pub(crate) fn read(
+        property: &jbk::reader::builder::ArrayProperty,
+        reader: &ByteSlice,
+    ) -> jbk::Result<Self> {
+        let mut data = jbk::SmallBytes::new();
+        property.create(reader)?.resolve_to_vec(&mut data)?;
+        Ok(Self::from_bytes(&data).unwrap_or_else(|| {
+            log::warn!("Invalid extended attributes, ignoring them");
+            Self::new()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustest::{test, *};
+
+    #[test]
+    fn test_xattrs_roundtrip() -> Result {
+        let mut xattrs = Xattrs::new();
+        xattrs.insert(b"user.foo".as_slice(), b"bar".as_slice());
+        xattrs.insert(b"security.capability".as_slice(), vec![1, 0, 0, 2, 0]);
+        xattrs.insert(b"user.empty".as_slice(), vec![]);
+        let data = xattrs.to_bytes();
+        assert_eq!(Xattrs::from_bytes(&data), Some(xattrs));
+        assert_eq!(Xattrs::from_bytes(&[]), Some(Xattrs::new()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_xattrs_truncated() -> Result {
+        let mut xattrs = Xattrs::new();
+        xattrs.insert(b"user.foo".as_slice(), b"bar".as_slice());
+        let data = xattrs.to_bytes();
+        assert_eq!(Xattrs::from_bytes(&data[..data.len() - 1]), None);
+        assert_eq!(Xattrs::from_bytes(b"user.foo"), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,373 @@
+use super::fs_adder::SMALL_FILE_SIZE;
+use super::{Edits, EntryKind, EntryTrait, SimpleCreator, Void};
+use crate::{
+    escape_bytes, Arx, ArxError, ArxFormatError, CommonEntry, CreatorError, Dir, FileEntry,
+    FullBuilder, IncoherentStructure, InputError, Link, Special, Walker, Whiteout, Xattrs,
+};
+use jbk::creator::{ContentAdder, InputReader};
+use jbk::reader::MayMissPack;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Cursor;
+
+/// How to handle an entry whose path has already been added from another archive.
+///
+/// Directories present in several archives are always merged, the metadata of the
+/// directory added first being kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cmd_utils", derive(clap::ValueEnum))]
+pub enum Conflict {
+    /// Stop with an error
+    #[default]
+    Error,
+    /// Keep the entry of the first archive
+    FirstWins,
+    /// Keep the entry of the last archive (see `ArxAdder::add_arxs`)
+    LastWins,
+}
+
+/// An entry read from an existing archive.
+struct ArxEntry {
+    path: crate::PathBuf,
+    kind: EntryKind,
+    uid: u64,
+    gid: u64,
+    owner_name: Option<Vec<u8>>,
+    group_name: Option<Vec<u8>>,
+    mode: u64,
+    mtime: u64,
+    mtime_nsec: u32,
+    atime: u64,
+    ctime: u64,
+    btime: u64,
+    xattrs: Xattrs,
+}
+
+impl ArxEntry {
+    fn new(path: crate::PathBuf, mode: u64, kind: EntryKind, entry: &impl CommonEntry) -> Self {
+        Self {
+            path,
+            kind,
+            uid: entry.owner() as u64,
+            gid: entry.group() as u64,
+            owner_name: entry.owner_name().map(<[u8]>::to_vec),
+            group_name: entry.group_name().map(<[u8]>::to_vec),
+            mode,
+            mtime: entry.mtime(),
+            mtime_nsec: entry.mtime_nsec(),
+            atime: entry.atime_ns().unwrap_or(0),
+            ctime: entry.ctime_ns().unwrap_or(0),
+            btime: entry.btime_ns().unwrap_or(0),
+            xattrs: entry.xattrs().clone(),
+        }
+    }
+}
+
+impl EntryTrait for ArxEntry {
+    fn kind(&self) -> Result<Option<EntryKind>, CreatorError> {
+        Ok(Some(self.kind.clone()))
+    }
+    fn path(&self) -> &crate::Path {
+        &self.path
+    }
+    fn uid(&self) -> u64 {
+        self.uid
+    }
+    fn gid(&self) -> u64 {
+        self.gid
+    }
+    fn owner_name(&self) -> Option<&[u8]> {
+        self.owner_name.as_deref()
+    }
+    fn group_name(&self) -> Option<&[u8]> {
+        self.group_name.as_deref()
+    }
+    fn mode(&self) -> u64 {
+        self.mode
+    }
+    fn mtime(&self) -> u64 {
+        self.mtime
+    }
+    fn mtime_nsec(&self) -> u32 {
+        self.mtime_nsec
+    }
+    fn atime_ns(&self) -> u64 {
+        self.atime
+    }
+    fn ctime_ns(&self) -> u64 {
+        self.ctime
+    }
+    fn btime_ns(&self) -> u64 {
+        self.btime
+    }
+    fn xattrs(&self) -> Option<&Xattrs> {
+        Some(&self.xattrs)
+    }
+}
+
+/// Read the content of `file` from `arx`, to add it again.
+fn read_content(
+    arx: &Arx,
+    file: &FileEntry,
+    path: &crate::Path,
+) -> Result<Box<dyn InputReader>, CreatorError> {
+    let bytes = match arx
+        .get_bytes(file.content())
+        .map_err(ArxError::from)?
+        .and_then(|m| m.transpose())
+        .ok_or(ArxFormatError("Content address should be valid"))
+        .map_err(ArxError::from)?
+    {
+        MayMissPack::FOUND(bytes) => bytes,
+        MayMissPack::MISSING(pack_info) => {
+            return Err(InputError(format!(
+                "Missing pack {} for {path}. Declared location is {}",
+                pack_info.uuid, pack_info.pack_location
+            ))
+            .into())
+        }
+    };
+    let size = file.size().into_u64();
+    if size < SMALL_FILE_SIZE {
+        let mut content = Vec::with_capacity(size as usize);
+        std::io::copy(&mut bytes.stream(), &mut content)?;
+        Ok(Box::new(Cursor::new(content)))
+    } else {
+        let mut content = tempfile::tempfile()?;
+        std::io::copy(&mut bytes.stream(), &mut content)?;
+        Ok(Box::new(jbk::creator::InputFile::new_range(
+            content,
+            0,
+            Some(size),
+        )?))
+    }
+}
+
+/// Add the entries of existing archives to a `SimpleCreator`.
+pub struct ArxAdder<'a> {
+    creator: &'a mut SimpleCreator,
+    conflict: Conflict,
+    edits: Option<&'a Edits>,
+    reuse_packs: bool,
+    /// The added paths, with true for directories.
+    added: HashMap<crate::PathBuf, bool>,
+}
+
+impl<'a> ArxAdder<'a> {
+    pub fn new(creator: &'a mut SimpleCreator, conflict: Conflict) -> Self {
+        Self {
+            creator,
+            conflict,
+            edits: None,
+            reuse_packs: false,
+            added: HashMap::new(),
+        }
+    }
+
+    /// Reference the content packs of the added archives instead of storing their contents
+    /// again (see `SimpleCreator::reuse_content`).
+    ///
+    /// Reused contents keep the compression they have in the added archives.
+    pub fn set_reuse_packs(&mut self, reuse_packs: bool) {
+        self.reuse_packs = reuse_packs;
+    }
+
+    /// Apply `edits` to the entries read from the archives.
+    ///
+    /// Deleted entries are not added, so their content is not stored in the new archive.
+    pub fn set_edits(&mut self, edits: &'a Edits) {
+        self.edits = Some(edits);
+    }
+
+    /// The path and mode of `entry` (read at `path`) once edited, or `None` if it is deleted.
+    fn edit(
+        &self,
+        path: &crate::Path,
+        is_dir: bool,
+        entry: &impl CommonEntry,
+    ) -> Option<(crate::PathBuf, u64)> {
+        let mode = entry.rights() as u64;
+        match self.edits {
+            None => Some((path.to_relative_path_buf(), mode)),
+            Some(edits) => edits.apply(path, is_dir, mode),
+        }
+    }
+
+    /// Add all the entries of `arx` (opened from `arx_path`) under `prefix` (which may be empty).
+    ///
+    /// Entries conflicting with already added ones are skipped with `Conflict::FirstWins`
+    /// and `Conflict::LastWins`.
+    pub fn add_arx(&mut self, arx: &Arx, arx_path: &std::path::Path, prefix: &crate::Path) -> Void {
+        let walker = ArxWalker {
+            arx,
+            arx_path,
+            adder: RefCell::new(self),
+            hard_links: Default::default(),
+        };
+        Walker::new(arx, prefix.to_relative_path_buf()).run(&walker)
+    }
+
+    /// Add all the entries of the archives (with the path they are opened from),
+    /// each one under its prefix.
+    ///
+    /// With `Conflict::LastWins`, archives are added from the last one.
+    pub fn add_arxs(&mut self, inputs: &[(Arx, std::path::PathBuf, crate::PathBuf)]) -> Void {
+        if self.conflict == Conflict::LastWins {
+            for (arx, arx_path, prefix) in inputs.iter().rev() {
+                self.add_arx(arx, arx_path, prefix)?;
+            }
+        } else {
+            for (arx, arx_path, prefix) in inputs {
+                self.add_arx(arx, arx_path, prefix)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Tell if the entry at `path` must be added, according to the conflict policy.
+    fn accept(&mut self, path: &crate::Path, is_dir: bool) -> Result<bool, CreatorError> {
+        match self.added.get(path) {
+            None => {
+                self.added.insert(path.to_relative_path_buf(), is_dir);
+                Ok(true)
+            }
+            Some(true) if is_dir => Ok(true),
+            Some(_) => match self.conflict {
+                Conflict::Error => Err(IncoherentStructure(format!(
+                    "{path} is present in several archives"
+                ))
+                .into()),
+                Conflict::FirstWins | Conflict::LastWins => {
+                    log::info!("{path} is already added, skipping");
+                    Ok(false)
+                }
+            },
+        }
+    }
+}
+
+struct ArxWalker<'a, 'b> {
+    arx: &'b Arx,
+    arx_path: &'b std::path::Path,
+    adder: RefCell<&'b mut ArxAdder<'a>>,
+    /// The path of the first added entry of each hard linked inode.
+    hard_links: RefCell<HashMap<jbk::EntryIdx, crate::PathBuf>>,
+}
+
+impl crate::walk::Operator<crate::PathBuf, FullBuilder> for ArxWalker<'_, '_> {
+    type Error = CreatorError;
+    fn on_start(&self, _current_path: &mut crate::PathBuf) -> Void {
+        Ok(())
+    }
+    fn on_stop(&self, _current_path: &mut crate::PathBuf) -> Void {
+        Ok(())
+    }
+    fn on_directory_enter(
+        &self,
+        current_path: &mut crate::PathBuf,
+        dir: &Dir,
+    ) -> Result<bool, CreatorError> {
+        current_path.push(escape_bytes(dir.path()).as_ref());
+        let mut adder = self.adder.borrow_mut();
+        let Some((path, mode)) = adder.edit(current_path, true, dir) else {
+            return Ok(false);
+        };
+        if !adder.accept(&path, true)? {
+            return Ok(false);
+        }
+        adder
+            .creator
+            .add_entry(&ArxEntry::new(path, mode, EntryKind::Dir, dir))?;
+        Ok(true)
+    }
+    fn on_directory_exit(&self, current_path: &mut crate::PathBuf, _dir: &Dir) -> Void {
+        current_path.pop();
+        Ok(())
+    }
+    fn on_file(&self, current_path: &mut crate::PathBuf, file: &FileEntry) -> Void {
+        let path = current_path.join(escape_bytes(file.path()).as_ref());
+        let mut adder = self.adder.borrow_mut();
+        let Some((path, mode)) = adder.edit(&path, false, file) else {
+            return Ok(());
+        };
+        if !adder.accept(&path, false)? {
+            return Ok(());
+        }
+        let first_link = if file.nlink() > 1 {
+            self.hard_links.borrow().get(&file.inode()).cloned()
+        } else {
+            None
+        };
+        let kind = match first_link {
+            Some(target) => EntryKind::HardLink(target),
+            None => {
+                let reused = if adder.reuse_packs {
+                    adder.creator.reuse_content(self.arx, self.arx_path, file)?
+                } else {
+                    None
+                };
+                let content_address = match reused {
+                    Some(content_address) => content_address,
+                    None => {
+                        let content = read_content(self.arx, file, &path)?;
+                        adder
+                            .creator
+                            .adder()
+                            .add_content(content, jbk::creator::CompHint::Detect)?
+                    }
+                };
+                if file.nlink() > 1 {
+                    self.hard_links
+                        .borrow_mut()
+                        .insert(file.inode(), path.clone());
+                }
+                EntryKind::File(file.size(), content_address)
+            }
+        };
+        adder
+            .creator
+            .add_entry(&ArxEntry::new(path, mode, kind, file))
+    }
+    fn on_link(&self, current_path: &mut crate::PathBuf, link: &Link) -> Void {
+        let path = current_path.join(escape_bytes(link.path()).as_ref());
+        let mut adder = self.adder.borrow_mut();
+        let Some((path, mode)) = adder.edit(&path, false, link) else {
+            return Ok(());
+        };
+        if !adder.accept(&path, false)? {
+            return Ok(());
+        }
+        let kind = EntryKind::Link(link.target().into());
+        adder
+            .creator
+            .add_entry(&ArxEntry::new(path, mode, kind, link))
+    }
+    fn on_special(&self, current_path: &mut crate::PathBuf, special: &Special) -> Void {
+        let path = current_path.join(escape_bytes(special.path()).as_ref());
+        let mut adder = self.adder.borrow_mut();
+        let Some((path, mode)) = adder.edit(&path, false, special) else {
+            return Ok(());
+        };
+        if !adder.accept(&path, false)? {
+            return Ok(());
+        }
+        let kind = EntryKind::Special(special.kind(), special.major(), special.minor());
+        adder
+            .creator
+            .add_entry(&ArxEntry::new(path, mode, kind, special))
+    }
+
+    fn on_whiteout(&self, current_path: &mut crate::PathBuf, whiteout: &Whiteout) -> Void {
+        let path = current_path.join(escape_bytes(whiteout.path()).as_ref());
+        let mut adder = self.adder.borrow_mut();
+        let Some((path, mode)) = adder.edit(&path, false, whiteout) else {
+            return Ok(());
+        };
+        if !adder.accept(&path, false)? {
+            return Ok(());
+        }
+        adder
+            .creator
+            .add_entry(&ArxEntry::new(path, mode, EntryKind::Whiteout, whiteout))
+    }
+}
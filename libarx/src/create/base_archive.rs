@@ -0,0 +1,331 @@
+use super::fs_adder::{DetectedEntryKind, ReadEntry};
+use super::{EntryKind, EntryTrait};
+use crate::common::LocalNames;
+use crate::{
+    escape_bytes, Arx, ArxError, CommonEntry, CreatorError, Dir, Entry, FileEntry, FullBuilder,
+    Link, QueryError, Walker,
+};
+use bstr::ByteVec;
+use std::cell::RefCell;
+use std::collections::HashSet;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+/// How to detect that a file has changed since the base archive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cmd_utils", derive(clap::ValueEnum))]
+pub enum ChangeDetection {
+    /// Compare size and modification time
+    #[default]
+    Metadata,
+    /// Compare the BLAKE3 digest of the content (files without stored digest are changed)
+    Checksum,
+}
+
+/// The state of an entry compared to the base archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Unchanged,
+    Modified,
+    Added,
+    /// The entry (and all its children) is in the base archive but not added anymore.
+    Removed,
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Unchanged => "unchanged",
+                Self::Modified => "modified",
+                Self::Added => "added",
+                Self::Removed => "removed",
+            }
+        )
+    }
+}
+
+/// The changes between a base archive and the added entries, in the order they are found.
+#[derive(Debug, Default)]
+pub struct ChangeReport {
+    pub entries: Vec<(crate::PathBuf, Change)>,
+}
+
+impl ChangeReport {
+    pub fn count(&self, change: Change) -> usize {
+        self.entries.iter().filter(|(_, c)| *c == change).count()
+    }
+}
+
+/// An archive the added entries are compared to (see `FsAdder::set_base_archive`).
+pub struct BaseArchive {
+    arx: Arx,
+    /// The file the archive is opened from, to reference its content packs.
+    path: std::path::PathBuf,
+    detection: ChangeDetection,
+    /// The archive paths of the compared entries.
+    seen: HashSet<crate::PathBuf>,
+    report: ChangeReport,
+}
+
+fn same_mtime(attr: &std::fs::Metadata, entry: &impl CommonEntry) -> bool {
+    let Some(mtime) = attr
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+    else {
+        return false;
+    };
+    mtime.as_secs() == entry.mtime() && mtime.subsec_nanos() == entry.mtime_nsec()
+}
+
+/// Whether `entry` is stored with the metadata read from the filesystem: owner, group and their
+/// names, rights, modification time and extended attributes.
+#[cfg(unix)]
+fn same_metadata(read_entry: &ReadEntry, names: &LocalNames, entry: &impl CommonEntry) -> bool {
+    let attr = &read_entry.attr;
+    attr.uid() == entry.owner()
+        && attr.gid() == entry.group()
+        && names.user_name(attr.uid()).as_deref() == entry.owner_name()
+        && names.group_name(attr.gid()).as_deref() == entry.group_name()
+        && attr.mode() & 0o7777 == entry.rights() as u32
+        && same_mtime(attr, entry)
+        && read_entry.xattrs == *entry.xattrs()
+}
+
+#[cfg(not(unix))]
+fn same_metadata(read_entry: &ReadEntry, _names: &LocalNames, entry: &impl CommonEntry) -> bool {
+    same_mtime(&read_entry.attr, entry) && read_entry.xattrs == *entry.xattrs()
+}
+
+impl BaseArchive {
+    pub fn open(
+        path: impl AsRef<std::path::Path>,
+        detection: ChangeDetection,
+    ) -> Result<Self, ArxError> {
+        Ok(Self {
+            arx: Arx::new(&path)?,
+            path: path.as_ref().to_path_buf(),
+            detection,
+            seen: HashSet::new(),
+            report: Default::default(),
+        })
+    }
+
+    pub fn arx(&self) -> &Arx {
+        &self.arx
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    fn same_content(
+        &self,
+        read_entry: &mut ReadEntry,
+        file: &FileEntry,
+    ) -> Result<bool, CreatorError> {
+        let DetectedEntryKind::File(size, _) = &read_entry.kind else {
+            return Ok(false);
+        };
+        if *size != file.size().into_u64() {
+            return Ok(false);
+        }
+        Ok(match self.detection {
+            ChangeDetection::Metadata => same_mtime(&read_entry.attr, file),
+            ChangeDetection::Checksum => {
+                let Some(digest) = file.digest() else {
+                    return Ok(false);
+                };
+                // The content is read only for the files of the same size.
+                read_entry.read_content()?;
+                read_entry.digest.is_some_and(|d| d.as_slice() == digest)
+            }
+        })
+    }
+
+    /// Compare the entry read from the filesystem with the entry at `arx_path` in the base archive.
+    ///
+    /// For files with the same content, the base file is also returned, so its content can be
+    /// reused (even if the metadata changed).
+    pub(crate) fn compare(
+        &mut self,
+        arx_path: &crate::Path,
+        read_entry: &mut ReadEntry,
+        names: &LocalNames,
+    ) -> Result<(Change, Option<FileEntry>), CreatorError> {
+        let base_entry = match self.arx.get_entry::<FullBuilder>(arx_path) {
+            Ok(entry) => Some(entry),
+            Err(QueryError::PathNotFound(_)) => None,
+            Err(e) => return Err(ArxError::from(e).into()),
+        };
+        let mut same_file = None;
+        let change = match (&read_entry.kind, base_entry) {
+            (_, None) => Change::Added,
+            (DetectedEntryKind::File(..), Some(Entry::File(file))) => {
+                if self.same_content(read_entry, &file)? {
+                    let change = if same_metadata(read_entry, names, &file) {
+                        Change::Unchanged
+                    } else {
+                        Change::Modified
+                    };
+                    same_file = Some(file);
+                    change
+                } else {
+                    Change::Modified
+                }
+            }
+            (DetectedEntryKind::Dir, Some(Entry::Dir(_, dir))) => {
+                if same_metadata(read_entry, names, &dir) {
+                    Change::Unchanged
+                } else {
+                    Change::Modified
+                }
+            }
+            (DetectedEntryKind::Link(path), Some(Entry::Link(link))) => {
+                let target = std::fs::read_link(path)?;
+                if Vec::from_path_lossy(&target).as_ref() == link.target()
+                    && same_metadata(read_entry, names, &link)
+                {
+                    Change::Unchanged
+                } else {
+                    Change::Modified
+                }
+            }
+            (DetectedEntryKind::Special(kind, major, minor), Some(Entry::Special(special))) => {
+                if (*kind, *major, *minor) == (special.kind(), special.major(), special.minor())
+                    && same_metadata(read_entry, names, &special)
+                {
+                    Change::Unchanged
+                } else {
+                    Change::Modified
+                }
+            }
+            _ => Change::Modified,
+        };
+        self.seen.insert(arx_path.to_relative_path_buf());
+        // The parents of the entry are in the new archive, even if they are created implicitly
+        // (by a transform or by keeping the parents of the input paths).
+        let mut parent = arx_path.parent();
+        while let Some(path) = parent.filter(|p| !p.as_str().is_empty()) {
+            if !self.seen.insert(path.to_relative_path_buf()) {
+                break;
+            }
+            parent = path.parent();
+        }
+        self.report
+            .entries
+            .push((arx_path.to_relative_path_buf(), change));
+        Ok((change, same_file))
+    }
+
+    /// Add the entries of the base archive which have not been compared as removed and
+    /// return the report.
+    pub fn finish(self) -> Result<ChangeReport, ArxError> {
+        let collector = RemovedCollector {
+            seen: &self.seen,
+            report: RefCell::new(self.report),
+        };
+        Walker::new(&self.arx, Default::default()).run(&collector)?;
+        Ok(collector.report.into_inner())
+    }
+}
+
+struct RemovedCollector<'a> {
+    seen: &'a HashSet<crate::PathBuf>,
+    report: RefCell<ChangeReport>,
+}
+
+impl RemovedCollector<'_> {
+    /// Record the entry as removed if it has not been seen. Return true if it has been seen.
+    fn check(&self, path: crate::PathBuf) -> bool {
+        if self.seen.contains(&path) {
+            true
+        } else {
+            self.report
+                .borrow_mut()
+                .entries
+                .push((path, Change::Removed));
+            false
+        }
+    }
+}
+
+impl crate::walk::Operator<crate::PathBuf, FullBuilder> for RemovedCollector<'_> {
+    type Error = ArxError;
+    fn on_start(&self, _current_path: &mut crate::PathBuf) -> Result<(), ArxError> {
+        Ok(())
+    }
+    fn on_stop(&self, _current_path: &mut crate::PathBuf) -> Result<(), ArxError> {
+        Ok(())
+    }
+    fn on_directory_enter(
+        &self,
+        current_path: &mut crate::PathBuf,
+        dir: &Dir,
+    ) -> Result<bool, ArxError> {
+        current_path.push(escape_bytes(dir.path()).as_ref());
+        // Children of a removed directory are not reported.
+        Ok(self.check(current_path.clone()))
+    }
+    fn on_directory_exit(
+        &self,
+        current_path: &mut crate::PathBuf,
+        _dir: &Dir,
+    ) -> Result<(), ArxError> {
+        current_path.pop();
+        Ok(())
+    }
+    fn on_file(&self, current_path: &mut crate::PathBuf, file: &FileEntry) -> Result<(), ArxError> {
+        self.check(current_path.join(escape_bytes(file.path()).as_ref()));
+        Ok(())
+    }
+    fn on_link(&self, current_path: &mut crate::PathBuf, link: &Link) -> Result<(), ArxError> {
+        self.check(current_path.join(escape_bytes(link.path()).as_ref()));
+        Ok(())
+    }
+    fn on_special(
+        &self,
+        current_path: &mut crate::PathBuf,
+        special: &crate::Special,
+    ) -> Result<(), ArxError> {
+        self.check(current_path.join(escape_bytes(special.path()).as_ref()));
+        Ok(())
+    }
+}
+
+/// An entry marking `path` as deleted from the base archive (see `Layers`).
+///
+/// Whiteouts can only be added to patch archives (see `SimpleCreator::enable_whiteouts`).
+pub struct Whiteout {
+    path: crate::PathBuf,
+}
+
+impl Whiteout {
+    pub fn new(path: crate::PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl EntryTrait for Whiteout {
+    fn kind(&self) -> Result<Option<EntryKind>, CreatorError> {
+        Ok(Some(EntryKind::Whiteout))
+    }
+    fn path(&self) -> &crate::Path {
+        &self.path
+    }
+    fn uid(&self) -> u64 {
+        0
+    }
+    fn gid(&self) -> u64 {
+        0
+    }
+    fn mode(&self) -> u64 {
+        0
+    }
+    fn mtime(&self) -> u64 {
+        0
+    }
+}
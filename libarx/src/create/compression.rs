@@ -0,0 +1,165 @@
+use crate::InputError;
+use jbk::creator::CompHint;
+
+/// Choose, for each file, if its content must be compressed.
+///
+/// The compression algorithm and level are the ones of the archive: a policy can only
+/// force or prevent compression of a content.
+pub trait CompressionPolicy {
+    fn comp_hint(&self, path: &crate::Path) -> CompHint;
+}
+
+/// The default policy: let the creator detect if content is worth compressing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DetectCompression;
+
+impl CompressionPolicy for DetectCompression {
+    fn comp_hint(&self, _path: &crate::Path) -> CompHint {
+        CompHint::Detect
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compress {
+    Yes,
+    No,
+    Detect,
+}
+
+impl Compress {
+    pub fn parse(s: &str) -> Result<Self, InputError> {
+        match s.to_ascii_lowercase().as_str() {
+            "yes" | "true" => Ok(Self::Yes),
+            "no" | "false" => Ok(Self::No),
+            "auto" | "detect" => Ok(Self::Detect),
+            _ => Err(InputError(format!(
+                "Invalid compression choice {s:?} (expecting yes, no or auto)"
+            ))),
+        }
+    }
+}
+
+impl From<Compress> for CompHint {
+    fn from(compress: Compress) -> Self {
+        match compress {
+            Compress::Yes => CompHint::Yes,
+            Compress::No => CompHint::No,
+            Compress::Detect => CompHint::Detect,
+        }
+    }
+}
+
+/// The compression algorithms, which cannot be used as a rule choice.
+const ALGORITHMS: [&str; 4] = ["none", "lz4", "lzma", "zstd"];
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: glob::Pattern,
+    /// Pattern is matched against the whole path instead of the file name.
+    anchored: bool,
+    compress: Compress,
+}
+
+/// A `CompressionPolicy` selecting compression with glob patterns.
+///
+/// A pattern without `/` matches the file name, other patterns match the whole path in the archive.
+/// When several patterns match, the last one wins. Files matching no pattern are detected.
+#[derive(Debug, Clone, Default)]
+pub struct CompressionRules {
+    rules: Vec<Rule>,
+}
+
+impl CompressionRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, pattern: &str, compress: Compress) -> Result<(), InputError> {
+        let anchored = pattern.contains('/');
+        let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+        let pattern = glob::Pattern::new(pattern)
+            .map_err(|e| InputError(format!("Invalid pattern {pattern:?}: {e}")))?;
+        self.rules.push(Rule {
+            pattern,
+            anchored,
+            compress,
+        });
+        Ok(())
+    }
+
+    /// Add a rule in the form `PATTERN[,PATTERN...][=yes|no|auto]`.
+    ///
+    /// `default` is used if the rule doesn't specify the choice.
+    /// Rules choosing a compression algorithm (`*.log=zstd=19`) are rejected: all compressed
+    /// contents of an archive use the same algorithm and level.
+    pub fn add_rule(&mut self, rule: &str, default: Compress) -> Result<(), InputError> {
+        if let Some((_, choice)) = rule.split_once('=') {
+            let algorithm = choice.split('=').next().unwrap_or_default();
+            if ALGORITHMS.contains(&algorithm.to_ascii_lowercase().as_str()) {
+                return Err(InputError(format!(
+                    "Invalid compression rule {rule:?}: compression algorithm and level cannot be \
+                     chosen per pattern, use yes, no or auto"
+                )));
+            }
+        }
+        let (patterns, compress) = match rule.rsplit_once('=') {
+            Some((patterns, choice)) => (patterns, Compress::parse(choice)?),
+            None => (rule, default),
+        };
+        for pattern in patterns.split(',').filter(|p| !p.is_empty()) {
+            self.add(pattern, compress)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The choice of the last rule matching `path`, if any.
+    pub fn matches(&self, path: &crate::Path) -> Option<Compress> {
+        let name = path.file_name().unwrap_or_default();
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                if rule.anchored {
+                    rule.pattern.matches(path.as_str())
+                } else {
+                    rule.pattern.matches(name)
+                }
+            })
+            .map(|rule| rule.compress)
+    }
+}
+
+impl CompressionPolicy for CompressionRules {
+    fn comp_hint(&self, path: &crate::Path) -> CompHint {
+        self.matches(path).unwrap_or(Compress::Detect).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustest::{test, *};
+
+    #[test]
+    fn test_rules() -> Result {
+        let mut rules = CompressionRules::new();
+        rules.add_rule("*.log", Compress::Yes)?;
+        rules.add_rule("*.jpg,*.zst", Compress::No)?;
+        rules.add_rule("logs/old/*=no", Compress::Yes)?;
+        let check = |path| rules.matches(crate::Path::new(path));
+        assert_eq!(check("app.log"), Some(Compress::Yes));
+        assert_eq!(check("logs/app.log"), Some(Compress::Yes));
+        assert_eq!(check("logs/old/app.log"), Some(Compress::No));
+        assert_eq!(check("img/photo.jpg"), Some(Compress::No));
+        assert_eq!(check("data.zst"), Some(Compress::No));
+        assert_eq!(check("main.rs"), None);
+        assert!(rules.add_rule("*.txt=maybe", Compress::Yes).is_err());
+        assert!(rules.add_rule("*.txt=lz4", Compress::Yes).is_err());
+        assert!(rules.add_rule("*.txt=zstd=19", Compress::Yes).is_err());
+        Ok(())
+    }
+}
@@ -1,13 +1,103 @@
+use std::collections::{hash_map::Entry, HashMap};
+use std::io::Seek;
 use std::rc::Rc;
 use std::sync::Arc;
 
-use jbk::creator::{BasicCreator, CachedContentAdder, ConcatMode, ContentAdder};
+use jbk::creator::{
+    BasicCreator, CachedContentAdder, CompHint, ConcatMode, ContentAdder, InputReader,
+};
 
-use super::{EntryStoreCreator, EntryTrait, Void};
+use super::entry_store_creator::Digests;
+use super::implicit_dir::ImplicitDirEntry;
+use super::reproducible::{content_hash, ReproducibleEntry};
+use super::reuse::ReusedPacks;
+use super::{
+    EntryStoreCreator, EntryTrait, ImplicitDirMetadata, ImplicitDirs, MetadataOverride,
+    PathTransforms, Reproducible, Void, Whiteout,
+};
+use crate::{Arx, CreatorError, FileEntry, IncoherentStructure, InputError};
+
+/// A content adder to which the BLAKE3 digest of the content can be given,
+/// to avoid reading the content twice when the caller already computed it.
+pub trait DigestContentAdder: ContentAdder {
+    fn add_content_with_digest(
+        &mut self,
+        content: Box<dyn InputReader>,
+        comp_hint: CompHint,
+        digest: [u8; 32],
+    ) -> jbk::creator::Result<jbk::ContentAddress>;
+}
+
+/// A content adder computing the BLAKE3 digest of every added content.
+struct DigestAdder<A> {
+    inner: A,
+    digests: Digests,
+}
+
+impl<A: ContentAdder> ContentAdder for DigestAdder<A> {
+    fn add_content(
+        &mut self,
+        mut content: Box<dyn InputReader>,
+        comp_hint: CompHint,
+    ) -> jbk::creator::Result<jbk::ContentAddress> {
+        let mut hasher = blake3::Hasher::new();
+        std::io::copy(&mut content, &mut hasher)?;
+        content.rewind()?;
+        let content_address = self.inner.add_content(content, comp_hint)?;
+        self.digests
+            .lock()
+            .unwrap()
+            .insert(content_address, *hasher.finalize().as_bytes());
+        Ok(content_address)
+    }
+}
+
+impl<A: ContentAdder> DigestContentAdder for DigestAdder<A> {
+    fn add_content_with_digest(
+        &mut self,
+        content: Box<dyn InputReader>,
+        comp_hint: CompHint,
+        digest: [u8; 32],
+    ) -> jbk::creator::Result<jbk::ContentAddress> {
+        let content_address = self.inner.add_content(content, comp_hint)?;
+        self.digests.lock().unwrap().insert(content_address, digest);
+        Ok(content_address)
+    }
+}
 
 pub struct SimpleCreator {
-    cached_content_creator: CachedContentAdder<BasicCreator>,
+    content_adder: DigestAdder<CachedContentAdder<BasicCreator>>,
     entry_store_creator: Box<EntryStoreCreator>,
+    reproducible: Option<Reproducible>,
+    metadata_override: Option<MetadataOverride>,
+    path_transforms: Option<PathTransforms>,
+    implicit_dirs: ImplicitDirs,
+    /// Packs of existing archives are referenced only if packs are not concatenated in one file.
+    one_file: bool,
+    reused_packs: ReusedPacks,
+    /// The original path of the entries stored under a transformed path.
+    transformed_paths: HashMap<crate::PathBuf, crate::PathBuf>,
+}
+
+fn add_overridden_entry<E: EntryTrait>(
+    entry_store_creator: &mut EntryStoreCreator,
+    metadata_override: Option<&MetadataOverride>,
+    entry: &E,
+) -> Void {
+    match metadata_override {
+        None => entry_store_creator.add_entry(entry),
+        Some(metadata_override) => entry_store_creator.add_entry(&metadata_override.apply(entry)),
+    }
+}
+
+fn overridden_metadata<E: EntryTrait>(
+    metadata_override: Option<&MetadataOverride>,
+    entry: &E,
+) -> ImplicitDirMetadata {
+    match metadata_override {
+        None => ImplicitDirMetadata::from_entry(entry),
+        Some(metadata_override) => ImplicitDirMetadata::from_entry(&metadata_override.apply(entry)),
+    }
 }
 
 impl SimpleCreator {
@@ -28,26 +118,189 @@ impl SimpleCreator {
 
         let entry_store_creator = Box::new(EntryStoreCreator::new());
 
-        let cached_content_creator = CachedContentAdder::new(basic_creator, cache_progress);
+        let content_adder = DigestAdder {
+            inner: CachedContentAdder::new(basic_creator, cache_progress),
+            digests: entry_store_creator.digests(),
+        };
 
         Ok(Self {
-            cached_content_creator,
+            content_adder,
             entry_store_creator,
+            reproducible: None,
+            metadata_override: None,
+            path_transforms: None,
+            implicit_dirs: Default::default(),
+            one_file: matches!(concat_mode, ConcatMode::OneFile),
+            reused_packs: Default::default(),
+            transformed_paths: HashMap::new(),
         })
     }
 
     pub fn finalize(self) -> Void {
-        Ok(self
-            .cached_content_creator
-            .into_inner()
-            .finalize(self.entry_store_creator, vec![])?)
+        let mut basic_creator = self.content_adder.inner.into_inner();
+        self.reused_packs.declare(&mut basic_creator);
+        if let Some(reproducible) = self.reproducible {
+            // Pack uuids are random by default.
+            let content_hash = content_hash(&self.entry_store_creator.digests());
+            let entry_hash = self.entry_store_creator.entry_hash();
+            basic_creator.set_pack_uuid_generator(move |pack_kind| {
+                reproducible.pack_uuid(&content_hash, &entry_hash, pack_kind)
+            });
+        }
+        Ok(basic_creator.finalize(self.entry_store_creator, vec![])?)
+    }
+
+    pub fn adder(&mut self) -> &mut impl DigestContentAdder {
+        &mut self.content_adder
+    }
+
+    /// Reference the content of `file`, read from `arx` opened from `arx_path`, instead of
+    /// storing it again (see the `reuse` module).
+    ///
+    /// Return `None` if the content cannot be referenced, it must then be added again.
+    pub(crate) fn reuse_content(
+        &mut self,
+        arx: &Arx,
+        arx_path: &std::path::Path,
+        file: &FileEntry,
+    ) -> Result<Option<jbk::ContentAddress>, CreatorError> {
+        if self.one_file {
+            return Ok(None);
+        }
+        let Some(content_address) = self.reused_packs.content_address(arx, arx_path, file)? else {
+            return Ok(None);
+        };
+        if let Some(digest) = file.digest().and_then(|d| <[u8; 32]>::try_from(d).ok()) {
+            self.entry_store_creator
+                .digests()
+                .lock()
+                .unwrap()
+                .insert(content_address, digest);
+        }
+        Ok(Some(content_address))
     }
 
-    pub fn adder(&mut self) -> &mut impl ContentAdder {
-        &mut self.cached_content_creator
+    /// Normalize the metadata of all entries added from now on (see `Reproducible`).
+    pub fn set_reproducible(&mut self, reproducible: Reproducible) {
+        self.reproducible = Some(reproducible);
+        self.update_implicit_dirs();
+    }
+
+    pub fn reproducible(&self) -> Option<&Reproducible> {
+        self.reproducible.as_ref()
+    }
+
+    /// Force metadata of all entries added from now on (see `MetadataOverride`).
+    ///
+    /// The override is applied after the `Reproducible` normalization.
+    pub fn set_metadata_override(&mut self, metadata_override: MetadataOverride) {
+        self.metadata_override = Some(metadata_override);
+        self.update_implicit_dirs();
+    }
+
+    /// Rewrite the path of all entries added from now on (see `PathTransforms`).
+    ///
+    /// Adding two entries transformed to the same path is an `IncoherentStructure` error.
+    pub fn set_path_transforms(&mut self, path_transforms: PathTransforms) {
+        self.path_transforms = Some(path_transforms);
+    }
+
+    /// The path an entry added at `path` is stored at (see `set_path_transforms`).
+    ///
+    /// Returns `None` if the entry is skipped.
+    pub fn stored_path(&self, path: &crate::Path) -> Result<Option<crate::PathBuf>, InputError> {
+        match &self.path_transforms {
+            None => Ok(Some(path.to_owned())),
+            Some(path_transforms) => path_transforms.apply(path),
+        }
+    }
+
+    /// Add a whiteout hiding the entry at `path` of the base archive (see `enable_whiteouts`).
+    ///
+    /// `path` comes from the base archive and is not transformed again.
+    pub fn add_whiteout(&mut self, path: crate::PathBuf) -> Void {
+        self.add_normalized_entry(&Whiteout::new(path))
     }
 
     pub fn add_entry<E: EntryTrait>(&mut self, entry: &E) -> Void {
-        self.entry_store_creator.add_entry(entry)
+        let Some(path_transforms) = &self.path_transforms else {
+            return self.add_normalized_entry(entry);
+        };
+        let Some(transformed) = path_transforms.apply_entry(entry)? else {
+            log::debug!("{} is transformed to an empty path, skipping", entry.path());
+            return Ok(());
+        };
+        match self.transformed_paths.entry(transformed.path().to_owned()) {
+            Entry::Occupied(original) => {
+                if original.get() != entry.path() {
+                    return Err(IncoherentStructure(format!(
+                        "{} and {} are both transformed to {}",
+                        original.get(),
+                        entry.path(),
+                        original.key()
+                    ))
+                    .into());
+                }
+            }
+            Entry::Vacant(slot) => {
+                slot.insert(entry.path().to_owned());
+            }
+        }
+        self.add_normalized_entry(&transformed)
+    }
+
+    fn add_normalized_entry<E: EntryTrait>(&mut self, entry: &E) -> Void {
+        let metadata_override = self.metadata_override.as_ref();
+        match &self.reproducible {
+            None => add_overridden_entry(&mut self.entry_store_creator, metadata_override, entry),
+            Some(reproducible) => add_overridden_entry(
+                &mut self.entry_store_creator,
+                metadata_override,
+                &ReproducibleEntry {
+                    inner: entry,
+                    reproducible,
+                },
+            ),
+        }
+    }
+
+    /// Set how the directories created implicitly get their metadata (see `ImplicitDirs`).
+    pub fn set_implicit_dirs(&mut self, implicit_dirs: ImplicitDirs) {
+        self.implicit_dirs = implicit_dirs;
+        self.update_implicit_dirs();
+    }
+
+    /// Normalize and override the fixed metadata of implicit directories as other entries.
+    ///
+    /// Directories created from their child get the metadata of the already modified child.
+    fn update_implicit_dirs(&mut self) {
+        let implicit_dirs = match &self.implicit_dirs {
+            ImplicitDirs::Fixed(metadata) => {
+                let metadata_override = self.metadata_override.as_ref();
+                let entry = ImplicitDirEntry(metadata);
+                ImplicitDirs::Fixed(match &self.reproducible {
+                    None => overridden_metadata(metadata_override, &entry),
+                    Some(reproducible) => overridden_metadata(
+                        metadata_override,
+                        &ReproducibleEntry {
+                            inner: &entry,
+                            reproducible,
+                        },
+                    ),
+                })
+            }
+            ImplicitDirs::FromChild => ImplicitDirs::FromChild,
+        };
+        self.entry_store_creator.set_implicit_dirs(implicit_dirs)
+    }
+
+    /// Set an archive-level metadata (provenance, description, ...).
+    pub fn set_metadata(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.entry_store_creator.set_metadata(key, value)
+    }
+
+    /// Allow adding whiteouts, to create a patch archive (see `EntryStoreCreator::enable_whiteouts`).
+    pub fn enable_whiteouts(&mut self) {
+        self.entry_store_creator.enable_whiteouts()
     }
 }
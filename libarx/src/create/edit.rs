@@ -0,0 +1,157 @@
+use super::ModeSpec;
+use crate::InputError;
+
+const MATCH_OPTIONS: glob::MatchOptions = glob::MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+#[derive(Debug, Clone)]
+enum Edit {
+    Delete(glob::Pattern),
+    Rename(crate::PathBuf, crate::PathBuf),
+    Chmod(glob::Pattern, ModeSpec),
+}
+
+fn parse_pattern(pattern: &str) -> Result<glob::Pattern, InputError> {
+    glob::Pattern::new(pattern.trim_matches('/'))
+        .map_err(|e| InputError(format!("Invalid pattern {pattern:?}: {e}")))
+}
+
+fn parse_path(path: &str) -> Result<crate::PathBuf, InputError> {
+    let normalized = crate::Path::new(path.trim_matches('/')).normalize();
+    match normalized.components().next() {
+        None | Some(relative_path::Component::ParentDir) => {
+            Err(InputError(format!("Invalid archive path {path:?}")))
+        }
+        _ => Ok(normalized),
+    }
+}
+
+/// A list of edits (deletions, renamings and mode changes) applied to the entries of an
+/// archive (see `ArxAdder::set_edits`).
+///
+/// Patterns use the glob syntax and are matched against the whole path of the entries
+/// (`*` does not match `/`).
+/// Edits are applied in order, each one on the path produced by the previous ones.
+#[derive(Debug, Clone, Default)]
+pub struct Edits(Vec<Edit>);
+
+impl Edits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Remove the entries matching `pattern`. Children of removed directories are removed too.
+    pub fn delete(&mut self, pattern: &str) -> Result<(), InputError> {
+        self.0.push(Edit::Delete(parse_pattern(pattern)?));
+        Ok(())
+    }
+
+    /// Move the entry at `from` (with all its children) to `to`.
+    ///
+    /// Symbolic links pointing to the moved entries are not changed.
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<(), InputError> {
+        self.0
+            .push(Edit::Rename(parse_path(from)?, parse_path(to)?));
+        Ok(())
+    }
+
+    /// Change the mode of the entries matching `pattern`.
+    pub fn chmod(&mut self, pattern: &str, mode: ModeSpec) -> Result<(), InputError> {
+        self.0.push(Edit::Chmod(parse_pattern(pattern)?, mode));
+        Ok(())
+    }
+
+    /// Apply the edits to an entry at `path` with `mode`.
+    ///
+    /// Returns the new path and mode of the entry, or `None` if it is deleted.
+    pub fn apply(
+        &self,
+        path: &crate::Path,
+        is_dir: bool,
+        mode: u64,
+    ) -> Option<(crate::PathBuf, u64)> {
+        let mut path = path.to_relative_path_buf();
+        let mut mode = mode;
+        for edit in &self.0 {
+            match edit {
+                Edit::Delete(pattern) => {
+                    if pattern.matches_with(path.as_str(), MATCH_OPTIONS) {
+                        return None;
+                    }
+                }
+                Edit::Rename(from, to) => {
+                    if let Ok(suffix) = path.strip_prefix(from) {
+                        path = if suffix.as_str().is_empty() {
+                            to.clone()
+                        } else {
+                            to.join(suffix)
+                        };
+                    }
+                }
+                Edit::Chmod(pattern, spec) => {
+                    if pattern.matches_with(path.as_str(), MATCH_OPTIONS) {
+                        mode = spec.apply(mode, is_dir);
+                    }
+                }
+            }
+        }
+        Some((path, mode))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustest::{test, *};
+
+    fn apply(edits: &Edits, path: &str) -> Option<(String, u64)> {
+        edits
+            .apply(crate::Path::new(path), false, 0o644)
+            .map(|(p, m)| (p.as_str().to_string(), m))
+    }
+
+    #[test]
+    fn test_edits() -> Result {
+        let mut edits = Edits::new();
+        edits.delete("*.tmp")?;
+        edits.rename("src", "source")?;
+        edits.chmod("source/*.sh", ModeSpec::parse("+x")?)?;
+        edits.delete("source/old")?;
+        assert_eq!(apply(&edits, "a.tmp"), None);
+        assert_eq!(
+            apply(&edits, "dir/a.tmp"),
+            Some(("dir/a.tmp".into(), 0o644))
+        );
+        assert_eq!(apply(&edits, "src"), Some(("source".into(), 0o644)));
+        assert_eq!(
+            apply(&edits, "src/run.sh"),
+            Some(("source/run.sh".into(), 0o755))
+        );
+        assert_eq!(
+            apply(&edits, "src/sub/run.sh"),
+            Some(("source/sub/run.sh".into(), 0o644))
+        );
+        assert_eq!(
+            apply(&edits, "srcs/run.sh"),
+            Some(("srcs/run.sh".into(), 0o644))
+        );
+        assert_eq!(apply(&edits, "src/old"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid() -> Result {
+        let mut edits = Edits::new();
+        assert!(edits.delete("[a").is_err());
+        assert!(edits.rename("", "a").is_err());
+        assert!(edits.rename("a", "../b").is_err());
+        Ok(())
+    }
+}
@@ -1,10 +1,15 @@
-use crate::common::{EntryType, Property};
+use crate::common::{encode_rdev, unescape_str, EntryType, Property};
 use crate::IncoherentStructure;
+use crate::SpecialKind;
 use jbk::creator::schema;
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 
-use super::{EntryKind, EntryTrait, Void};
+use super::{EntryKind, EntryTrait, ImplicitDirs, Void};
+
+/// The BLAKE3 digest of each added content.
+pub(crate) type Digests = Arc<Mutex<HashMap<jbk::ContentAddress, [u8; 32]>>>;
 
 type EntryStore = jbk::creator::EntryStore<
     Property,
@@ -17,7 +22,41 @@ type EntryIdx = jbk::Bound<jbk::EntryIdx>;
 
 enum DirOrFile {
     Dir(DirEntry),
-    File(EntryIdx),
+    File(EntryIdx, Option<Arc<FileInode>>),
+}
+
+/// The kind of an entry to add, with hard links resolved to the inode they share.
+enum ResolvedKind {
+    Dir,
+    File(jbk::Size, jbk::ContentAddress, Option<[u8; 32]>),
+    HardLink(Arc<FileInode>),
+    Link(bstr::BString),
+    Special(SpecialKind, u32, u32),
+    Whiteout,
+}
+
+/// The inode shared by a file and all its hard links.
+///
+/// The inode identity stored in the archive is the index of the first entry
+/// added for this inode.
+struct FileInode {
+    idx: EntryIdx,
+    size: jbk::Size,
+    content_address: jbk::ContentAddress,
+    digest: Option<[u8; 32]>,
+    nlink: Arc<AtomicU64>,
+}
+
+impl FileInode {
+    fn inode_generator(&self) -> Box<dyn Fn() -> u64 + Sync + Send> {
+        let idx = self.idx.clone();
+        Box::new(move || idx.get().into_u64())
+    }
+
+    fn nlink_generator(&self) -> Box<dyn Fn() -> u64 + Sync + Send> {
+        let nlink = Arc::clone(&self.nlink);
+        Box::new(move || nlink.load(Ordering::Relaxed))
+    }
 }
 
 /// A DirEntry structure to keep track of added direcotry in the archive.
@@ -50,7 +89,7 @@ impl DirEntry {
                 .unwrap()
                 .values()
                 .map(|e| match e {
-                    DirOrFile::File(i) => i.get().into_u64(),
+                    DirOrFile::File(i, _) => i.get().into_u64(),
                     DirOrFile::Dir(e) => e.idx.as_ref().unwrap().get().into_u64(),
                 })
                 .min()
@@ -73,19 +112,42 @@ impl DirEntry {
         }
     }
 
-    fn add<'a, E, C>(&mut self, entry: &E, mut components: C, entry_store: &mut EntryStore) -> Void
+    fn find_file<'a, C>(&self, mut components: C) -> Option<Arc<FileInode>>
+    where
+        C: Iterator<Item = relative_path::Component<'a>>,
+    {
+        let component = components.next()?;
+        match self.children.try_read().unwrap().get(component.as_str())? {
+            DirOrFile::Dir(e) => e.find_file(components),
+            DirOrFile::File(_, inode) => match components.next() {
+                None => inode.clone(),
+                Some(_) => None,
+            },
+        }
+    }
+
+    fn add<'a, E, C>(
+        &mut self,
+        entry: &E,
+        entry_kind: ResolvedKind,
+        mut components: C,
+        implicit_dirs: &ImplicitDirs,
+        entry_store: &mut EntryStore,
+    ) -> Void
     where
         E: EntryTrait + ?Sized,
         C: Iterator<Item = relative_path::Component<'a>>,
     {
         match components.next() {
-            None => self.add_entry(entry, entry_store),
+            None => self.add_entry(entry, entry_kind, entry_store),
             Some(component) => {
-                self.ensure_dir(component.as_str(), entry_store)?;
+                self.ensure_dir(component.as_str(), entry, implicit_dirs, entry_store)?;
                 let mut write_children = self.children.try_write().unwrap();
                 match write_children.get_mut(component.as_str()).unwrap() {
-                    DirOrFile::Dir(e) => e.add(entry, components, entry_store),
-                    DirOrFile::File(_) => Err(IncoherentStructure(format!(
+                    DirOrFile::Dir(e) => {
+                        e.add(entry, entry_kind, components, implicit_dirs, entry_store)
+                    }
+                    DirOrFile::File(..) => Err(IncoherentStructure(format!(
                         "Adding {}, cannot add a entry to something which is not a directory",
                         entry.path()
                     ))
@@ -95,27 +157,50 @@ impl DirEntry {
         }
     }
 
-    fn ensure_dir(&mut self, dir_name: &str, entry_store: &mut EntryStore) -> Void {
+    fn ensure_dir<E>(
+        &mut self,
+        dir_name: &str,
+        child: &E,
+        implicit_dirs: &ImplicitDirs,
+        entry_store: &mut EntryStore,
+    ) -> Void
+    where
+        E: EntryTrait + ?Sized,
+    {
         self.children
             .try_write()
             .unwrap()
             .entry(dir_name.into())
             .or_insert_with(|| {
+                let metadata = implicit_dirs.metadata(child);
                 let entry_idx = jbk::Vow::new(jbk::EntryIdx::from(0));
                 let dir_entry = DirEntry::new(entry_idx.bind());
                 let values = HashMap::from([
                     (
                         Property::Name,
-                        jbk::Value::Array(dir_name.as_bytes().into()),
+                        jbk::Value::Array(unescape_str(dir_name).as_ref().into()),
                     ),
                     (
                         Property::Parent,
                         jbk::Value::UnsignedWord(self.as_parent_idx_generator().into()),
                     ),
-                    (Property::Owner, jbk::Value::Unsigned(1000)),
-                    (Property::Group, jbk::Value::Unsigned(1000)),
-                    (Property::Rights, jbk::Value::Unsigned(0o755)),
-                    (Property::Mtime, jbk::Value::Unsigned(0)),
+                    (Property::Owner, jbk::Value::Unsigned(metadata.owner.id)),
+                    (Property::Group, jbk::Value::Unsigned(metadata.group.id)),
+                    (Property::Rights, jbk::Value::Unsigned(metadata.mode)),
+                    (Property::Mtime, jbk::Value::Unsigned(metadata.mtime)),
+                    (Property::MtimeNsec, jbk::Value::Unsigned(0)),
+                    (Property::Atime, jbk::Value::Unsigned(0)),
+                    (Property::Ctime, jbk::Value::Unsigned(0)),
+                    (Property::Btime, jbk::Value::Unsigned(0)),
+                    (
+                        Property::OwnerName,
+                        jbk::Value::Array(metadata.owner.name.unwrap_or_default().into()),
+                    ),
+                    (
+                        Property::GroupName,
+                        jbk::Value::Array(metadata.group.name.unwrap_or_default().into()),
+                    ),
+                    (Property::Xattrs, jbk::Value::Array(Vec::new().into())),
                     (
                         Property::FirstChild,
                         jbk::Value::UnsignedWord(dir_entry.first_entry_generator().into()),
@@ -139,16 +224,15 @@ impl DirEntry {
         Ok(())
     }
 
-    fn add_entry<E>(&mut self, entry: &E, entry_store: &mut EntryStore) -> Void
+    fn add_entry<E>(
+        &mut self,
+        entry: &E,
+        entry_kind: ResolvedKind,
+        entry_store: &mut EntryStore,
+    ) -> Void
     where
         E: EntryTrait + ?Sized,
     {
-        let entry_kind = match entry.kind()? {
-            Some(k) => k,
-            None => {
-                return Ok(());
-            }
-        };
         let entry_name = entry
             .path()
             .file_name()
@@ -156,7 +240,7 @@ impl DirEntry {
         let mut values = HashMap::from([
             (
                 Property::Name,
-                jbk::Value::Array(entry_name.as_bytes().into()),
+                jbk::Value::Array(unescape_str(entry_name).as_ref().into()),
             ),
             (
                 Property::Parent,
@@ -166,13 +250,38 @@ impl DirEntry {
             (Property::Group, jbk::Value::Unsigned(entry.gid())),
             (Property::Rights, jbk::Value::Unsigned(entry.mode())),
             (Property::Mtime, jbk::Value::Unsigned(entry.mtime())),
+            (
+                Property::MtimeNsec,
+                jbk::Value::Unsigned(entry.mtime_nsec() as u64),
+            ),
+            (Property::Atime, jbk::Value::Unsigned(entry.atime_ns())),
+            (Property::Ctime, jbk::Value::Unsigned(entry.ctime_ns())),
+            (Property::Btime, jbk::Value::Unsigned(entry.btime_ns())),
+            (
+                Property::OwnerName,
+                jbk::Value::Array(entry.owner_name().unwrap_or_default().to_vec().into()),
+            ),
+            (
+                Property::GroupName,
+                jbk::Value::Array(entry.group_name().unwrap_or_default().to_vec().into()),
+            ),
+            (
+                Property::Xattrs,
+                jbk::Value::Array(
+                    entry
+                        .xattrs()
+                        .map(|x| x.to_bytes())
+                        .unwrap_or_default()
+                        .into(),
+                ),
+            ),
         ]);
 
         match entry_kind {
-            EntryKind::Dir => {
+            ResolvedKind::Dir => {
                 match self.children.try_read().unwrap().get(entry_name) {
                     Some(DirOrFile::Dir(_)) => return Ok(()),
-                    Some(DirOrFile::File(_)) => {
+                    Some(DirOrFile::File(..)) => {
                         return Err(IncoherentStructure(format!(
                             "Adding {}, cannot add a dir when file or link already exists",
                             entry.path()
@@ -208,113 +317,377 @@ impl DirEntry {
                     .insert(entry_name.into(), DirOrFile::Dir(dir_entry));
                 Ok(())
             }
-            EntryKind::File(size, content_address) => {
+            ResolvedKind::File(size, content_address, digest) => {
+                let entry_idx = jbk::Vow::new(jbk::EntryIdx::from(0));
+                let inode = Arc::new(FileInode {
+                    idx: entry_idx.bind(),
+                    size,
+                    content_address,
+                    digest,
+                    nlink: Arc::new(AtomicU64::new(0)),
+                });
+                self.add_file(entry, entry_name, values, entry_idx, inode, entry_store)
+            }
+            ResolvedKind::HardLink(inode) => {
+                let entry_idx = jbk::Vow::new(jbk::EntryIdx::from(0));
+                self.add_file(entry, entry_name, values, entry_idx, inode, entry_store)
+            }
+            ResolvedKind::Link(target) => {
                 if self.children.try_read().unwrap().contains_key(entry_name) {
                     return Err(IncoherentStructure(format!(
-                        "Adding {}, cannot add a file when one already exists",
+                        "Adding {}, cannot add a link when one already exists",
                         entry.path()
                     ))
                     .into());
                 }
-                values.insert(Property::Content, jbk::Value::Content(content_address));
-                values.insert(Property::Size, jbk::Value::Unsigned(size.into_u64()));
+                values.insert(
+                    Property::Target,
+                    jbk::Value::Array(Vec::from(target).into()),
+                );
                 let entry = Box::new(jbk::creator::BasicEntry::new_from_schema(
                     &entry_store.schema,
-                    Some(EntryType::File),
+                    Some(EntryType::Link),
                     values,
                 ));
                 let current_idx = entry_store.add_entry(entry);
                 self.children
                     .try_write()
                     .unwrap()
-                    .insert(entry_name.into(), DirOrFile::File(current_idx));
+                    .insert(entry_name.into(), DirOrFile::File(current_idx, None));
                 Ok(())
             }
-            EntryKind::Link(target) => {
+            ResolvedKind::Special(kind, major, minor) => {
                 if self.children.try_read().unwrap().contains_key(entry_name) {
                     return Err(IncoherentStructure(format!(
-                        "Adding {}, cannot add a link when one already exists",
+                        "Adding {}, cannot add a special file when one already exists",
                         entry.path()
                     ))
                     .into());
                 }
+                values.insert(Property::Kind, jbk::Value::Unsigned(kind.to_u64()));
                 values.insert(
-                    Property::Target,
-                    jbk::Value::Array(Vec::from(target).into()),
+                    Property::Rdev,
+                    jbk::Value::Unsigned(encode_rdev(major, minor)),
                 );
                 let entry = Box::new(jbk::creator::BasicEntry::new_from_schema(
                     &entry_store.schema,
-                    Some(EntryType::Link),
+                    Some(EntryType::Special),
                     values,
                 ));
                 let current_idx = entry_store.add_entry(entry);
                 self.children
                     .try_write()
                     .unwrap()
-                    .insert(entry_name.into(), DirOrFile::File(current_idx));
+                    .insert(entry_name.into(), DirOrFile::File(current_idx, None));
+                Ok(())
+            }
+            ResolvedKind::Whiteout => {
+                if self.children.try_read().unwrap().contains_key(entry_name) {
+                    return Err(IncoherentStructure(format!(
+                        "Adding {}, cannot add a whiteout when one already exists",
+                        entry.path()
+                    ))
+                    .into());
+                }
+                let entry = Box::new(jbk::creator::BasicEntry::new_from_schema(
+                    &entry_store.schema,
+                    Some(EntryType::Whiteout),
+                    values,
+                ));
+                let current_idx = entry_store.add_entry(entry);
+                self.children
+                    .try_write()
+                    .unwrap()
+                    .insert(entry_name.into(), DirOrFile::File(current_idx, None));
                 Ok(())
             }
         }
     }
+
+    fn add_file<E>(
+        &mut self,
+        entry: &E,
+        entry_name: &str,
+        mut values: HashMap<Property, jbk::Value>,
+        entry_idx: jbk::Vow<jbk::EntryIdx>,
+        inode: Arc<FileInode>,
+        entry_store: &mut EntryStore,
+    ) -> Void
+    where
+        E: EntryTrait + ?Sized,
+    {
+        if self.children.try_read().unwrap().contains_key(entry_name) {
+            return Err(IncoherentStructure(format!(
+                "Adding {}, cannot add a file when one already exists",
+                entry.path()
+            ))
+            .into());
+        }
+        inode.nlink.fetch_add(1, Ordering::Relaxed);
+        let current_idx = entry_idx.bind();
+        values.insert(
+            Property::Content,
+            jbk::Value::Content(inode.content_address),
+        );
+        values.insert(Property::Size, jbk::Value::Unsigned(inode.size.into_u64()));
+        values.insert(
+            Property::Digest,
+            jbk::Value::Array(inode.digest.map(Vec::from).unwrap_or_default().into()),
+        );
+        values.insert(
+            Property::Inode,
+            jbk::Value::UnsignedWord(inode.inode_generator().into()),
+        );
+        values.insert(
+            Property::Nlink,
+            jbk::Value::UnsignedWord(inode.nlink_generator().into()),
+        );
+        let entry = Box::new(jbk::creator::BasicEntry::new_from_schema_idx(
+            &entry_store.schema,
+            entry_idx,
+            Some(EntryType::File),
+            values,
+        ));
+        entry_store.add_entry(entry);
+        self.children
+            .try_write()
+            .unwrap()
+            .insert(entry_name.into(), DirOrFile::File(current_idx, Some(inode)));
+        Ok(())
+    }
 }
 
 pub struct EntryStoreCreator {
     entry_store: Box<EntryStore>,
     path_store: jbk::creator::StoreHandle,
+    xattrs_store: jbk::creator::StoreHandle,
+    names_store: jbk::creator::StoreHandle,
+    digest_store: jbk::creator::StoreHandle,
+    digests: Digests,
+    metadata: BTreeMap<String, String>,
+    implicit_dirs: ImplicitDirs,
+    whiteouts: bool,
     root_entry: DirEntry,
+    /// A hash of the added entries (see `entry_hash`).
+    entry_hasher: blake3::Hasher,
+}
+
+fn hash_bytes(hasher: &mut blake3::Hasher, bytes: &[u8]) {
+    hasher.update(&(bytes.len() as u64).to_le_bytes());
+    hasher.update(bytes);
+}
+
+/// Hash what is stored about `entry` of kind `kind`.
+fn hash_entry<E: EntryTrait>(
+    hasher: &mut blake3::Hasher,
+    entry: &E,
+    kind: &EntryKind,
+    digests: &Digests,
+) {
+    hash_bytes(hasher, entry.path().as_str().as_bytes());
+    match kind {
+        EntryKind::Dir => {
+            hasher.update(b"d");
+        }
+        EntryKind::File(size, content_address) => {
+            hasher.update(b"f");
+            hasher.update(&size.into_u64().to_le_bytes());
+            match digests.lock().unwrap().get(content_address) {
+                Some(digest) => hash_bytes(hasher, digest),
+                None => hash_bytes(hasher, format!("{content_address:?}").as_bytes()),
+            }
+        }
+        EntryKind::Link(target) => {
+            hasher.update(b"l");
+            hash_bytes(hasher, target);
+        }
+        EntryKind::HardLink(target) => {
+            hasher.update(b"h");
+            hash_bytes(hasher, target.as_str().as_bytes());
+        }
+        EntryKind::Special(kind, major, minor) => {
+            hasher.update(b"s");
+            hash_bytes(hasher, format!("{kind:?}").as_bytes());
+            hasher.update(&major.to_le_bytes());
+            hasher.update(&minor.to_le_bytes());
+        }
+        EntryKind::Whiteout => {
+            hasher.update(b"w");
+        }
+    }
+    for value in [
+        entry.uid(),
+        entry.gid(),
+        entry.mode(),
+        entry.mtime(),
+        u64::from(entry.mtime_nsec()),
+        entry.atime_ns(),
+        entry.ctime_ns(),
+        entry.btime_ns(),
+    ] {
+        hasher.update(&value.to_le_bytes());
+    }
+    hash_bytes(hasher, entry.owner_name().unwrap_or_default());
+    hash_bytes(hasher, entry.group_name().unwrap_or_default());
+    let xattrs = entry.xattrs().map(|x| x.iter().collect::<Vec<_>>());
+    let xattrs = xattrs.unwrap_or_default();
+    hasher.update(&(xattrs.len() as u64).to_le_bytes());
+    for (name, value) in xattrs {
+        hash_bytes(hasher, name);
+        hash_bytes(hasher, value);
+    }
+}
+
+/// The entry store of the archive, with the whiteout variant if `whiteouts` is true.
+fn new_entry_store(
+    path_store: &jbk::creator::StoreHandle,
+    xattrs_store: &jbk::creator::StoreHandle,
+    names_store: &jbk::creator::StoreHandle,
+    digest_store: &jbk::creator::StoreHandle,
+    whiteouts: bool,
+) -> Box<EntryStore> {
+    let common = schema::CommonProperties::new(vec![
+        schema::Property::new_array(1, path_store.clone(), Property::Name), // the path
+        schema::Property::new_uint(Property::Parent), // index of the parent entry
+        schema::Property::new_uint(Property::Owner),  // owner
+        schema::Property::new_uint(Property::Group),  // group
+        schema::Property::new_uint(Property::Rights), // rights
+        schema::Property::new_uint(Property::Mtime),  // modification time
+        schema::Property::new_array(0, xattrs_store.clone(), Property::Xattrs), // extended attributes
+        schema::Property::new_uint(Property::MtimeNsec), // sub-second part of the modification time
+        schema::Property::new_uint(Property::Atime), // access time (ns since epoch, 0 if unknown)
+        schema::Property::new_uint(Property::Ctime), // status change time (ns since epoch, 0 if unknown)
+        schema::Property::new_uint(Property::Btime), // birth time (ns since epoch, 0 if unknown)
+        schema::Property::new_array(0, names_store.clone(), Property::OwnerName), // owner name (empty if unknown)
+        schema::Property::new_array(0, names_store.clone(), Property::GroupName), // group name (empty if unknown)
+    ]);
+    let mut variants = vec![
+        // File
+        (
+            EntryType::File,
+            schema::VariantProperties::new(vec![
+                schema::Property::new_content_address(Property::Content),
+                schema::Property::new_uint(Property::Size), // Size
+                schema::Property::new_uint(Property::Inode), // index of the first entry sharing the inode
+                schema::Property::new_uint(Property::Nlink), // number of entries sharing the inode
+                schema::Property::new_array(0, digest_store.clone(), Property::Digest), // BLAKE3 digest of the content (empty if unknown)
+            ]),
+        ),
+        // Directory
+        (
+            EntryType::Dir,
+            schema::VariantProperties::new(vec![
+                schema::Property::new_uint(Property::FirstChild), // index of the first entry
+                schema::Property::new_uint(Property::NbChildren), // nb entries in the directory
+            ]),
+        ),
+        // Link
+        (
+            EntryType::Link,
+            schema::VariantProperties::new(vec![
+                schema::Property::new_array(1, path_store.clone(), Property::Target), // Id of the linked entry
+            ]),
+        ),
+        // Special file
+        (
+            EntryType::Special,
+            schema::VariantProperties::new(vec![
+                schema::Property::new_uint(Property::Kind), // kind of special file
+                schema::Property::new_uint(Property::Rdev), // device numbers
+            ]),
+        ),
+    ];
+    if whiteouts {
+        // Whiteout (only in patch archives, readers not knowing it reject the archive)
+        variants.push((EntryType::Whiteout, schema::VariantProperties::new(vec![])));
+    }
+    let entry_def = schema::Schema::new(
+        common,
+        variants,
+        Some(vec![Property::Parent, Property::Name]),
+    );
+
+    Box::new(EntryStore::new(entry_def, None))
 }
 
 impl EntryStoreCreator {
     pub fn new() -> Self {
         let path_store = jbk::creator::ValueStore::new_plain(None);
-
-        let entry_def = schema::Schema::new(
-            // Common part
-            schema::CommonProperties::new(vec![
-                schema::Property::new_array(1, path_store.clone(), Property::Name), // the path
-                schema::Property::new_uint(Property::Parent), // index of the parent entry
-                schema::Property::new_uint(Property::Owner),  // owner
-                schema::Property::new_uint(Property::Group),  // group
-                schema::Property::new_uint(Property::Rights), // rights
-                schema::Property::new_uint(Property::Mtime),  // modification time
-            ]),
-            vec![
-                // File
-                (
-                    EntryType::File,
-                    schema::VariantProperties::new(vec![
-                        schema::Property::new_content_address(Property::Content),
-                        schema::Property::new_uint(Property::Size), // Size
-                    ]),
-                ),
-                // Directory
-                (
-                    EntryType::Dir,
-                    schema::VariantProperties::new(vec![
-                        schema::Property::new_uint(Property::FirstChild), // index of the first entry
-                        schema::Property::new_uint(Property::NbChildren), // nb entries in the directory
-                    ]),
-                ),
-                // Link
-                (
-                    EntryType::Link,
-                    schema::VariantProperties::new(vec![
-                        schema::Property::new_array(1, path_store.clone(), Property::Target), // Id of the linked entry
-                    ]),
-                ),
-            ],
-            Some(vec![Property::Parent, Property::Name]),
+        let xattrs_store = jbk::creator::ValueStore::new_plain(None);
+        let names_store = jbk::creator::ValueStore::new_plain(None);
+        let digest_store = jbk::creator::ValueStore::new_plain(None);
+
+        let entry_store = new_entry_store(
+            &path_store,
+            &xattrs_store,
+            &names_store,
+            &digest_store,
+            false,
         );
 
-        let entry_store = Box::new(EntryStore::new(entry_def, None));
-
         let root_entry = DirEntry::new_root();
 
         Self {
             entry_store,
             path_store,
+            xattrs_store,
+            names_store,
+            digest_store,
+            digests: Default::default(),
+            metadata: BTreeMap::new(),
+            implicit_dirs: Default::default(),
+            whiteouts: false,
             root_entry,
+            entry_hasher: blake3::Hasher::new(),
+        }
+    }
+
+    /// Allow adding whiteouts (`EntryKind::Whiteout`), to create a patch archive.
+    ///
+    /// Whiteouts are stored in a variant unknown to the readers not supporting patch archives.
+    /// Must be called before adding entries.
+    pub fn enable_whiteouts(&mut self) {
+        self.entry_store = new_entry_store(
+            &self.path_store,
+            &self.xattrs_store,
+            &self.names_store,
+            &self.digest_store,
+            true,
+        );
+        self.whiteouts = true;
+    }
+
+    /// Set an archive-level metadata (provenance, description, ...).
+    ///
+    /// Setting an existing key replaces its value.
+    pub fn set_metadata(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.metadata.insert(key.into(), value.into());
+    }
+
+    /// Set how the directories created implicitly get their metadata.
+    pub fn set_implicit_dirs(&mut self, implicit_dirs: ImplicitDirs) {
+        self.implicit_dirs = implicit_dirs;
+    }
+
+    /// The digests of the contents, used to set the digest of the added files.
+    ///
+    /// Files whose content has no digest are stored without digest.
+    pub(crate) fn digests(&self) -> Digests {
+        Arc::clone(&self.digests)
+    }
+
+    /// A hash of all that is stored in the directory pack: the added entries, in the order
+    /// they are added, and the archive-level metadata.
+    pub(crate) fn entry_hash(&self) -> [u8; 32] {
+        let mut hasher = self.entry_hasher.clone();
+        hasher.update(&[u8::from(self.whiteouts)]);
+        hash_bytes(&mut hasher, format!("{:?}", self.implicit_dirs).as_bytes());
+        hasher.update(&(self.metadata.len() as u64).to_le_bytes());
+        for (key, value) in &self.metadata {
+            hash_bytes(&mut hasher, key.as_bytes());
+            hash_bytes(&mut hasher, value.as_bytes());
         }
+        *hasher.finalize().as_bytes()
     }
 
     pub fn entry_count(&self) -> jbk::EntryCount {
@@ -325,14 +698,53 @@ impl EntryStoreCreator {
     where
         E: EntryTrait,
     {
+        let Some(kind) = entry.kind()? else {
+            return Ok(());
+        };
+        hash_entry(&mut self.entry_hasher, entry, &kind, &self.digests);
+        let entry_kind = match kind {
+            EntryKind::Dir => ResolvedKind::Dir,
+            EntryKind::File(size, content_address) => {
+                let digest = self.digests.lock().unwrap().get(&content_address).copied();
+                ResolvedKind::File(size, content_address, digest)
+            }
+            EntryKind::Link(target) => ResolvedKind::Link(target),
+            EntryKind::Special(kind, major, minor) => ResolvedKind::Special(kind, major, minor),
+            EntryKind::Whiteout if !self.whiteouts => {
+                return Err(IncoherentStructure(format!(
+                    "Adding whiteout {}, whiteouts are allowed only in patch archives",
+                    entry.path()
+                ))
+                .into());
+            }
+            EntryKind::Whiteout => ResolvedKind::Whiteout,
+            EntryKind::HardLink(target) => {
+                ResolvedKind::HardLink(self.root_entry.find_file(target.components()).ok_or_else(
+                    || {
+                        IncoherentStructure(format!(
+                            "Adding {}, hard link target {target} is not an already added file",
+                            entry.path()
+                        ))
+                    },
+                )?)
+            }
+        };
         let path = entry.path();
         match path.parent() {
-            None => self
-                .root_entry
-                .add(entry, std::iter::empty(), &mut self.entry_store),
-            Some(parent) => self
-                .root_entry
-                .add(entry, parent.components(), &mut self.entry_store),
+            None => self.root_entry.add(
+                entry,
+                entry_kind,
+                std::iter::empty(),
+                &self.implicit_dirs,
+                &mut self.entry_store,
+            ),
+            Some(parent) => self.root_entry.add(
+                entry,
+                entry_kind,
+                parent.components(),
+                &self.implicit_dirs,
+                &mut self.entry_store,
+            ),
         }
     }
 }
@@ -342,6 +754,9 @@ impl jbk::creator::EntryStoreTrait for EntryStoreCreator {
         let root_count = self.entry_count();
         let entry_count = self.entry_store.len();
         directory_pack.add_value_store(self.path_store);
+        directory_pack.add_value_store(self.xattrs_store);
+        directory_pack.add_value_store(self.names_store);
+        directory_pack.add_value_store(self.digest_store);
         let entry_store_id = directory_pack.add_entry_store(self.entry_store);
         directory_pack.create_index(
             "arx_entries",
@@ -359,9 +774,58 @@ impl jbk::creator::EntryStoreTrait for EntryStoreCreator {
             root_count,
             jbk::EntryIdx::from(0).into(),
         );
+        if !self.metadata.is_empty() {
+            finalize_metadata(self.metadata, directory_pack);
+        }
     }
 }
 
+/// Store the archive-level metadata in their own entry store, indexed as `arx_metadata`.
+fn finalize_metadata(
+    metadata: BTreeMap<String, String>,
+    directory_pack: &mut jbk::creator::DirectoryPackCreator,
+) {
+    let metadata_store = jbk::creator::ValueStore::new_plain(None);
+    let metadata_def = schema::Schema::new(
+        schema::CommonProperties::new(vec![
+            schema::Property::new_array(0, metadata_store.clone(), Property::MetaKey),
+            schema::Property::new_array(0, metadata_store.clone(), Property::MetaValue),
+        ]),
+        vec![],
+        None,
+    );
+    let mut entry_store = Box::new(EntryStore::new(metadata_def, None));
+    let entry_count = metadata.len();
+    for (key, value) in metadata {
+        let values = HashMap::from([
+            (
+                Property::MetaKey,
+                jbk::Value::Array(key.into_bytes().into()),
+            ),
+            (
+                Property::MetaValue,
+                jbk::Value::Array(value.into_bytes().into()),
+            ),
+        ]);
+        let entry = Box::new(jbk::creator::BasicEntry::new_from_schema(
+            &entry_store.schema,
+            None,
+            values,
+        ));
+        entry_store.add_entry(entry);
+    }
+    directory_pack.add_value_store(metadata_store);
+    let entry_store_id = directory_pack.add_entry_store(entry_store);
+    directory_pack.create_index(
+        "arx_metadata",
+        Default::default(),
+        jbk::PropertyIdx::from(0),
+        entry_store_id,
+        jbk::EntryCount::from(entry_count as u32),
+        jbk::EntryIdx::from(0).into(),
+    );
+}
+
 impl Default for EntryStoreCreator {
     fn default() -> Self {
         Self::new()
@@ -456,4 +920,27 @@ mod tests {
         assert!(!index.is_empty());
         Ok(())
     }
+
+    #[test]
+    fn test_whiteout() -> Result {
+        let whiteout = Whiteout::new("foo.txt".into());
+
+        let mut entry_store_creator = Box::new(EntryStoreCreator::new());
+        assert!(entry_store_creator.add_entry(&whiteout).is_err());
+
+        let arx_file = tempfile::NamedTempFile::new_in(std::env::temp_dir())?;
+        let (mut arx_file, arx_name) = arx_file.into_parts();
+        let mut creator = jbk::creator::DirectoryPackCreator::new(
+            jbk::PackId::from(0),
+            crate::VENDOR_ID,
+            Default::default(),
+        );
+        let mut entry_store_creator = Box::new(EntryStoreCreator::new());
+        entry_store_creator.enable_whiteouts();
+        entry_store_creator.add_entry(&whiteout)?;
+        entry_store_creator.finalize(&mut creator);
+        creator.finalize()?.write(&mut arx_file)?;
+        assert!(arx_name.is_file());
+        Ok(())
+    }
 }
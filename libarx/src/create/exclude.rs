@@ -0,0 +1,258 @@
+use crate::{CreatorError, InputError};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+const MATCH_OPTIONS: glob::MatchOptions = glob::MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Names of the version control system files and directories (as excluded by `--exclude-vcs`).
+pub const VCS_NAMES: &[&str] = &[
+    "CVS",
+    ".cvsignore",
+    "RCS",
+    "SCCS",
+    ".git",
+    ".gitignore",
+    ".gitattributes",
+    ".gitmodules",
+    ".svn",
+    ".hg",
+    ".hgignore",
+    ".hgtags",
+    ".bzr",
+    ".bzrignore",
+    "_darcs",
+];
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: glob::Pattern,
+    /// Pattern is matched against the whole relative path instead of the file name.
+    anchored: bool,
+    dir_only: bool,
+    negated: bool,
+}
+
+/// A list of exclude patterns, using the `.gitignore` syntax.
+///
+/// - A pattern without `/` (except a trailing one) matches the name of an entry at any depth.
+/// - Other patterns are matched against the path relative to the directory the rules apply to.
+/// - A trailing `/` only matches directories.
+/// - A leading `!` re-includes entries excluded by a previous pattern.
+/// - Empty lines and lines starting with `#` are ignored.
+///
+/// When several patterns match, the last one wins.
+#[derive(Debug, Clone, Default)]
+pub struct ExcludeRules {
+    rules: Vec<Rule>,
+}
+
+impl ExcludeRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rules excluding the version control system files and directories.
+    pub fn vcs() -> Self {
+        let mut rules = Self::new();
+        for name in VCS_NAMES {
+            rules.add(name).expect("VCS names are valid patterns");
+        }
+        rules
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Add a pattern.
+    pub fn add(&mut self, pattern: &str) -> Result<(), InputError> {
+        let pattern = pattern.trim_end_matches(['\n', '\r']);
+        if pattern.is_empty() || pattern.starts_with('#') {
+            return Ok(());
+        }
+        let (negated, pattern) = match pattern.strip_prefix('!') {
+            Some(p) => (true, p),
+            None => (false, pattern.strip_prefix('\\').unwrap_or(pattern)),
+        };
+        let (dir_only, pattern) = match pattern.strip_suffix('/') {
+            Some(p) => (true, p),
+            None => (false, pattern),
+        };
+        let anchored = pattern.contains('/');
+        let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+        let pattern = glob::Pattern::new(pattern)
+            .map_err(|e| InputError(format!("Invalid exclude pattern {pattern:?}: {e}")))?;
+        self.rules.push(Rule {
+            pattern,
+            anchored,
+            dir_only,
+            negated,
+        });
+        Ok(())
+    }
+
+    /// Add all the patterns of `file` (one per line).
+    ///
+    /// The file is not required to be valid utf-8 (invalid bytes are replaced), patterns are
+    /// matched against the utf-8 paths only.
+    pub fn add_from_file(&mut self, file: impl AsRef<Path>) -> Result<(), CreatorError> {
+        let content = std::fs::read(file)?;
+        for line in content.split(|c| *c == b'\n') {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            self.add(&String::from_utf8_lossy(line))?;
+        }
+        Ok(())
+    }
+
+    /// Check the `relative_path` against the rules.
+    ///
+    /// Return `Some(true)` if the entry is excluded, `Some(false)` if it is explicitly re-included
+    /// and `None` if no rule matches.
+    pub fn matches(&self, relative_path: &Path, is_dir: bool) -> Option<bool> {
+        let file_name = relative_path.file_name().map(Path::new);
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                if rule.dir_only && !is_dir {
+                    return false;
+                }
+                if rule.anchored {
+                    rule.pattern.matches_path_with(relative_path, MATCH_OPTIONS)
+                } else {
+                    file_name.is_some_and(|n| rule.pattern.matches_path_with(n, MATCH_OPTIONS))
+                }
+            })
+            .map(|rule| !rule.negated)
+    }
+}
+
+/// The `depth` last components of `path`.
+fn tail(path: &Path, depth: usize) -> PathBuf {
+    let components: Vec<_> = path.components().collect();
+    components[components.len().saturating_sub(depth)..]
+        .iter()
+        .collect()
+}
+
+/// A filter to use with `FsAdder::add_from_path_with_filter`.
+///
+/// Entries are excluded following the global rules (relative to the added path) and
+/// the rules read from the ignore files (`.gitignore`, `.arxignore`, ...) found while walking
+/// (relative to the directory containing the ignore file).
+/// The added path itself is never excluded.
+#[derive(Debug, Clone, Default)]
+pub struct ExcludeFilter {
+    rules: ExcludeRules,
+    ignore_files: Vec<OsString>,
+    /// The rules of the ignore files of the directories being walked, with their depth.
+    stack: Vec<(usize, ExcludeRules)>,
+}
+
+impl ExcludeFilter {
+    pub fn new(rules: ExcludeRules) -> Self {
+        Self {
+            rules,
+            ignore_files: vec![],
+            stack: vec![],
+        }
+    }
+
+    /// Read the exclude rules of the files named `name` found in the walked directories.
+    pub fn ignore_file(mut self, name: impl Into<OsString>) -> Self {
+        self.ignore_files.push(name.into());
+        self
+    }
+
+    fn read_ignore_files(&self, dir: &Path) -> ExcludeRules {
+        let mut rules = ExcludeRules::new();
+        for name in &self.ignore_files {
+            let file = dir.join(name);
+            if !file.is_file() {
+                continue;
+            }
+            if let Err(e) = rules.add_from_file(&file) {
+                log::warn!("Cannot read ignore file {}: {e}", file.display());
+            }
+        }
+        rules
+    }
+
+    /// Return true if the entry at `path` (as given in a file list) must be added.
+    ///
+    /// Only the global rules apply (relative to `path` as given), ignore files are read only
+    /// when walking directories.
+    pub fn accept_path(&self, path: &Path) -> bool {
+        let is_dir = path.symlink_metadata().is_ok_and(|m| m.is_dir());
+        if self.rules.matches(path, is_dir) == Some(true) {
+            log::debug!("Excluding {}", path.display());
+            return false;
+        }
+        true
+    }
+
+    /// Return true if the entry must be added.
+    pub fn accept(&mut self, entry: &walkdir::DirEntry) -> bool {
+        let depth = entry.depth();
+        let is_dir = entry.file_type().is_dir();
+        while self.stack.last().is_some_and(|(d, _)| *d >= depth) {
+            self.stack.pop();
+        }
+        if depth > 0 {
+            let path = entry.path();
+            let mut excluded = self.rules.matches(&tail(path, depth), is_dir);
+            for (dir_depth, rules) in &self.stack {
+                if let Some(e) = rules.matches(&tail(path, depth - dir_depth), is_dir) {
+                    excluded = Some(e);
+                }
+            }
+            if excluded == Some(true) {
+                log::debug!("Excluding {}", path.display());
+                return false;
+            }
+        }
+        if is_dir && !self.ignore_files.is_empty() {
+            let rules = self.read_ignore_files(entry.path());
+            if !rules.is_empty() {
+                self.stack.push((depth, rules));
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustest::{test, *};
+
+    #[test]
+    fn test_rules() -> Result {
+        let mut rules = ExcludeRules::new();
+        for pattern in ["# comment", "*.o", "/build/", "doc/**/*.tmp", "!keep.o", ""] {
+            rules.add(pattern)?;
+        }
+        assert_eq!(rules.matches(Path::new("foo.o"), false), Some(true));
+        assert_eq!(rules.matches(Path::new("src/foo.o"), false), Some(true));
+        assert_eq!(rules.matches(Path::new("src/keep.o"), false), Some(false));
+        assert_eq!(rules.matches(Path::new("build"), true), Some(true));
+        assert_eq!(rules.matches(Path::new("build"), false), None);
+        assert_eq!(rules.matches(Path::new("src/build"), true), None);
+        assert_eq!(rules.matches(Path::new("doc/a/b/c.tmp"), false), Some(true));
+        assert_eq!(rules.matches(Path::new("src/c.tmp"), false), None);
+        assert_eq!(rules.matches(Path::new("# comment"), false), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_vcs() -> Result {
+        let rules = ExcludeRules::vcs();
+        assert_eq!(rules.matches(Path::new("project/.git"), true), Some(true));
+        assert_eq!(rules.matches(Path::new("project/src"), true), None);
+        Ok(())
+    }
+}
@@ -1,20 +1,28 @@
-use crate::create::{EntryKind, EntryTrait, SimpleCreator, Void};
-use crate::{CreatorError, InputError};
+use crate::common::LocalNames;
+use crate::create::{
+    BaseArchive, Change, CompressionPolicy, DigestContentAdder, EntryKind, EntryTrait,
+    SimpleCreator, Void,
+};
+use crate::{CreatorError, InputError, SpecialKind, Xattrs};
 use bstr::{BString, ByteVec};
 use jbk::creator::InputReader;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs::Metadata;
 #[cfg(unix)]
 use std::os::unix::fs::MetadataExt;
 #[cfg(windows)]
 use std::os::windows::fs::MetadataExt;
 use std::path::PathBuf;
-use std::{borrow::Cow, io::Cursor, sync::mpsc, thread::spawn};
+use std::sync::{mpsc, Arc, Mutex};
+use std::{borrow::Cow, io::Cursor, thread::spawn};
 
 #[derive(Debug)]
 pub enum FsEntryKind {
     Dir,
     File(jbk::Size, jbk::ContentAddress),
+    HardLink(crate::PathBuf),
     Link(PathBuf),
+    Special(SpecialKind, u32, u32),
     Other,
 }
 
@@ -23,6 +31,7 @@ pub enum DetectedEntryKind {
     Dir,
     File(u64, PathBuf),
     Link(PathBuf),
+    Special(SpecialKind, u32, u32),
     Other,
 }
 
@@ -31,8 +40,115 @@ pub struct FsEntry {
     pub arx_path: crate::PathBuf,
     uid: u64,
     gid: u64,
+    owner_name: Option<Vec<u8>>,
+    group_name: Option<Vec<u8>>,
     mode: u64,
     mtime: u64,
+    mtime_nsec: u32,
+    atime: u64,
+    ctime: u64,
+    btime: u64,
+    xattrs: Xattrs,
+}
+
+/// Convert a filesystem time into nanoseconds since epoch (0 if not available).
+fn nanoseconds(time: std::io::Result<std::time::SystemTime>) -> u64 {
+    time.ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos() as u64)
+}
+
+#[cfg(unix)]
+fn read_xattrs(path: &std::path::Path, follow_symlink: bool) -> Result<Xattrs, std::io::Error> {
+    use std::os::unix::ffi::OsStrExt;
+    let mut xattrs = Xattrs::new();
+    let names = if follow_symlink {
+        xattr::list_deref(path)
+    } else {
+        xattr::list(path)
+    };
+    let names = match names {
+        Ok(names) => names,
+        // Xattrs are not supported by the filesystem (or the platform)
+        Err(e) if e.kind() == std::io::ErrorKind::Unsupported => return Ok(xattrs),
+        Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => return Ok(xattrs),
+        Err(e) => return Err(e),
+    };
+    for name in names {
+        let value = if follow_symlink {
+            xattr::get_deref(path, &name)?
+        } else {
+            xattr::get(path, &name)?
+        };
+        // Attribute may have been removed since we list them.
+        if let Some(value) = value {
+            xattrs.insert(name.as_bytes(), value);
+        }
+    }
+    log::trace!("xattrs of {path:?} are {xattrs:?}");
+    Ok(xattrs)
+}
+
+#[cfg(not(unix))]
+fn read_xattrs(_path: &std::path::Path, _follow_symlink: bool) -> Result<Xattrs, std::io::Error> {
+    Ok(Xattrs::new())
+}
+
+#[cfg(unix)]
+fn is_hard_linked(attr: &Metadata) -> bool {
+    attr.nlink() > 1
+}
+
+#[cfg(not(unix))]
+fn is_hard_linked(_attr: &Metadata) -> bool {
+    false
+}
+
+/// The archive path of the files already added, indexed by their `(dev, ino)`.
+pub type HardLinks = HashMap<(u64, u64), crate::PathBuf>;
+
+/// The `(dev, ino)` of a file having several hard links.
+#[cfg(unix)]
+fn hard_link_inode(attr: &Metadata) -> Option<(u64, u64)> {
+    (attr.is_file() && attr.nlink() > 1).then(|| (attr.dev(), attr.ino()))
+}
+
+#[cfg(not(unix))]
+fn hard_link_inode(_attr: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Return the archive path of the first added file sharing the inode of `attr`.
+fn hard_link_target(attr: &Metadata, hard_links: &HardLinks) -> Option<crate::PathBuf> {
+    hard_link_inode(attr).and_then(|inode| hard_links.get(&inode).cloned())
+}
+
+#[cfg(unix)]
+fn detect_special(attr: &Metadata) -> Option<DetectedEntryKind> {
+    use std::os::unix::fs::FileTypeExt;
+    let file_type = attr.file_type();
+    let kind = if file_type.is_char_device() {
+        SpecialKind::CharDevice
+    } else if file_type.is_block_device() {
+        SpecialKind::BlockDevice
+    } else if file_type.is_fifo() {
+        SpecialKind::Fifo
+    } else if file_type.is_socket() {
+        SpecialKind::Socket
+    } else {
+        return None;
+    };
+    let rdev = attr.rdev() as libc::dev_t;
+    Some(DetectedEntryKind::Special(
+        kind,
+        libc::major(rdev) as u32,
+        libc::minor(rdev) as u32,
+    ))
+}
+
+#[cfg(not(unix))]
+fn detect_special(_attr: &Metadata) -> Option<DetectedEntryKind> {
+    None
 }
 
 fn detect_kind(
@@ -56,35 +172,139 @@ fn detect_kind(
     } else if attr.is_symlink() {
         DetectedEntryKind::Link(path)
     } else {
-        DetectedEntryKind::Other
+        detect_special(&attr).unwrap_or(DetectedEntryKind::Other)
     };
     Ok((kind, attr))
 }
 
+/// Files smaller than this are read in memory instead of being opened.
+pub(super) const SMALL_FILE_SIZE: u64 = 1024 * 1024;
+
+/// What can be read from the filesystem about an entry before adding it.
+///
+/// This doesn't depend on the creator, so entries can be read in parallel
+/// (see `FsAdder::set_jobs`) and added in order afterwards.
+pub struct ReadEntry {
+    fs_path: PathBuf,
+    pub(crate) kind: DetectedEntryKind,
+    pub(crate) attr: Metadata,
+    pub(crate) xattrs: Xattrs,
+    /// The content of small files (see `read_content`).
+    pub(crate) content: Option<Vec<u8>>,
+    /// The BLAKE3 digest of the content of files (see `read_content`).
+    pub(crate) digest: Option<[u8; 32]>,
+    /// The address of the same content already in the archive (see `SimpleCreator::reuse_content`).
+    pub(crate) content_address: Option<jbk::ContentAddress>,
+}
+
+impl ReadEntry {
+    pub fn new(fs_path: &std::path::Path, follow_symlink: bool) -> Result<Self, std::io::Error> {
+        let (kind, attr) = detect_kind(fs_path.to_path_buf(), follow_symlink)?;
+        let xattrs = read_xattrs(fs_path, follow_symlink)?;
+        Ok(Self {
+            fs_path: fs_path.to_path_buf(),
+            kind,
+            attr,
+            xattrs,
+            content: None,
+            digest: None,
+            content_address: None,
+        })
+    }
+
+    /// Read the content of small files and compute the digest of files, if not already done.
+    ///
+    /// The digest is given to the content adder, so it doesn't read the content a second time
+    /// to compute it. Files whose digest is not computed are hashed when their content is added.
+    pub(crate) fn read_content(&mut self) -> Result<(), std::io::Error> {
+        let DetectedEntryKind::File(file_size, path) = &self.kind else {
+            return Ok(());
+        };
+        if self.digest.is_some() {
+            return Ok(());
+        }
+        let mut hasher = blake3::Hasher::new();
+        if *file_size < SMALL_FILE_SIZE {
+            let content = std::fs::read(path)?;
+            hasher.update(&content);
+            self.content = Some(content);
+        } else {
+            std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+        }
+        self.digest = Some(*hasher.finalize().as_bytes());
+        Ok(())
+    }
+}
+
 impl FsEntry {
-    pub fn new_from_path<A: jbk::creator::ContentAdder>(
+    pub fn new_from_path<A: DigestContentAdder>(
         fs_path: &std::path::Path,
         arx_path: crate::PathBuf,
         adder: &mut A,
+        comp_hint: jbk::creator::CompHint,
         follow_symlink: bool,
+        hard_links: &HardLinks,
+        names: &LocalNames,
     ) -> Result<Box<Self>, CreatorError> {
-        let (kind, attr) = detect_kind(fs_path.to_path_buf(), follow_symlink)?;
+        let read_entry = ReadEntry::new(fs_path, follow_symlink)?;
+        Self::new_from_read_entry(read_entry, arx_path, adder, comp_hint, hard_links, names)
+    }
+
+    pub fn new_from_read_entry<A: DigestContentAdder>(
+        read_entry: ReadEntry,
+        arx_path: crate::PathBuf,
+        adder: &mut A,
+        comp_hint: jbk::creator::CompHint,
+        hard_links: &HardLinks,
+        names: &LocalNames,
+    ) -> Result<Box<Self>, CreatorError> {
+        let ReadEntry {
+            fs_path,
+            kind,
+            attr,
+            xattrs,
+            content,
+            digest,
+            content_address,
+        } = read_entry;
         let kind = match kind {
             DetectedEntryKind::Dir => FsEntryKind::Dir,
-            DetectedEntryKind::File(file_size, path) => {
-                let reader: Box<dyn InputReader> = if file_size < 1024 * 1024 {
-                    let content = std::fs::read(&path)?;
-                    Box::new(Cursor::new(content))
-                } else {
-                    Box::new(jbk::creator::InputFile::open(&path)?)
-                };
-                let content_address = adder.add_content(reader, jbk::creator::CompHint::Detect)?;
-                FsEntryKind::File(file_size.into(), content_address)
-            }
+            DetectedEntryKind::File(file_size, path) => match hard_link_target(&attr, hard_links) {
+                Some(target) => FsEntryKind::HardLink(target),
+                None => {
+                    let content_address = match content_address {
+                        Some(content_address) => content_address,
+                        None => {
+                            let reader: Box<dyn InputReader> = match content {
+                                Some(content) => Box::new(Cursor::new(content)),
+                                None => Box::new(jbk::creator::InputFile::open(&path)?),
+                            };
+                            match digest {
+                                Some(digest) => {
+                                    adder.add_content_with_digest(reader, comp_hint, digest)?
+                                }
+                                None => adder.add_content(reader, comp_hint)?,
+                            }
+                        }
+                    };
+                    FsEntryKind::File(file_size.into(), content_address)
+                }
+            },
             DetectedEntryKind::Link(path) => FsEntryKind::Link(std::fs::read_link(&path)?),
+            DetectedEntryKind::Special(kind, major, minor) => {
+                FsEntryKind::Special(kind, major, minor)
+            }
             DetectedEntryKind::Other => FsEntryKind::Other,
         };
         log::debug!("{fs_path:?} is dectected as a {kind:?}");
+        #[cfg(unix)]
+        let (owner_name, group_name) = (names.user_name(attr.uid()), names.group_name(attr.gid()));
+        // Names are not used on windows.
+        #[cfg(windows)]
+        let (owner_name, group_name) = {
+            let _ = names;
+            (None, None)
+        };
         Ok(Box::new(Self {
             kind,
             arx_path,
@@ -96,6 +316,8 @@ impl FsEntry {
             gid: attr.gid() as u64,
             #[cfg(windows)]
             gid: 1000,
+            owner_name,
+            group_name,
             #[cfg(unix)]
             mode: attr.mode() as u64,
             #[cfg(windows)]
@@ -105,6 +327,14 @@ impl FsEntry {
             #[cfg(windows)]
             mtime: epochs::to_unix(epochs::windows_file(attr.last_write_time() as i64).unwrap())
                 as u64,
+            mtime_nsec: (nanoseconds(attr.modified()) % 1_000_000_000) as u32,
+            atime: nanoseconds(attr.accessed()),
+            #[cfg(unix)]
+            ctime: (attr.ctime() as u64) * 1_000_000_000 + attr.ctime_nsec() as u64,
+            #[cfg(windows)]
+            ctime: 0,
+            btime: nanoseconds(attr.created()),
+            xattrs,
         }))
     }
 }
@@ -116,10 +346,13 @@ impl EntryTrait for FsEntry {
             FsEntryKind::File(size, content_address) => {
                 Some(EntryKind::File(*size, *content_address))
             }
+            FsEntryKind::HardLink(target) => Some(EntryKind::HardLink(target.clone())),
+            FsEntryKind::Special(kind, major, minor) => {
+                Some(EntryKind::Special(*kind, *major, *minor))
+            }
 
             FsEntryKind::Link(target) => Some(EntryKind::Link(BString::from(
-                Vec::from_path_buf(target.clone())
-                    .unwrap_or_else(|target| panic!("{target:?} must be utf-8")),
+                Vec::from_path_lossy(target).into_owned(),
             ))),
             _ => None,
         })
@@ -134,12 +367,33 @@ impl EntryTrait for FsEntry {
     fn gid(&self) -> u64 {
         self.gid
     }
+    fn owner_name(&self) -> Option<&[u8]> {
+        self.owner_name.as_deref()
+    }
+    fn group_name(&self) -> Option<&[u8]> {
+        self.group_name.as_deref()
+    }
     fn mode(&self) -> u64 {
         self.mode
     }
     fn mtime(&self) -> u64 {
         self.mtime
     }
+    fn mtime_nsec(&self) -> u32 {
+        self.mtime_nsec
+    }
+    fn atime_ns(&self) -> u64 {
+        self.atime
+    }
+    fn ctime_ns(&self) -> u64 {
+        self.ctime
+    }
+    fn btime_ns(&self) -> u64 {
+        self.btime
+    }
+    fn xattrs(&self) -> Option<&Xattrs> {
+        Some(&self.xattrs)
+    }
 }
 
 struct Trimer<'a>(Option<&'a std::path::Path>);
@@ -173,7 +427,38 @@ impl<'a> Trimer<'a> {
     }
 }
 
+/// Convert a path, escaping the invalid bytes (see `crate::escape_bytes`).
+#[cfg(unix)]
+fn escaped_to_arx_path(path: &std::path::Path) -> Result<crate::PathBuf, InputError> {
+    use crate::escape_bytes;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Component;
+    let mut arx_path = crate::PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => arx_path.push(escape_bytes(name.as_bytes()).as_ref()),
+            Component::CurDir => arx_path.push("."),
+            Component::ParentDir => arx_path.push(".."),
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(InputError(format!(
+                    "{} is not a relative path",
+                    path.display()
+                )))
+            }
+        }
+    }
+    Ok(arx_path)
+}
+
 fn to_arx_path(path: &std::path::Path) -> Result<Cow<'_, crate::Path>, InputError> {
+    // Valid utf-8 names may contain the escape chars, they must be escaped too.
+    #[cfg(unix)]
+    if path
+        .to_str()
+        .is_none_or(|p| matches!(crate::escape_bytes(p.as_bytes()), Cow::Owned(_)))
+    {
+        return escaped_to_arx_path(path).map(Cow::Owned);
+    }
     let ret = match crate::Path::from_path(path) {
         Ok(p) => Ok(p.into()),
         Err(e) => {
@@ -201,11 +486,74 @@ fn to_arx_path(path: &std::path::Path) -> Result<Cow<'_, crate::Path>, InputErro
     })
 }
 
+/// What to do when a file cannot be read while adding it.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cmd_utils", derive(clap::ValueEnum))]
+pub enum OnError {
+    /// Stop and return the error
+    #[default]
+    Abort,
+    /// Skip the file silently
+    Skip,
+    /// Skip the file with a warning
+    Warn,
+}
+
+/// What to do with directories on another filesystem than the walked input path.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum MountPoints {
+    /// Walk into mount points as in any other directory
+    #[default]
+    Cross,
+    /// Don't add mount points nor their content (as `--one-file-system` does)
+    Exclude,
+    /// Add mount points as empty directories
+    EmptyDir,
+}
+
+/// The device of a file, if the platform has one.
+#[cfg(unix)]
+fn device(attr: &Metadata) -> Option<u64> {
+    Some(attr.dev())
+}
+
+#[cfg(not(unix))]
+fn device(_attr: &Metadata) -> Option<u64> {
+    None
+}
+
+/// A file which has been skipped because of an error (see `OnError`).
+#[derive(Debug)]
+pub struct SkippedEntry {
+    pub path: PathBuf,
+    pub error: std::io::Error,
+}
+
+/// An entry waiting to be added, possibly still being read on the thread pool.
+struct PendingEntry {
+    path: PathBuf,
+    arx_path: crate::PathBuf,
+    read_entry: mpsc::Receiver<Result<ReadEntry, std::io::Error>>,
+}
+
 pub struct FsAdder<'a> {
     creator: &'a mut SimpleCreator,
     keep_parents: bool,
     follow_symlink: bool,
     dir_as_root: bool,
+    mount_points: MountPoints,
+    hard_links: HardLinks,
+    /// The `(dev, ino)` of the hard linked files already read on the thread pool.
+    read_inodes: Arc<Mutex<HashSet<(u64, u64)>>>,
+    names: LocalNames,
+    compression_policy: Option<Box<dyn CompressionPolicy>>,
+    on_error: OnError,
+    skipped: Vec<SkippedEntry>,
+    pool: Option<rayon::ThreadPool>,
+    /// The archive paths of the added directories, if parents are added from the filesystem.
+    added_dirs: Option<HashSet<crate::PathBuf>>,
+    base_archive: Option<&'a mut BaseArchive>,
+    skip_unchanged: bool,
 }
 
 impl<'a> FsAdder<'a> {
@@ -214,13 +562,99 @@ impl<'a> FsAdder<'a> {
         keep_parents: bool,
         follow_symlink: bool,
         dir_as_root: bool,
+        mount_points: MountPoints,
     ) -> Self {
         Self {
             creator,
             keep_parents,
             follow_symlink,
             dir_as_root,
+            mount_points,
+            hard_links: Default::default(),
+            read_inodes: Default::default(),
+            names: Default::default(),
+            compression_policy: None,
+            on_error: OnError::Abort,
+            skipped: vec![],
+            pool: None,
+            added_dirs: None,
+            base_archive: None,
+            skip_unchanged: false,
+        }
+    }
+
+    /// Compare the added entries with the entries of `base_archive`.
+    ///
+    /// Use `BaseArchive::finish` once all entries are added to get the changes.
+    pub fn set_base_archive(&mut self, base_archive: &'a mut BaseArchive) {
+        self.base_archive = Some(base_archive);
+    }
+
+    /// Do not add the entries unchanged since the base archive (see `set_base_archive`).
+    ///
+    /// Directories are always added, as are hard linked files (so other links to the
+    /// same inode can refer to them).
+    pub fn set_skip_unchanged(&mut self, skip_unchanged: bool) {
+        self.skip_unchanged = skip_unchanged;
+    }
+
+    /// Add the missing parents of the entries with the metadata of their counterpart on the filesystem.
+    ///
+    /// When adding `foo/bar/baz.txt`, `foo` and `bar` are added from the directories containing
+    /// `baz.txt` on the filesystem (or their nearest existing ancestor) instead of being created
+    /// implicitly by the creator.
+    pub fn set_parents_from_fs(&mut self, parents_from_fs: bool) {
+        self.added_dirs = parents_from_fs.then(HashSet::new);
+    }
+
+    /// Read the files with `jobs` threads.
+    ///
+    /// Entries are still added to the archive in the order they are walked (or listed).
+    /// With `jobs <= 1` (the default), files are read in the calling thread.
+    pub fn set_jobs(&mut self, jobs: usize) -> Void {
+        self.pool = if jobs > 1 {
+            Some(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(jobs)
+                    .build()
+                    .map_err(std::io::Error::other)?,
+            )
+        } else {
+            None
+        };
+        Ok(())
+    }
+
+    /// Choose what to do with unreadable, vanished files and broken symlinks.
+    ///
+    /// Other errors always abort.
+    pub fn set_on_error(&mut self, on_error: OnError) {
+        self.on_error = on_error;
+    }
+
+    /// The entries skipped so far because of an error.
+    pub fn skipped(&self) -> &[SkippedEntry] {
+        &self.skipped
+    }
+
+    fn handle_io_error(&mut self, path: &std::path::Path, error: std::io::Error) -> Void {
+        match self.on_error {
+            OnError::Abort => return Err(error.into()),
+            OnError::Skip => log::info!("Skipping {}: {error}", path.display()),
+            OnError::Warn => log::warn!("Skipping {}: {error}", path.display()),
         }
+        self.skipped.push(SkippedEntry {
+            path: path.to_path_buf(),
+            error,
+        });
+        Ok(())
+    }
+
+    /// Use `policy` to choose if the content of the added files must be compressed.
+    ///
+    /// By default, compression is detected.
+    pub fn set_compression_policy(&mut self, policy: impl CompressionPolicy + 'static) {
+        self.compression_policy = Some(Box::new(policy));
     }
 
     pub fn add_from_path<P>(&mut self, path: P, recurse: bool) -> Void
@@ -242,21 +676,55 @@ impl<'a> FsAdder<'a> {
         let path_copy = path.to_path_buf();
         let follow_symlink = self.follow_symlink;
         let trimmer = Trimer::new(self.keep_parents, path);
+        // Walk in a stable order so contents are added in the same order whatever the fs.
+        let sorted = self.creator.reproducible().is_some();
+        let mount_points = self.mount_points;
 
         spawn(move || {
-            let mut walker = walkdir::WalkDir::new(path_copy).follow_links(follow_symlink);
+            // Mount points are detected comparing devices with the one of the walked path.
+            let root_device = match mount_points {
+                MountPoints::EmptyDir => std::fs::metadata(&path_copy)
+                    .ok()
+                    .and_then(|attr| device(&attr)),
+                _ => None,
+            };
+            let mut walker = walkdir::WalkDir::new(path_copy)
+                .follow_links(follow_symlink)
+                .same_file_system(mount_points == MountPoints::Exclude);
 
             if !recurse {
                 walker = walker.max_depth(0);
             }
-            let walker = walker.into_iter();
-            for entry in walker.filter_entry(filter) {
-                let entry = entry.unwrap();
-                tx.send(entry).unwrap();
+            if sorted {
+                walker = walker.sort_by_file_name();
+            }
+            let mut walker = walker.into_iter().filter_entry(filter);
+            while let Some(entry) = walker.next() {
+                if let (Ok(entry), Some(root_device)) = (&entry, root_device) {
+                    if entry.depth() > 0
+                        && entry.file_type().is_dir()
+                        && entry.metadata().ok().and_then(|attr| device(&attr)) != Some(root_device)
+                    {
+                        walker.skip_current_dir();
+                    }
+                }
+                if tx.send(entry).is_err() {
+                    // Receiver has stopped on error
+                    break;
+                }
             }
         });
 
+        let mut pending = VecDeque::new();
         while let Ok(entry) = rx.recv() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    let error_path = e.path().unwrap_or(path).to_path_buf();
+                    self.handle_io_error(&error_path, e.into())?;
+                    continue;
+                }
+            };
             // We always want to follow link if this is the root entry.
             // This allow user to create a link to a file/dir to add the entry under a different name.
             // Walkdir will do the same anyway if it is a directory.
@@ -273,20 +741,91 @@ impl<'a> FsAdder<'a> {
                 trimmer.trim(entry.path())
             };
             let arx_path = to_arx_path(entry_path)?;
-            self.add_entry_from_path(entry.path(), &arx_path, is_root_entry)?;
+            self.queue_entry(&mut pending, entry.path(), &arx_path, is_root_entry)?;
         }
-        Ok(())
+        self.flush(pending)
     }
 
     pub fn add_from_list<Iter>(&mut self, paths: Iter) -> Void
     where
         Iter: Iterator<Item = std::path::PathBuf>,
     {
+        let mut pending = VecDeque::new();
         for path in paths {
             let trimer = Trimer::new(self.keep_parents, &path);
             let arx_path = trimer.trim(&path);
             let arx_path = to_arx_path(arx_path)?;
-            self.add_entry_from_path(&path, &arx_path, false)?;
+            self.queue_entry(&mut pending, &path, &arx_path, false)?;
+        }
+        self.flush(pending)
+    }
+
+    /// Add the entry, or start reading it on the thread pool if there is one.
+    fn queue_entry(
+        &mut self,
+        pending: &mut VecDeque<PendingEntry>,
+        path: &std::path::Path,
+        arx_path: &crate::Path,
+        is_root_dir: bool,
+    ) -> Void {
+        let Some(pool) = &self.pool else {
+            return self.add_entry_from_path(path, arx_path, is_root_dir);
+        };
+        log::debug!("queue_path(path:{path:?}, arx_path: {arx_path:?}, is_root_dir:{is_root_dir})");
+        if arx_path.as_str().is_empty() {
+            return Ok(());
+        }
+        let (tx, rx) = mpsc::sync_channel(1);
+        let fs_path = path.to_path_buf();
+        let follow_symlink = self.follow_symlink || is_root_dir;
+        // With a base archive, the content is read only if the metadata are not enough to
+        // compare the entries (see `BaseArchive::compare`).
+        let read_content = self.base_archive.is_none();
+        let read_inodes = Arc::clone(&self.read_inodes);
+        pool.spawn(move || {
+            let read_entry = ReadEntry::new(&fs_path, follow_symlink).and_then(|mut read_entry| {
+                // Only the first link of an inode is read, the others are added as hard links.
+                let first_link = match hard_link_inode(&read_entry.attr) {
+                    Some(inode) => read_inodes.lock().unwrap().insert(inode),
+                    None => true,
+                };
+                if read_content && first_link {
+                    read_entry.read_content()?;
+                }
+                Ok(read_entry)
+            });
+            // Receiver is dropped if adding a previous entry failed.
+            let _ = tx.send(read_entry);
+        });
+        // Bound the number of entries (and small file contents) kept in memory.
+        let max_pending = 4 * pool.current_num_threads();
+        pending.push_back(PendingEntry {
+            path: path.to_path_buf(),
+            arx_path: arx_path.to_relative_path_buf(),
+            read_entry: rx,
+        });
+        while pending.len() > max_pending {
+            let entry = pending.pop_front().unwrap();
+            self.add_pending_entry(entry)?;
+        }
+        Ok(())
+    }
+
+    fn add_pending_entry(&mut self, entry: PendingEntry) -> Void {
+        // The sender is dropped without sending only if the reading thread panicked.
+        let read_entry = entry.read_entry.recv().map_err(|_| {
+            std::io::Error::other(format!(
+                "Reading thread stopped before reading {}",
+                entry.path.display()
+            ))
+        })?;
+        self.add_read_entry(&entry.path, &entry.arx_path, read_entry)
+    }
+
+    /// Add all the pending entries, in order.
+    fn flush(&mut self, pending: VecDeque<PendingEntry>) -> Void {
+        for entry in pending {
+            self.add_pending_entry(entry)?;
         }
         Ok(())
     }
@@ -301,13 +840,105 @@ impl<'a> FsAdder<'a> {
         if arx_path.as_str().is_empty() {
             return Ok(());
         }
-        let entry = FsEntry::new_from_path(
-            path,
+        let read_entry = ReadEntry::new(path, self.follow_symlink || is_root_dir);
+        self.add_read_entry(path, arx_path, read_entry)
+    }
+
+    fn add_read_entry(
+        &mut self,
+        path: &std::path::Path,
+        arx_path: &crate::Path,
+        read_entry: Result<ReadEntry, std::io::Error>,
+    ) -> Void {
+        let read_entry = match read_entry {
+            Ok(read_entry) => read_entry,
+            Err(e) => return self.handle_io_error(path, e),
+        };
+        let mut read_entry = read_entry;
+        // Entries are compared with the base archive at the path they are stored at.
+        let stored_path = match &self.base_archive {
+            Some(_) => self.creator.stored_path(arx_path)?,
+            None => None,
+        };
+        if let (Some(base_archive), Some(stored_path)) = (&mut self.base_archive, stored_path) {
+            let (change, same_file) =
+                match base_archive.compare(&stored_path, &mut read_entry, &self.names) {
+                    Ok(compared) => compared,
+                    Err(CreatorError::IoError(e)) => return self.handle_io_error(path, e),
+                    Err(e) => return Err(e),
+                };
+            log::info!("{stored_path} is {change}");
+            let skippable = match read_entry.kind {
+                DetectedEntryKind::Dir => false,
+                DetectedEntryKind::File(..) => !is_hard_linked(&read_entry.attr),
+                _ => true,
+            };
+            if self.skip_unchanged && change == Change::Unchanged && skippable {
+                return Ok(());
+            }
+            if let Some(file) = same_file {
+                read_entry.content_address =
+                    self.creator
+                        .reuse_content(base_archive.arx(), base_archive.path(), &file)?;
+            }
+        }
+        let comp_hint = match &self.compression_policy {
+            None => jbk::creator::CompHint::Detect,
+            Some(policy) => policy.comp_hint(arx_path),
+        };
+        let inode = hard_link_inode(&read_entry.attr);
+        let entry = match FsEntry::new_from_read_entry(
+            read_entry,
             arx_path.into(),
             self.creator.adder(),
-            self.follow_symlink || is_root_dir,
-        )?;
+            comp_hint,
+            &self.hard_links,
+            &self.names,
+        ) {
+            Ok(entry) => entry,
+            Err(CreatorError::IoError(e)) => return self.handle_io_error(path, e),
+            Err(e) => return Err(e),
+        };
+
+        if self.added_dirs.is_some() {
+            self.add_parents(path, arx_path)?;
+            if let FsEntryKind::Dir = entry.kind {
+                if let Some(added_dirs) = &mut self.added_dirs {
+                    added_dirs.insert(arx_path.to_relative_path_buf());
+                }
+            }
+        }
+        self.creator.add_entry(entry.as_ref())?;
+        // The other links target this file only once it is added (it may be skipped on error).
+        if let (Some(inode), FsEntryKind::File(..)) = (inode, &entry.kind) {
+            self.hard_links
+                .insert(inode, arx_path.to_relative_path_buf());
+        }
+        Ok(())
+    }
 
-        self.creator.add_entry(entry.as_ref())
+    /// Add the parents of `arx_path` not already added, reading them from the ancestors of `path`.
+    fn add_parents(&mut self, path: &std::path::Path, arx_path: &crate::Path) -> Void {
+        let Some(added_dirs) = &self.added_dirs else {
+            return Ok(());
+        };
+        let mut missing_parents = vec![];
+        let mut fs_parent = path.parent();
+        let mut arx_parent = arx_path.parent();
+        while let Some(parent) = arx_parent.filter(|p| !p.as_str().is_empty()) {
+            if added_dirs.contains(parent) {
+                break;
+            }
+            missing_parents.push((fs_parent, parent));
+            fs_parent = fs_parent.and_then(|p| p.parent());
+            arx_parent = parent.parent();
+        }
+        for (fs_parent, arx_parent) in missing_parents.into_iter().rev() {
+            let fs_parent = fs_parent
+                .and_then(|p| p.ancestors().find(|p| p.is_dir()))
+                .unwrap_or(std::path::Path::new("."));
+            self.add_entry_from_path(fs_parent, arx_parent, true)?;
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,122 @@
+use super::{EntryKind, EntryTrait, Owner};
+use crate::CreatorError;
+
+/// The metadata of a directory created implicitly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImplicitDirMetadata {
+    pub owner: Owner,
+    pub group: Owner,
+    pub mode: u64,
+    pub mtime: u64,
+}
+
+impl Default for ImplicitDirMetadata {
+    fn default() -> Self {
+        Self {
+            owner: Owner {
+                id: 1000,
+                name: None,
+            },
+            group: Owner {
+                id: 1000,
+                name: None,
+            },
+            mode: 0o755,
+            mtime: 0,
+        }
+    }
+}
+
+impl ImplicitDirMetadata {
+    /// The metadata of `entry`, once normalized/overridden (see `SimpleCreator`).
+    pub(crate) fn from_entry<E: EntryTrait + ?Sized>(entry: &E) -> Self {
+        Self {
+            owner: Owner {
+                id: entry.uid(),
+                name: entry.owner_name().map(<[u8]>::to_vec),
+            },
+            group: Owner {
+                id: entry.gid(),
+                name: entry.group_name().map(<[u8]>::to_vec),
+            },
+            mode: entry.mode(),
+            mtime: entry.mtime(),
+        }
+    }
+}
+
+/// An implicit directory seen as an entry, to apply the creator's metadata changes on it.
+pub(crate) struct ImplicitDirEntry<'a>(pub(crate) &'a ImplicitDirMetadata);
+
+impl EntryTrait for ImplicitDirEntry<'_> {
+    fn kind(&self) -> Result<Option<EntryKind>, CreatorError> {
+        Ok(Some(EntryKind::Dir))
+    }
+    fn path(&self) -> &crate::Path {
+        crate::Path::new("")
+    }
+    fn uid(&self) -> u64 {
+        self.0.owner.id
+    }
+    fn gid(&self) -> u64 {
+        self.0.group.id
+    }
+    fn owner_name(&self) -> Option<&[u8]> {
+        self.0.owner.name.as_deref()
+    }
+    fn group_name(&self) -> Option<&[u8]> {
+        self.0.group.name.as_deref()
+    }
+    fn mode(&self) -> u64 {
+        self.0.mode
+    }
+    fn mtime(&self) -> u64 {
+        self.0.mtime
+    }
+}
+
+/// How to set the metadata of the directories created implicitly.
+///
+/// A directory is created implicitly when an entry is added without its parent
+/// (`foo/bar.txt` without `foo`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImplicitDirs {
+    /// Use the same metadata for all implicit directories.
+    ///
+    /// As for other entries, the metadata are normalized and overridden if the creator is set
+    /// to (see `SimpleCreator::set_reproducible` and `SimpleCreator::set_metadata_override`).
+    Fixed(ImplicitDirMetadata),
+    /// Use the metadata of the entry whose addition creates the directory.
+    ///
+    /// Execution bits are set where read bits are, so the directory can be traversed.
+    FromChild,
+}
+
+impl Default for ImplicitDirs {
+    fn default() -> Self {
+        Self::Fixed(Default::default())
+    }
+}
+
+impl ImplicitDirs {
+    pub(crate) fn metadata<E: EntryTrait + ?Sized>(&self, child: &E) -> ImplicitDirMetadata {
+        match self {
+            Self::Fixed(metadata) => metadata.clone(),
+            Self::FromChild => {
+                let permissions = child.mode() & 0o777;
+                ImplicitDirMetadata {
+                    owner: Owner {
+                        id: child.uid(),
+                        name: child.owner_name().map(<[u8]>::to_vec),
+                    },
+                    group: Owner {
+                        id: child.gid(),
+                        name: child.group_name().map(<[u8]>::to_vec),
+                    },
+                    mode: permissions | (permissions & 0o444) >> 2,
+                    mtime: child.mtime(),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use super::{EntryKind, EntryTrait};
+use crate::common::LocalNames;
+use crate::{CreatorError, InputError, Xattrs};
+
+/// An owner (or group) to set on entries: an id and optionally a name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Owner {
+    pub id: u64,
+    pub name: Option<Vec<u8>>,
+}
+
+impl Owner {
+    fn parse(
+        s: &str,
+        what: &str,
+        lookup: impl FnOnce(&[u8]) -> Option<u32>,
+    ) -> Result<Self, InputError> {
+        let invalid = || InputError(format!("Invalid {what} {s:?}"));
+        if s.is_empty() {
+            return Err(invalid());
+        }
+        if let Some((name, id)) = s.rsplit_once(':') {
+            let id = id.parse().map_err(|_| invalid())?;
+            let name = (!name.is_empty()).then(|| name.as_bytes().to_vec());
+            return Ok(Self { id, name });
+        }
+        if let Ok(id) = s.parse() {
+            return Ok(Self { id, name: None });
+        }
+        match lookup(s.as_bytes()) {
+            Some(id) => Ok(Self {
+                id: id as u64,
+                name: Some(s.as_bytes().to_vec()),
+            }),
+            None => Err(InputError(format!(
+                "Unknown {what} {s:?} (use NAME:ID to give the id)"
+            ))),
+        }
+    }
+
+    /// Parse a user given as `ID`, `NAME` (looked up in the local user database) or `NAME:ID`.
+    pub fn parse_user(s: &str) -> Result<Self, InputError> {
+        Self::parse(s, "user", |name| LocalNames::default().uid(name))
+    }
+
+    /// Parse a group given as `ID`, `NAME` (looked up in the local group database) or `NAME:ID`.
+    pub fn parse_group(s: &str) -> Result<Self, InputError> {
+        Self::parse(s, "group", |name| LocalNames::default().gid(name))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModeOp {
+    Add,
+    Remove,
+    Set,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ModeClause {
+    Absolute(u64),
+    Symbolic {
+        who: u64,
+        op: ModeOp,
+        perms: u64,
+        /// `X`: set execution bits only on directories or already executable entries.
+        conditional_exec: bool,
+    },
+}
+
+/// A chmod-like mode change.
+///
+/// Either an octal mode (`644`) or a comma separated list of symbolic clauses (`u+rwX,go-w`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModeSpec(Vec<ModeClause>);
+
+impl ModeSpec {
+    pub fn parse(s: &str) -> Result<Self, InputError> {
+        let invalid = || InputError(format!("Invalid mode {s:?}"));
+        if !s.is_empty() && s.bytes().all(|c| c.is_ascii_digit()) {
+            let mode = u64::from_str_radix(s, 8).map_err(|_| invalid())?;
+            if mode > 0o7777 {
+                return Err(invalid());
+            }
+            return Ok(Self(vec![ModeClause::Absolute(mode)]));
+        }
+        let mut clauses = vec![];
+        for clause in s.split(',') {
+            let mut chars = clause.chars().peekable();
+            let mut who = 0;
+            while let Some(c) = chars.next_if(|c| "ugoa".contains(*c)) {
+                who |= match c {
+                    'u' => 0o4700,
+                    'g' => 0o2070,
+                    'o' => 0o1007,
+                    _ => 0o7777,
+                };
+            }
+            if who == 0 {
+                who = 0o7777;
+            }
+            if chars.peek().is_none() {
+                return Err(invalid());
+            }
+            while let Some(op) = chars.next() {
+                let op = match op {
+                    '+' => ModeOp::Add,
+                    '-' => ModeOp::Remove,
+                    '=' => ModeOp::Set,
+                    _ => return Err(invalid()),
+                };
+                let mut perms = 0;
+                let mut conditional_exec = false;
+                while let Some(c) = chars.next_if(|c| "rwxXst".contains(*c)) {
+                    match c {
+                        'r' => perms |= 0o444,
+                        'w' => perms |= 0o222,
+                        'x' => perms |= 0o111,
+                        'X' => conditional_exec = true,
+                        's' => perms |= 0o6000,
+                        _ => perms |= 0o1000,
+                    }
+                }
+                clauses.push(ModeClause::Symbolic {
+                    who,
+                    op,
+                    perms,
+                    conditional_exec,
+                });
+            }
+        }
+        Ok(Self(clauses))
+    }
+
+    /// Apply the change to `mode`. File type bits (above `0o7777`) are kept.
+    pub fn apply(&self, mut mode: u64, is_dir: bool) -> u64 {
+        for clause in &self.0 {
+            match clause {
+                ModeClause::Absolute(new_mode) => mode = (mode & !0o7777) | new_mode,
+                ModeClause::Symbolic {
+                    who,
+                    op,
+                    perms,
+                    conditional_exec,
+                } => {
+                    let mut perms = *perms;
+                    if *conditional_exec && (is_dir || mode & 0o111 != 0) {
+                        perms |= 0o111;
+                    }
+                    let perms = perms & who;
+                    match op {
+                        ModeOp::Add => mode |= perms,
+                        ModeOp::Remove => mode &= !perms,
+                        ModeOp::Set => mode = (mode & !who) | perms,
+                    }
+                }
+            }
+        }
+        mode
+    }
+}
+
+/// Metadata to force on entries, as `tar --owner/--group/--mode/--mtime` do.
+///
+/// Use `MetadataOverride::apply` to wrap any `EntryTrait` or
+/// `SimpleCreator::set_metadata_override` to apply it to all added entries.
+#[derive(Debug, Clone, Default)]
+pub struct MetadataOverride {
+    pub owner: Option<Owner>,
+    pub group: Option<Owner>,
+    pub mode: Option<ModeSpec>,
+    pub mtime: Option<u64>,
+}
+
+impl MetadataOverride {
+    pub fn is_empty(&self) -> bool {
+        self.owner.is_none() && self.group.is_none() && self.mode.is_none() && self.mtime.is_none()
+    }
+
+    pub fn apply<'a, E: EntryTrait + ?Sized>(&'a self, entry: &'a E) -> OverriddenEntry<'a, E> {
+        OverriddenEntry {
+            inner: entry,
+            metadata: self,
+        }
+    }
+}
+
+/// An entry with some of its metadata replaced (see `MetadataOverride`).
+pub struct OverriddenEntry<'a, E: ?Sized> {
+    inner: &'a E,
+    metadata: &'a MetadataOverride,
+}
+
+impl<E: EntryTrait + ?Sized> EntryTrait for OverriddenEntry<'_, E> {
+    fn kind(&self) -> Result<Option<EntryKind>, CreatorError> {
+        self.inner.kind()
+    }
+    fn path(&self) -> &crate::Path {
+        self.inner.path()
+    }
+    fn uid(&self) -> u64 {
+        match &self.metadata.owner {
+            Some(owner) => owner.id,
+            None => self.inner.uid(),
+        }
+    }
+    fn gid(&self) -> u64 {
+        match &self.metadata.group {
+            Some(group) => group.id,
+            None => self.inner.gid(),
+        }
+    }
+    fn owner_name(&self) -> Option<&[u8]> {
+        match &self.metadata.owner {
+            Some(owner) => owner.name.as_deref(),
+            None => self.inner.owner_name(),
+        }
+    }
+    fn group_name(&self) -> Option<&[u8]> {
+        match &self.metadata.group {
+            Some(group) => group.name.as_deref(),
+            None => self.inner.group_name(),
+        }
+    }
+    fn mode(&self) -> u64 {
+        match &self.metadata.mode {
+            Some(mode) => {
+                let is_dir = matches!(self.inner.kind(), Ok(Some(EntryKind::Dir)));
+                mode.apply(self.inner.mode(), is_dir)
+            }
+            None => self.inner.mode(),
+        }
+    }
+    fn mtime(&self) -> u64 {
+        self.metadata.mtime.unwrap_or_else(|| self.inner.mtime())
+    }
+    fn mtime_nsec(&self) -> u32 {
+        match self.metadata.mtime {
+            Some(_) => 0,
+            None => self.inner.mtime_nsec(),
+        }
+    }
+    fn atime_ns(&self) -> u64 {
+        self.inner.atime_ns()
+    }
+    fn ctime_ns(&self) -> u64 {
+        self.inner.ctime_ns()
+    }
+    fn btime_ns(&self) -> u64 {
+        self.inner.btime_ns()
+    }
+    fn xattrs(&self) -> Option<&Xattrs> {
+        self.inner.xattrs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustest::{test, *};
+
+    #[test]
+    fn test_owner() -> Result {
+        assert_eq!(Owner::parse_user("0")?, Owner { id: 0, name: None });
+        assert_eq!(
+            Owner::parse_user("build:1234")?,
+            Owner {
+                id: 1234,
+                name: Some(b"build".to_vec())
+            }
+        );
+        assert!(Owner::parse_group("").is_err());
+        assert!(Owner::parse_group("staff:abc").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_mode() -> Result {
+        let mode = ModeSpec::parse("644")?;
+        assert_eq!(mode.apply(0o100755, false), 0o100644);
+        let mode = ModeSpec::parse("u+rwX,go-w,o=rX")?;
+        assert_eq!(mode.apply(0o100600, false), 0o100604);
+        assert_eq!(mode.apply(0o40700, true), 0o40705);
+        assert_eq!(mode.apply(0o100777, false), 0o100755);
+        let mode = ModeSpec::parse("a+x-w")?;
+        assert_eq!(mode.apply(0o644, false), 0o555);
+        assert!(ModeSpec::parse("u").is_err());
+        assert!(ModeSpec::parse("u+q").is_err());
+        assert!(ModeSpec::parse("17777").is_err());
+        Ok(())
+    }
+}
@@ -1,17 +1,42 @@
+mod arx_adder;
+mod base_archive;
+mod compression;
 mod creator;
+mod edit;
 mod entry_store_creator;
+mod exclude;
 mod fs_adder;
+mod implicit_dir;
+mod metadata_override;
+mod reproducible;
+mod reuse;
+mod transform;
 
-use crate::CreatorError;
-pub use creator::SimpleCreator;
+use crate::{CreatorError, SpecialKind, Xattrs};
+pub use arx_adder::{ArxAdder, Conflict};
+pub use base_archive::{BaseArchive, Change, ChangeDetection, ChangeReport, Whiteout};
+pub use compression::{Compress, CompressionPolicy, CompressionRules, DetectCompression};
+pub use creator::{DigestContentAdder, SimpleCreator};
+pub use edit::Edits;
 pub use entry_store_creator::EntryStoreCreator;
-pub use fs_adder::FsAdder;
+pub use exclude::{ExcludeFilter, ExcludeRules, VCS_NAMES};
+pub use fs_adder::{FsAdder, MountPoints, OnError, SkippedEntry};
+pub use implicit_dir::{ImplicitDirMetadata, ImplicitDirs};
+pub use metadata_override::{MetadataOverride, ModeSpec, OverriddenEntry, Owner};
+pub use reproducible::Reproducible;
+pub use transform::{PathTransform, PathTransforms, TransformedEntry};
 
 #[derive(Clone)]
 pub enum EntryKind {
     Dir,
     File(jbk::Size, jbk::ContentAddress),
     Link(bstr::BString),
+    /// A hard link to a file already added in the archive (given by its path in the archive).
+    HardLink(crate::PathBuf),
+    /// A special file (device, fifo or socket) with its major and minor device numbers.
+    Special(SpecialKind, u32, u32),
+    /// A whiteout, hiding the entry at the same path in the base archive (see `Whiteout`).
+    Whiteout,
 }
 
 pub trait EntryTrait {
@@ -23,8 +48,43 @@ pub trait EntryTrait {
 
     fn uid(&self) -> u64;
     fn gid(&self) -> u64;
+
+    /// The name of the owner, if known
+    fn owner_name(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// The name of the group, if known
+    fn group_name(&self) -> Option<&[u8]> {
+        None
+    }
     fn mode(&self) -> u64;
     fn mtime(&self) -> u64;
+
+    /// The sub-second part of the modification time, in nanoseconds
+    fn mtime_nsec(&self) -> u32 {
+        0
+    }
+
+    /// The last access time, in nanoseconds since epoch (0 if unknown)
+    fn atime_ns(&self) -> u64 {
+        0
+    }
+
+    /// The last status change time, in nanoseconds since epoch (0 if unknown)
+    fn ctime_ns(&self) -> u64 {
+        0
+    }
+
+    /// The creation (birth) time, in nanoseconds since epoch (0 if unknown)
+    fn btime_ns(&self) -> u64 {
+        0
+    }
+
+    /// The extended attributes of the entry
+    fn xattrs(&self) -> Option<&Xattrs> {
+        None
+    }
 }
 
 pub type Void = Result<(), CreatorError>;
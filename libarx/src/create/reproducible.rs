@@ -0,0 +1,170 @@
+use super::entry_store_creator::Digests;
+use super::{EntryKind, EntryTrait};
+use crate::{CreatorError, InputError, Xattrs};
+
+/// Metadata used to create reproducible archives.
+///
+/// When set on a `SimpleCreator`, all entries are stored with the same owner, group and
+/// modification time, and with a mode normalized to `0o755` (directories and executables)
+/// or `0o644` (other entries). Access, change and birth times and owner/group names are dropped.
+/// `FsAdder` also walks directories in a sorted order so contents are added in a stable order,
+/// and the pack uuids are derived from the stored contents instead of being random.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Reproducible {
+    pub mtime: u64,
+    pub uid: u64,
+    pub gid: u64,
+}
+
+impl Reproducible {
+    /// Use `SOURCE_DATE_EPOCH` (if set) as modification time and root (0) as owner and group.
+    ///
+    /// See <https://reproducible-builds.org/specs/source-date-epoch/>.
+    pub fn from_env() -> Result<Self, InputError> {
+        let mtime = match std::env::var("SOURCE_DATE_EPOCH") {
+            Ok(value) => value
+                .trim()
+                .parse()
+                .map_err(|_| InputError(format!("Invalid SOURCE_DATE_EPOCH value {value:?}")))?,
+            Err(std::env::VarError::NotPresent) => 0,
+            Err(std::env::VarError::NotUnicode(value)) => {
+                return Err(InputError(format!(
+                    "Invalid SOURCE_DATE_EPOCH value {value:?}"
+                )))
+            }
+        };
+        Ok(Self {
+            mtime,
+            ..Default::default()
+        })
+    }
+
+    /// A pack uuid derived from what is stored in the pack, used instead of a random one.
+    ///
+    /// The uuid of content packs depends on the stored contents (see `content_hash`).
+    /// The uuid of the other packs also depends on the stored entries and archive metadata
+    /// (see `EntryStoreCreator::entry_hash`), so archives with the same contents but
+    /// different trees get different directory packs.
+    pub(crate) fn pack_uuid(
+        &self,
+        content_hash: &[u8; 32],
+        entry_hash: &[u8; 32],
+        pack_kind: jbk::PackKind,
+    ) -> [u8; 16] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&self.mtime.to_le_bytes());
+        hasher.update(content_hash);
+        if !matches!(pack_kind, jbk::PackKind::Content) {
+            hasher.update(entry_hash);
+        }
+        hasher.update(format!("{pack_kind:?}").as_bytes());
+        let mut uuid = [0; 16];
+        uuid.copy_from_slice(&hasher.finalize().as_bytes()[..16]);
+        // Version 8 (custom) and RFC 4122 variant.
+        uuid[6] = (uuid[6] & 0x0f) | 0x80;
+        uuid[8] = (uuid[8] & 0x3f) | 0x80;
+        uuid
+    }
+
+    fn normalize_mode(mode: u64) -> u64 {
+        let permissions = if mode & 0o111 != 0 { 0o755 } else { 0o644 };
+        (mode & !0o7777) | permissions
+    }
+}
+
+/// A hash of all the contents stored in the archive, independent of the order they are added.
+pub(crate) fn content_hash(digests: &Digests) -> [u8; 32] {
+    let mut digests = digests
+        .lock()
+        .unwrap()
+        .values()
+        .copied()
+        .collect::<Vec<_>>();
+    digests.sort_unstable();
+    let mut hasher = blake3::Hasher::new();
+    for digest in &digests {
+        hasher.update(digest);
+    }
+    *hasher.finalize().as_bytes()
+}
+
+/// An entry whose metadata are normalized following a `Reproducible`.
+pub(crate) struct ReproducibleEntry<'a, E: ?Sized> {
+    pub(crate) inner: &'a E,
+    pub(crate) reproducible: &'a Reproducible,
+}
+
+impl<E: EntryTrait + ?Sized> EntryTrait for ReproducibleEntry<'_, E> {
+    fn kind(&self) -> Result<Option<EntryKind>, CreatorError> {
+        self.inner.kind()
+    }
+    fn path(&self) -> &crate::Path {
+        self.inner.path()
+    }
+    fn uid(&self) -> u64 {
+        self.reproducible.uid
+    }
+    fn gid(&self) -> u64 {
+        self.reproducible.gid
+    }
+    fn mode(&self) -> u64 {
+        Reproducible::normalize_mode(self.inner.mode())
+    }
+    fn mtime(&self) -> u64 {
+        self.reproducible.mtime
+    }
+    fn xattrs(&self) -> Option<&Xattrs> {
+        self.inner.xattrs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustest::{test, *};
+
+    #[test]
+    fn test_normalize_mode() -> Result {
+        assert_eq!(Reproducible::normalize_mode(0o100600), 0o100644);
+        assert_eq!(Reproducible::normalize_mode(0o104700), 0o100755);
+        assert_eq!(Reproducible::normalize_mode(0o40700), 0o40755);
+        assert_eq!(Reproducible::normalize_mode(0o777), 0o755);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pack_uuid() -> Result {
+        let reproducible = Reproducible::default();
+        let uuid = reproducible.pack_uuid(&[0; 32], &[0; 32], jbk::PackKind::Content);
+        assert_eq!(
+            uuid,
+            reproducible.pack_uuid(&[0; 32], &[0; 32], jbk::PackKind::Content)
+        );
+        assert_ne!(
+            uuid,
+            reproducible.pack_uuid(&[0; 32], &[0; 32], jbk::PackKind::Directory)
+        );
+        assert_ne!(
+            uuid,
+            reproducible.pack_uuid(&[1; 32], &[0; 32], jbk::PackKind::Content)
+        );
+        assert_ne!(
+            uuid,
+            Reproducible {
+                mtime: 1,
+                ..Default::default()
+            }
+            .pack_uuid(&[0; 32], &[0; 32], jbk::PackKind::Content)
+        );
+        // Only the directory pack depends on the entries.
+        assert_eq!(
+            uuid,
+            reproducible.pack_uuid(&[0; 32], &[1; 32], jbk::PackKind::Content)
+        );
+        assert_ne!(
+            reproducible.pack_uuid(&[0; 32], &[0; 32], jbk::PackKind::Directory),
+            reproducible.pack_uuid(&[0; 32], &[1; 32], jbk::PackKind::Directory)
+        );
+        Ok(())
+    }
+}
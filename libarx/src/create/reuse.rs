@@ -0,0 +1,95 @@
+//! Reference the content packs of existing archives instead of storing their contents again.
+//!
+//! A content is addressed by a pack id and a content id in this pack. To reuse the content of
+//! an existing archive, the pack containing it is declared in the manifest of the created
+//! archive (with its uuid and location) under a new pack id, and the content keeps its content id.
+//! Contents are then neither decompressed nor compressed again, but the created archive needs
+//! the existing archive to be found at its location.
+//!
+//! As the referenced packs are not part of the created archive, this is not possible when
+//! creating an archive in one file (`ConcatMode::OneFile`).
+
+use crate::{Arx, ArxError, CreatorError, FileEntry};
+use std::collections::{btree_map::Entry, BTreeMap};
+use std::path::{Path, PathBuf};
+
+/// The ids of the packs created by `jbk::creator::BasicCreator` are 0 (directory pack)
+/// and 1 (content pack), referenced packs are declared after them.
+const FIRST_REUSED_PACK_ID: u16 = 2;
+
+/// A pack of an existing archive declared in the created archive.
+struct ReusedPack {
+    pack_id: jbk::PackId,
+    uuid: [u8; 16],
+    location: String,
+}
+
+/// The packs of existing archives referenced by a `SimpleCreator`.
+#[derive(Default)]
+pub(crate) struct ReusedPacks {
+    /// The referenced packs, by uuid.
+    packs: BTreeMap<[u8; 16], ReusedPack>,
+}
+
+/// Where is a pack declared at `pack_location` in the archive at `arx_path`.
+///
+/// Packs with no location are inside the archive file itself, other locations are relative
+/// to the directory of the archive.
+fn pack_location(arx_path: &Path, pack_location: &str) -> Result<String, std::io::Error> {
+    let arx_path = std::fs::canonicalize(arx_path)?;
+    let location = if pack_location.is_empty() {
+        arx_path
+    } else {
+        match arx_path.parent() {
+            Some(dir) => dir.join(pack_location),
+            None => PathBuf::from(pack_location),
+        }
+    };
+    Ok(location.to_string_lossy().into_owned())
+}
+
+impl ReusedPacks {
+    /// The address, in the created archive, of the content of `file` read from `arx`
+    /// (opened from `arx_path`).
+    ///
+    /// Return `None` if the archive doesn't declare the pack of the content.
+    pub(crate) fn content_address(
+        &mut self,
+        arx: &Arx,
+        arx_path: &Path,
+        file: &FileEntry,
+    ) -> Result<Option<jbk::ContentAddress>, CreatorError> {
+        let content_address = file.content();
+        let Some(pack_info) = arx
+            .get_pack_info(content_address.pack_id)
+            .map_err(ArxError::from)?
+        else {
+            return Ok(None);
+        };
+        let uuid = *pack_info.uuid.as_bytes();
+        let next_pack_id = FIRST_REUSED_PACK_ID + self.packs.len() as u16;
+        let pack = match self.packs.entry(uuid) {
+            Entry::Occupied(pack) => pack.into_mut(),
+            Entry::Vacant(slot) => {
+                let location = pack_location(arx_path, &pack_info.pack_location.to_string())?;
+                log::info!("Reusing pack {} at {location}", pack_info.uuid);
+                slot.insert(ReusedPack {
+                    pack_id: jbk::PackId::from(next_pack_id),
+                    uuid,
+                    location,
+                })
+            }
+        };
+        Ok(Some(jbk::ContentAddress::new(
+            pack.pack_id,
+            content_address.content_id,
+        )))
+    }
+
+    /// Declare the referenced packs in the archive being created.
+    pub(crate) fn declare(self, basic_creator: &mut jbk::creator::BasicCreator) {
+        for pack in self.packs.into_values() {
+            basic_creator.add_external_content_pack(pack.pack_id, pack.uuid, &pack.location);
+        }
+    }
+}
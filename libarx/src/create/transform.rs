@@ -0,0 +1,276 @@
+use super::{EntryKind, EntryTrait};
+use crate::{CreatorError, InputError, Xattrs};
+use std::borrow::Cow;
+
+/// A sed-like substitution (`s/REGEX/REPLACEMENT/FLAGS`) applied to archive paths.
+///
+/// `REGEX` uses the syntax of the `regex` crate (extended regular expressions).
+/// Any character can be used as delimiter instead of `/` (and escaped with `\`).
+/// In `REPLACEMENT`, `&` is the whole match and `\1` to `\9` are the captured groups.
+/// Supported flags are `g` (replace all matches) and `i` (case insensitive).
+#[derive(Debug, Clone)]
+pub struct PathTransform {
+    regex: regex::Regex,
+    replacement: String,
+    global: bool,
+}
+
+/// Convert a sed replacement into a `regex` replacement.
+fn convert_replacement(replacement: &str) -> String {
+    let mut output = String::with_capacity(replacement.len());
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => output.push_str("${0}"),
+            '$' => output.push_str("$$"),
+            '\\' => match chars.next() {
+                Some(d @ '0'..='9') => {
+                    output.push_str("${");
+                    output.push(d);
+                    output.push('}');
+                }
+                Some('$') => output.push_str("$$"),
+                Some(c) => output.push(c),
+                None => output.push('\\'),
+            },
+            c => output.push(c),
+        }
+    }
+    output
+}
+
+impl PathTransform {
+    pub fn parse(expr: &str) -> Result<Self, InputError> {
+        let invalid = |reason: &str| InputError(format!("Invalid transform {expr:?}: {reason}"));
+        let mut chars = expr.chars();
+        if chars.next() != Some('s') {
+            return Err(invalid("expression must start with `s`"));
+        }
+        let delimiter = chars.next().ok_or_else(|| invalid("missing delimiter"))?;
+        let mut parts = vec![String::new()];
+        while let Some(c) = chars.next() {
+            let current = parts.last_mut().unwrap();
+            if c == '\\' {
+                match chars.next() {
+                    Some(c) if c == delimiter => current.push(c),
+                    Some(c) => {
+                        current.push('\\');
+                        current.push(c);
+                    }
+                    None => current.push('\\'),
+                }
+            } else if c == delimiter {
+                parts.push(String::new());
+            } else {
+                current.push(c);
+            }
+        }
+        let [pattern, replacement, flags]: [String; 3] = parts
+            .try_into()
+            .map_err(|_| invalid("expecting s/REGEX/REPLACEMENT/FLAGS"))?;
+        let mut global = false;
+        let mut case_insensitive = false;
+        for flag in flags.chars() {
+            match flag {
+                'g' => global = true,
+                'i' => case_insensitive = true,
+                _ => return Err(invalid(&format!("unknown flag `{flag}`"))),
+            }
+        }
+        let regex = regex::RegexBuilder::new(&pattern)
+            .case_insensitive(case_insensitive)
+            .build()
+            .map_err(|e| invalid(&e.to_string()))?;
+        Ok(Self {
+            regex,
+            replacement: convert_replacement(&replacement),
+            global,
+        })
+    }
+
+    pub fn apply<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        if self.global {
+            self.regex.replace_all(path, self.replacement.as_str())
+        } else {
+            self.regex.replace(path, self.replacement.as_str())
+        }
+    }
+}
+
+/// A list of `PathTransform` applied in order.
+#[derive(Debug, Clone, Default)]
+pub struct PathTransforms(Vec<PathTransform>);
+
+impl PathTransforms {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, transform: PathTransform) {
+        self.0.push(transform)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Transform `path`.
+    ///
+    /// Empty and `.` components of the result are removed.
+    /// Returns `None` if the path is transformed to an empty path (the entry must be skipped).
+    pub fn apply(&self, path: &crate::Path) -> Result<Option<crate::PathBuf>, InputError> {
+        let mut transformed = path.as_str().to_string();
+        for transform in &self.0 {
+            transformed = transform.apply(&transformed).into_owned();
+        }
+        let mut output = crate::PathBuf::new();
+        for component in transformed.split('/') {
+            match component {
+                "" | "." => {}
+                ".." => {
+                    return Err(InputError(format!(
+                        "{path} is transformed to {transformed}, which is outside of the archive"
+                    )))
+                }
+                component => output.push(component),
+            }
+        }
+        Ok((!output.as_str().is_empty()).then_some(output))
+    }
+
+    /// Wrap `entry` so it is stored under its transformed path.
+    ///
+    /// The target of hard links is transformed too.
+    pub fn apply_entry<'a, E: EntryTrait + ?Sized>(
+        &self,
+        entry: &'a E,
+    ) -> Result<Option<TransformedEntry<'a, E>>, CreatorError> {
+        let Some(path) = self.apply(entry.path())? else {
+            return Ok(None);
+        };
+        let kind = match entry.kind()? {
+            Some(EntryKind::HardLink(target)) => match self.apply(&target)? {
+                Some(target) => Some(EntryKind::HardLink(target)),
+                None => {
+                    return Err(InputError(format!(
+                        "Hard link target {target} of {} is transformed to an empty path",
+                        entry.path()
+                    ))
+                    .into())
+                }
+            },
+            kind => kind,
+        };
+        Ok(Some(TransformedEntry {
+            inner: entry,
+            path,
+            kind,
+        }))
+    }
+}
+
+impl FromIterator<PathTransform> for PathTransforms {
+    fn from_iter<I: IntoIterator<Item = PathTransform>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+/// An entry stored under a transformed path (see `PathTransforms::apply_entry`).
+pub struct TransformedEntry<'a, E: ?Sized> {
+    inner: &'a E,
+    path: crate::PathBuf,
+    kind: Option<EntryKind>,
+}
+
+impl<E: EntryTrait + ?Sized> EntryTrait for TransformedEntry<'_, E> {
+    fn kind(&self) -> Result<Option<EntryKind>, CreatorError> {
+        Ok(self.kind.clone())
+    }
+    fn path(&self) -> &crate::Path {
+        &self.path
+    }
+    fn uid(&self) -> u64 {
+        self.inner.uid()
+    }
+    fn gid(&self) -> u64 {
+        self.inner.gid()
+    }
+    fn owner_name(&self) -> Option<&[u8]> {
+        self.inner.owner_name()
+    }
+    fn group_name(&self) -> Option<&[u8]> {
+        self.inner.group_name()
+    }
+    fn mode(&self) -> u64 {
+        self.inner.mode()
+    }
+    fn mtime(&self) -> u64 {
+        self.inner.mtime()
+    }
+    fn mtime_nsec(&self) -> u32 {
+        self.inner.mtime_nsec()
+    }
+    fn atime_ns(&self) -> u64 {
+        self.inner.atime_ns()
+    }
+    fn ctime_ns(&self) -> u64 {
+        self.inner.ctime_ns()
+    }
+    fn btime_ns(&self) -> u64 {
+        self.inner.btime_ns()
+    }
+    fn xattrs(&self) -> Option<&Xattrs> {
+        self.inner.xattrs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustest::{test, *};
+
+    fn transform(exprs: &[&str], path: &str) -> std::result::Result<Option<String>, InputError> {
+        let mut transforms = PathTransforms::new();
+        for expr in exprs {
+            transforms.push(PathTransform::parse(expr)?);
+        }
+        Ok(transforms
+            .apply(crate::Path::new(path))?
+            .map(|p| p.as_str().to_string()))
+    }
+
+    #[test]
+    fn test_transform() -> Result {
+        assert_eq!(
+            transform(&["s/^src/source/"], "src/src/main.rs")?.as_deref(),
+            Some("source/src/main.rs")
+        );
+        assert_eq!(
+            transform(&["s/o/0/g"], "foo/boo")?.as_deref(),
+            Some("f00/b00")
+        );
+        assert_eq!(
+            transform(&[r"s|([a-z]*)\.TXT|\1.md|i"], "dir/Readme.txt")?.as_deref(),
+            Some("dir/Readme.md")
+        );
+        assert_eq!(
+            transform(&[r"s,(\w+)\.rs$,&.bak/\1,"], "main.rs")?.as_deref(),
+            Some("main.rs.bak/main")
+        );
+        assert_eq!(
+            transform(&["s/^prefix//", "s,^/*,new/,"], "prefix/a")?.as_deref(),
+            Some("new/a")
+        );
+        assert_eq!(transform(&["s/.*//"], "foo")?, None);
+        assert!(transform(&["s/foo/../"], "foo/bar").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid() -> Result {
+        for expr in ["x/a/b/", "s/a/b", "s/a/b/q", "s/(/b/", "s"] {
+            assert!(PathTransform::parse(expr).is_err(), "{expr}");
+        }
+        Ok(())
+    }
+}
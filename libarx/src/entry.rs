@@ -1,4 +1,8 @@
-use crate::common::{AllProperties, Builder};
+use crate::common::{
+    decode_rdev, read_mtime_nsec, read_optional_array, read_time, AllProperties, Builder,
+    SpecialKind, Xattrs,
+};
+use crate::BaseError;
 use jbk::reader::builder::PropertyBuilderTrait;
 use jbk::reader::ByteSlice;
 
@@ -9,8 +13,15 @@ pub struct CommonPart {
     parent: Option<jbk::EntryIdx>,
     owner: u32,
     group: u32,
-    rights: u8,
+    owner_name: Option<Vec<u8>>,
+    group_name: Option<Vec<u8>>,
+    rights: u16,
     mtime: u64,
+    mtime_nsec: u32,
+    atime: Option<u64>,
+    ctime: Option<u64>,
+    btime: Option<u64>,
+    xattrs: Xattrs,
 }
 
 pub trait CommonEntry {
@@ -30,12 +41,40 @@ pub trait CommonEntry {
     fn group(&self) -> u32 {
         self.common().group
     }
-    fn rights(&self) -> u8 {
+    /// The name of the owner, if known.
+    fn owner_name(&self) -> Option<&[u8]> {
+        self.common().owner_name.as_deref()
+    }
+    /// The name of the group, if known.
+    fn group_name(&self) -> Option<&[u8]> {
+        self.common().group_name.as_deref()
+    }
+    /// The permission bits of the entry, including setuid, setgid and sticky bits.
+    fn rights(&self) -> u16 {
         self.common().rights
     }
     fn mtime(&self) -> u64 {
         self.common().mtime
     }
+    /// The sub-second part of the modification time, in nanoseconds.
+    fn mtime_nsec(&self) -> u32 {
+        self.common().mtime_nsec
+    }
+    /// The last access time, in nanoseconds since epoch, if known.
+    fn atime_ns(&self) -> Option<u64> {
+        self.common().atime
+    }
+    /// The last status change time, in nanoseconds since epoch, if known.
+    fn ctime_ns(&self) -> Option<u64> {
+        self.common().ctime
+    }
+    /// The creation (birth) time, in nanoseconds since epoch, if known.
+    fn btime_ns(&self) -> Option<u64> {
+        self.common().btime
+    }
+    fn xattrs(&self) -> &Xattrs {
+        &self.common().xattrs
+    }
 }
 
 #[derive(Clone)]
@@ -43,6 +82,9 @@ pub struct FileEntry {
     common: CommonPart,
     content: jbk::ContentAddress,
     size: jbk::Size,
+    inode: jbk::EntryIdx,
+    nlink: u64,
+    digest: Option<Vec<u8>>,
 }
 
 impl CommonEntry for FileEntry {
@@ -58,6 +100,20 @@ impl FileEntry {
     pub fn size(&self) -> jbk::Size {
         self.size
     }
+    /// The index of the first entry sharing the same inode.
+    ///
+    /// This is the index of the entry itself if the file is not hard linked.
+    pub fn inode(&self) -> jbk::EntryIdx {
+        self.inode
+    }
+    /// The number of entries (hard links) sharing the same inode.
+    pub fn nlink(&self) -> u64 {
+        self.nlink
+    }
+    /// The BLAKE3 digest of the content, if stored in the archive.
+    pub fn digest(&self) -> Option<&[u8]> {
+        self.digest.as_deref()
+    }
 }
 
 #[derive(Clone)]
@@ -96,6 +152,50 @@ impl Dir {
     }
 }
 
+#[derive(Clone)]
+pub struct Special {
+    common: CommonPart,
+    kind: SpecialKind,
+    major: u32,
+    minor: u32,
+}
+
+impl CommonEntry for Special {
+    fn common(&self) -> &CommonPart {
+        &self.common
+    }
+}
+
+impl Special {
+    pub fn kind(&self) -> SpecialKind {
+        self.kind
+    }
+    /// The major device number (0 for fifos and sockets).
+    pub fn major(&self) -> u32 {
+        self.major
+    }
+    /// The minor device number (0 for fifos and sockets).
+    pub fn minor(&self) -> u32 {
+        self.minor
+    }
+}
+
+/// A marker telling the entry at the same path is deleted from the archives below
+/// (see `Layers`).
+///
+/// Whiteouts are only stored in patch archives (created by `arx diff-create`)
+/// and are never extracted.
+#[derive(Clone)]
+pub struct Whiteout {
+    common: CommonPart,
+}
+
+impl CommonEntry for Whiteout {
+    fn common(&self) -> &CommonPart {
+        &self.common
+    }
+}
+
 mod private {
     use super::*;
     pub struct CommonBuilder {
@@ -103,8 +203,15 @@ mod private {
         parent_prorperty: jbk::reader::builder::IntProperty,
         owner_property: jbk::reader::builder::IntProperty,
         group_property: jbk::reader::builder::IntProperty,
+        owner_name_property: Option<jbk::reader::builder::ArrayProperty>,
+        group_name_property: Option<jbk::reader::builder::ArrayProperty>,
         rights_property: jbk::reader::builder::IntProperty,
         mtime_property: jbk::reader::builder::IntProperty,
+        mtime_nsec_property: Option<jbk::reader::builder::IntProperty>,
+        atime_property: Option<jbk::reader::builder::IntProperty>,
+        ctime_property: Option<jbk::reader::builder::IntProperty>,
+        btime_property: Option<jbk::reader::builder::IntProperty>,
+        xattrs_property: Option<jbk::reader::builder::ArrayProperty>,
     }
 
     impl CommonBuilder {
@@ -114,8 +221,15 @@ mod private {
                 parent_prorperty: properties.parent_property.clone(),
                 owner_property: properties.owner_property.clone(),
                 group_property: properties.group_property.clone(),
+                owner_name_property: properties.owner_name_property.clone(),
+                group_name_property: properties.group_name_property.clone(),
                 rights_property: properties.rigths_property.clone(),
                 mtime_property: properties.mtime_property.clone(),
+                mtime_nsec_property: properties.mtime_nsec_property.clone(),
+                atime_property: properties.atime_property.clone(),
+                ctime_property: properties.ctime_property.clone(),
+                btime_property: properties.btime_property.clone(),
+                xattrs_property: properties.xattrs_property.clone(),
             }
         }
 
@@ -129,14 +243,25 @@ mod private {
             } else {
                 Some((parent as u32 - 1).into())
             };
+            let xattrs = match &self.xattrs_property {
+                Some(property) => Xattrs::read(property, reader)?,
+                None => Xattrs::new(),
+            };
             Ok(CommonPart {
                 idx,
                 path,
                 parent,
                 owner: self.owner_property.create(reader)? as u32,
                 group: self.group_property.create(reader)? as u32,
-                rights: self.rights_property.create(reader)? as u8,
+                owner_name: read_optional_array(&self.owner_name_property, reader)?,
+                group_name: read_optional_array(&self.group_name_property, reader)?,
+                rights: (self.rights_property.create(reader)? & 0o7777) as u16,
                 mtime: self.mtime_property.create(reader)?,
+                mtime_nsec: read_mtime_nsec(&self.mtime_nsec_property, reader)?,
+                atime: read_time(&self.atime_property, reader)?,
+                ctime: read_time(&self.ctime_property, reader)?,
+                btime: read_time(&self.btime_property, reader)?,
+                xattrs,
             })
         }
     }
@@ -145,6 +270,9 @@ mod private {
         common: CommonBuilder,
         content_address_property: jbk::reader::builder::ContentProperty,
         size_property: jbk::reader::builder::IntProperty,
+        inode_property: Option<jbk::reader::builder::IntProperty>,
+        nlink_property: Option<jbk::reader::builder::IntProperty>,
+        digest_property: Option<jbk::reader::builder::ArrayProperty>,
     }
 
     impl Builder for FileBuilder {
@@ -155,14 +283,32 @@ mod private {
                 common: CommonBuilder::new(properties),
                 content_address_property: properties.file_content_address_property,
                 size_property: properties.file_size_property.clone(),
+                inode_property: properties.file_inode_property.clone(),
+                nlink_property: properties.file_nlink_property.clone(),
+                digest_property: properties.file_digest_property.clone(),
             }
         }
 
-        fn create_entry(&self, idx: jbk::EntryIdx, reader: &ByteSlice) -> jbk::Result<Self::Entry> {
+        fn create_entry(
+            &self,
+            idx: jbk::EntryIdx,
+            reader: &ByteSlice,
+        ) -> Result<Self::Entry, BaseError> {
+            let inode = match &self.inode_property {
+                Some(property) => (property.create(reader)? as u32).into(),
+                None => idx,
+            };
+            let nlink = match &self.nlink_property {
+                Some(property) => property.create(reader)?,
+                None => 1,
+            };
             Ok(FileEntry {
                 common: self.common.create_entry(idx, reader)?,
                 content: self.content_address_property.create(reader)?,
                 size: self.size_property.create(reader)?.into(),
+                inode,
+                nlink,
+                digest: read_optional_array(&self.digest_property, reader)?,
             })
         }
     }
@@ -182,7 +328,11 @@ mod private {
             }
         }
 
-        fn create_entry(&self, idx: jbk::EntryIdx, reader: &ByteSlice) -> jbk::Result<Self::Entry> {
+        fn create_entry(
+            &self,
+            idx: jbk::EntryIdx,
+            reader: &ByteSlice,
+        ) -> Result<Self::Entry, BaseError> {
             let common = self.common.create_entry(idx, reader)?;
             let target_prop = self.link_property.create(reader)?;
             let mut target = jbk::SmallBytes::new();
@@ -208,7 +358,11 @@ mod private {
             }
         }
 
-        fn create_entry(&self, idx: jbk::EntryIdx, reader: &ByteSlice) -> jbk::Result<Self::Entry> {
+        fn create_entry(
+            &self,
+            idx: jbk::EntryIdx,
+            reader: &ByteSlice,
+        ) -> Result<Self::Entry, BaseError> {
             let common = self.common.create_entry(idx, reader)?;
             let first_child: jbk::EntryIdx =
                 (self.first_child_property.create(reader)? as u32).into();
@@ -220,12 +374,74 @@ mod private {
             })
         }
     }
+
+    pub struct SpecialBuilder {
+        common: CommonBuilder,
+        kind_property: Option<jbk::reader::builder::IntProperty>,
+        rdev_property: Option<jbk::reader::builder::IntProperty>,
+    }
+
+    impl Builder for SpecialBuilder {
+        type Entry = Special;
+
+        fn new(properties: &AllProperties) -> Self {
+            Self {
+                common: CommonBuilder::new(properties),
+                kind_property: properties.special_kind_property.clone(),
+                rdev_property: properties.special_rdev_property.clone(),
+            }
+        }
+
+        fn create_entry(
+            &self,
+            idx: jbk::EntryIdx,
+            reader: &ByteSlice,
+        ) -> Result<Self::Entry, BaseError> {
+            // Properties are always present if the layout contains a special variant.
+            let kind = self.kind_property.as_ref().unwrap().create(reader)?;
+            let kind = SpecialKind::try_from(kind)?;
+            let (major, minor) = decode_rdev(self.rdev_property.as_ref().unwrap().create(reader)?);
+            Ok(Special {
+                common: self.common.create_entry(idx, reader)?,
+                kind,
+                major,
+                minor,
+            })
+        }
+    }
+
+    pub struct WhiteoutBuilder {
+        common: CommonBuilder,
+    }
+
+    impl Builder for WhiteoutBuilder {
+        type Entry = Whiteout;
+
+        fn new(properties: &AllProperties) -> Self {
+            Self {
+                common: CommonBuilder::new(properties),
+            }
+        }
+
+        fn create_entry(
+            &self,
+            idx: jbk::EntryIdx,
+            reader: &ByteSlice,
+        ) -> Result<Self::Entry, BaseError> {
+            Ok(Whiteout {
+                common: self.common.create_entry(idx, reader)?,
+            })
+        }
+    }
 } // private mode
 
+pub(crate) use private::WhiteoutBuilder;
+
 pub type FullBuilder = (
     private::FileBuilder,
     private::LinkBuilder,
     private::DirBuilder,
+    private::SpecialBuilder,
 );
 
-pub type FullEntry = super::Entry<(FileEntry, Link, Dir)>;
+pub type FullEntry = super::Entry<(FileEntry, Link, Dir, Special)>;
@@ -81,6 +81,10 @@ pub enum ArxError {
     IsADirectory,
     #[error("Is a link")]
     IsALink,
+    #[error("Is a special file")]
+    IsASpecialFile,
+    #[error("Is a whiteout")]
+    IsAWhiteout,
 }
 
 impl From<jbk::Error> for ArxError {
@@ -188,4 +192,12 @@ pub enum CreatorError {
     IncoherentStructure(#[from] IncoherentStructure),
     #[error(transparent)]
     InputError(#[from] InputError),
+    #[error(transparent)]
+    ArxError(#[from] ArxError),
+}
+
+impl From<BaseError> for CreatorError {
+    fn from(value: BaseError) -> Self {
+        Self::ArxError(value.into())
+    }
 }
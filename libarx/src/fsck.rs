@@ -0,0 +1,322 @@
+use crate::common::{escape_bytes, AllProperties, EntryType};
+use crate::{Arx, ArxError};
+use jbk::reader::builder::PropertyBuilderTrait;
+use jbk::reader::ByteSlice;
+use std::collections::HashSet;
+
+/// A structural problem found in an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FsckProblemKind {
+    /// The entry cannot be read.
+    Unreadable(String),
+    /// The entry has an unknown type.
+    UnknownVariant,
+    /// The entry name is empty, contains a `/` or is `.` or `..`.
+    InvalidName,
+    /// Another entry of the directory has the same name.
+    DuplicateName,
+    /// The entry is not sorted after its previous sibling.
+    Unsorted,
+    /// The parent pointer of the entry is not the directory containing it.
+    WrongParent {
+        expected: Option<jbk::EntryIdx>,
+        actual: Option<jbk::EntryIdx>,
+    },
+    /// The children range of the directory points outside of the entry store.
+    InvalidChildrenRange { first_child: u64, nb_children: u64 },
+    /// The entry is reachable from several directories (or from itself).
+    AlreadyVisited,
+    /// The content address of the file doesn't point to a content.
+    InvalidContentAddress(jbk::ContentAddress),
+}
+
+impl std::fmt::Display for FsckProblemKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unreadable(e) => write!(f, "cannot read entry: {e}"),
+            Self::UnknownVariant => write!(f, "unknown entry type"),
+            Self::InvalidName => write!(f, "invalid name"),
+            Self::DuplicateName => write!(f, "duplicate name in directory"),
+            Self::Unsorted => write!(f, "entry is not sorted in its directory"),
+            Self::WrongParent { expected, actual } => {
+                let fmt_idx = |idx: &Option<jbk::EntryIdx>| match idx {
+                    None => "root".to_string(),
+                    Some(idx) => idx.into_u32().to_string(),
+                };
+                write!(
+                    f,
+                    "parent is {} but entry is in {}",
+                    fmt_idx(actual),
+                    fmt_idx(expected)
+                )
+            }
+            Self::InvalidChildrenRange {
+                first_child,
+                nb_children,
+            } => write!(
+                f,
+                "children range ({first_child}, {nb_children}) is out of the entry store"
+            ),
+            Self::AlreadyVisited => write!(f, "entry is referenced several times"),
+            Self::InvalidContentAddress(content_address) => {
+                write!(f, "invalid content address {content_address:?}")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FsckProblem {
+    pub idx: jbk::EntryIdx,
+    pub path: crate::PathBuf,
+    pub kind: FsckProblemKind,
+}
+
+impl std::fmt::Display for FsckProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "entry {} ({}): {}",
+            self.idx.into_u32(),
+            self.path,
+            self.kind
+        )
+    }
+}
+
+/// The result of `Arx::fsck`: all the structural problems found in the archive.
+#[derive(Debug, Default)]
+pub struct FsckReport {
+    pub problems: Vec<FsckProblem>,
+}
+
+impl FsckReport {
+    /// True if no problem has been found.
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// What we need to know about an entry to check the structure.
+struct RawEntry {
+    name: Vec<u8>,
+    /// The stored parent: the index of the parent plus one, 0 for the root.
+    parent: u64,
+    kind: Option<RawKind>,
+}
+
+enum RawKind {
+    File(jbk::ContentAddress),
+    Dir(u64, u64),
+    Other,
+}
+
+/// A directory to check: its children range, index and path.
+type SubDir = (jbk::EntryRange, Option<jbk::EntryIdx>, crate::PathBuf);
+
+struct Checker<'a> {
+    arx: &'a Arx,
+    properties: &'a AllProperties,
+    visited: HashSet<jbk::EntryIdx>,
+    report: FsckReport,
+}
+
+impl Checker<'_> {
+    fn read_entry(&self, reader: &ByteSlice) -> jbk::Result<RawEntry> {
+        let mut name = jbk::SmallBytes::new();
+        self.properties
+            .path_property
+            .create(reader)?
+            .resolve_to_vec(&mut name)?;
+        let parent = self.properties.parent_property.create(reader)?;
+        let kind = match self.properties.variant_id_property.create(reader)? {
+            Some(EntryType::File) => Some(RawKind::File(
+                self.properties
+                    .file_content_address_property
+                    .create(reader)?,
+            )),
+            Some(EntryType::Dir) => Some(RawKind::Dir(
+                self.properties.dir_first_child_property.create(reader)?,
+                self.properties.dir_nb_children_property.create(reader)?,
+            )),
+            Some(EntryType::Link) | Some(EntryType::Special) | Some(EntryType::Whiteout) => {
+                Some(RawKind::Other)
+            }
+            None => None,
+        };
+        Ok(RawEntry {
+            name: name.to_vec(),
+            parent,
+            kind,
+        })
+    }
+
+    fn problem(&mut self, idx: jbk::EntryIdx, path: &crate::Path, kind: FsckProblemKind) {
+        self.report.problems.push(FsckProblem {
+            idx,
+            path: path.to_owned(),
+            kind,
+        });
+    }
+
+    fn content_is_valid(&self, content_address: jbk::ContentAddress) -> jbk::Result<bool> {
+        // Content in a missing pack cannot be checked here, this is `verify`'s job.
+        Ok(self
+            .arx
+            .get_bytes(content_address)?
+            .and_then(|m| m.transpose())
+            .is_some())
+    }
+
+    /// Check all the directories from the root range.
+    ///
+    /// Directories to check are kept in an explicit stack instead of recursing, so deep
+    /// (or malicious) directory structures cannot overflow the stack.
+    fn check(&mut self, root_range: jbk::EntryRange) -> Result<(), ArxError> {
+        let mut to_check = vec![(root_range, None, crate::PathBuf::new())];
+        while let Some((range, parent, dir_path)) = to_check.pop() {
+            let sub_dirs = self.check_range(range, parent, &dir_path)?;
+            // Reversed to check directories in the order they are found.
+            to_check.extend(sub_dirs.into_iter().rev());
+        }
+        Ok(())
+    }
+
+    /// Check the entries of `range` and return the children ranges of the valid directories.
+    fn check_range(
+        &mut self,
+        range: jbk::EntryRange,
+        parent: Option<jbk::EntryIdx>,
+        dir_path: &crate::Path,
+    ) -> Result<Vec<SubDir>, ArxError> {
+        let mut sub_dirs = vec![];
+        let mut previous_name: Option<Vec<u8>> = None;
+        let begin = range.offset().into_u32();
+        let end = begin + range.count().into_u32();
+        for idx in begin..end {
+            let idx = jbk::EntryIdx::from(idx);
+            let Some(reader) = self.properties.store.get_entry_reader(idx) else {
+                self.problem(
+                    idx,
+                    dir_path,
+                    FsckProblemKind::Unreadable("no such entry".into()),
+                );
+                continue;
+            };
+            let mut entry = match self.read_entry(&reader) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.problem(idx, dir_path, FsckProblemKind::Unreadable(e.to_string()));
+                    continue;
+                }
+            };
+            let path = dir_path.join(escape_bytes(&entry.name).as_ref());
+
+            if entry.name.is_empty()
+                || entry.name.contains(&b'/')
+                || entry.name == b"."
+                || entry.name == b".."
+            {
+                self.problem(idx, &path, FsckProblemKind::InvalidName);
+            }
+            if let Some(previous_name) = &previous_name {
+                match previous_name.cmp(&entry.name) {
+                    std::cmp::Ordering::Less => {}
+                    std::cmp::Ordering::Equal => {
+                        self.problem(idx, &path, FsckProblemKind::DuplicateName)
+                    }
+                    std::cmp::Ordering::Greater => {
+                        self.problem(idx, &path, FsckProblemKind::Unsorted)
+                    }
+                }
+            }
+            previous_name = Some(std::mem::take(&mut entry.name));
+            let actual_parent = match entry.parent {
+                0 => Ok(None),
+                p => u32::try_from(p - 1).map(|p| Some(p.into())),
+            };
+            match actual_parent {
+                Ok(actual) if actual == parent => {}
+                Ok(actual) => self.problem(
+                    idx,
+                    &path,
+                    FsckProblemKind::WrongParent {
+                        expected: parent,
+                        actual,
+                    },
+                ),
+                Err(_) => self.problem(
+                    idx,
+                    &path,
+                    FsckProblemKind::Unreadable(format!("invalid parent {}", entry.parent)),
+                ),
+            }
+            if !self.visited.insert(idx) {
+                self.problem(idx, &path, FsckProblemKind::AlreadyVisited);
+                continue;
+            }
+
+            match entry.kind {
+                None => self.problem(idx, &path, FsckProblemKind::UnknownVariant),
+                Some(RawKind::File(content_address)) => {
+                    if !self.content_is_valid(content_address)? {
+                        self.problem(
+                            idx,
+                            &path,
+                            FsckProblemKind::InvalidContentAddress(content_address),
+                        );
+                    }
+                }
+                Some(RawKind::Dir(first_child, nb_children)) => {
+                    let in_store = |i: u64| {
+                        u32::try_from(i).is_ok_and(|i| {
+                            self.properties.store.get_entry_reader(i.into()).is_some()
+                        })
+                    };
+                    if nb_children != 0
+                        && !(in_store(first_child)
+                            && first_child
+                                .checked_add(nb_children - 1)
+                                .is_some_and(in_store))
+                    {
+                        self.problem(
+                            idx,
+                            &path,
+                            FsckProblemKind::InvalidChildrenRange {
+                                first_child,
+                                nb_children,
+                            },
+                        );
+                    } else {
+                        let range = jbk::EntryRange::new_from_size(
+                            (first_child as u32).into(),
+                            (nb_children as u32).into(),
+                        );
+                        sub_dirs.push((range, Some(idx), path));
+                    }
+                }
+                Some(RawKind::Other) => {}
+            }
+        }
+        Ok(sub_dirs)
+    }
+}
+
+impl Arx {
+    /// Check the structure of the archive.
+    ///
+    /// This checks that the parent pointers and the children ranges are coherent,
+    /// that entries in a directory are sorted and uniquely named and that
+    /// file content addresses are valid.
+    /// Problems are reported, not returned as errors.
+    pub fn fsck(&self) -> Result<FsckReport, ArxError> {
+        let mut checker = Checker {
+            arx: self,
+            properties: &self.properties,
+            visited: HashSet::new(),
+            report: FsckReport::default(),
+        };
+        checker.check(jbk::EntryRange::from_range(&self.root_index))?;
+        Ok(checker.report)
+    }
+}
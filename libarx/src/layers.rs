@@ -0,0 +1,207 @@
+use crate::common::{escape_bytes, Entry, FullBuilderTrait};
+use crate::error::{ArxError, PathNotFound, QueryError};
+use crate::walk::Operator;
+use crate::{
+    Arx, CommonEntry, Dir, FileEntry, FileFilter, FullBuilder, Link, Special, Walker, Whiteout,
+};
+use std::cell::RefCell;
+use std::collections::{hash_map, HashMap};
+
+/// Which layer(s) provide a path of the merged tree.
+#[derive(Debug, Clone, Copy)]
+enum Provider {
+    /// A directory, merged from all the layers between `bottom` and `top` containing it.
+    ///
+    /// The directory is `sealed` when a layer below `bottom` has a file or a whiteout at its
+    /// path: the directories of the lower layers are then hidden, with their children.
+    Dir {
+        bottom: usize,
+        top: usize,
+        sealed: bool,
+    },
+    /// A file, link or special file of the given layer.
+    Entry(usize),
+    /// The path is deleted.
+    Whiteout,
+}
+
+/// A stack of archives seen as one tree.
+///
+/// The first archive is the bottom layer, each following one is applied on top of the
+/// previous ones (as patches created by `arx diff-create`):
+/// - An entry hides the entries at the same path in the layers below, except for
+///   directories which are merged if they are directories in all layers.
+/// - A whiteout entry (`Whiteout`) hides the entry at the same path
+///   (and all its children) in the layers below.
+pub struct Layers {
+    layers: Vec<Arx>,
+    visible: HashMap<crate::PathBuf, Provider>,
+}
+
+impl Layers {
+    pub fn new(layers: Vec<Arx>) -> Result<Self, ArxError> {
+        let mut visible = HashMap::new();
+        for (layer, arx) in layers.iter().enumerate().rev() {
+            let collector = VisibleCollector {
+                layer,
+                visible: RefCell::new(&mut visible),
+            };
+            Walker::new(arx, Default::default()).run(&collector)?;
+        }
+        Ok(Self { layers, visible })
+    }
+
+    /// The archives, bottom layer first.
+    pub fn layers(&self) -> &[Arx] {
+        &self.layers
+    }
+
+    /// Get the visible entry at `path`.
+    ///
+    /// For a merged directory, this is the directory of the top most layer containing it,
+    /// so its range only covers the children stored in that layer.
+    pub fn get_entry<B>(&self, path: &crate::Path) -> Result<Entry<B::Entry>, QueryError>
+    where
+        B: FullBuilderTrait,
+    {
+        match self.visible.get(path) {
+            Some(Provider::Dir { top: layer, .. }) | Some(Provider::Entry(layer)) => {
+                self.layers[*layer].get_entry::<B>(path)
+            }
+            Some(Provider::Whiteout) | None => Err(PathNotFound(path.into()).into()),
+        }
+    }
+
+    /// A filter accepting the entries of `layer` which are visible in the merged tree.
+    pub fn filter(&self, layer: usize) -> LayerFilter<'_> {
+        LayerFilter {
+            visible: &self.visible,
+            layer,
+        }
+    }
+}
+
+pub struct LayerFilter<'a> {
+    visible: &'a HashMap<crate::PathBuf, Provider>,
+    layer: usize,
+}
+
+impl FileFilter for LayerFilter<'_> {
+    fn accept(&self, path: &crate::Path) -> bool {
+        match self.visible.get(path) {
+            Some(Provider::Dir { bottom, top, .. }) => (*bottom..=*top).contains(&self.layer),
+            Some(Provider::Entry(layer)) => *layer == self.layer,
+            Some(Provider::Whiteout) | None => false,
+        }
+    }
+}
+
+/// Record the entries of a layer not hidden by the layers above.
+///
+/// Layers must be walked from the top one.
+struct VisibleCollector<'a> {
+    layer: usize,
+    visible: RefCell<&'a mut HashMap<crate::PathBuf, Provider>>,
+}
+
+impl VisibleCollector<'_> {
+    /// Add a file, link, special file or whiteout.
+    fn add(&self, path: crate::PathBuf, provider: Provider) {
+        match self.visible.borrow_mut().entry(path) {
+            hash_map::Entry::Vacant(slot) => {
+                slot.insert(provider);
+            }
+            // Hidden by an upper directory, which hides the lower layers too.
+            hash_map::Entry::Occupied(mut slot) => {
+                if let Provider::Dir { sealed, .. } = slot.get_mut() {
+                    *sealed = true;
+                }
+            }
+        }
+    }
+}
+
+impl Operator<crate::PathBuf, FullBuilder> for VisibleCollector<'_> {
+    type Error = ArxError;
+    fn on_start(&self, _current_path: &mut crate::PathBuf) -> Result<(), ArxError> {
+        Ok(())
+    }
+    fn on_stop(&self, _current_path: &mut crate::PathBuf) -> Result<(), ArxError> {
+        Ok(())
+    }
+    fn on_directory_enter(
+        &self,
+        current_path: &mut crate::PathBuf,
+        dir: &Dir,
+    ) -> Result<bool, ArxError> {
+        current_path.push(escape_bytes(dir.path()).as_ref());
+        let mut visible = self.visible.borrow_mut();
+        match visible.get_mut(current_path.as_relative_path()) {
+            None => {
+                visible.insert(
+                    current_path.clone(),
+                    Provider::Dir {
+                        bottom: self.layer,
+                        top: self.layer,
+                        sealed: false,
+                    },
+                );
+                Ok(true)
+            }
+            Some(Provider::Dir {
+                bottom,
+                sealed: false,
+                ..
+            }) => {
+                *bottom = self.layer;
+                Ok(true)
+            }
+            // Hidden by a file, a whiteout or a directory sealed by them, so are its children.
+            Some(_) => Ok(false),
+        }
+    }
+    fn on_directory_exit(
+        &self,
+        current_path: &mut crate::PathBuf,
+        _dir: &Dir,
+    ) -> Result<(), ArxError> {
+        current_path.pop();
+        Ok(())
+    }
+    fn on_file(&self, current_path: &mut crate::PathBuf, file: &FileEntry) -> Result<(), ArxError> {
+        self.add(
+            current_path.join(escape_bytes(file.path()).as_ref()),
+            Provider::Entry(self.layer),
+        );
+        Ok(())
+    }
+    fn on_link(&self, current_path: &mut crate::PathBuf, link: &Link) -> Result<(), ArxError> {
+        self.add(
+            current_path.join(escape_bytes(link.path()).as_ref()),
+            Provider::Entry(self.layer),
+        );
+        Ok(())
+    }
+    fn on_special(
+        &self,
+        current_path: &mut crate::PathBuf,
+        special: &Special,
+    ) -> Result<(), ArxError> {
+        self.add(
+            current_path.join(escape_bytes(special.path()).as_ref()),
+            Provider::Entry(self.layer),
+        );
+        Ok(())
+    }
+    fn on_whiteout(
+        &self,
+        current_path: &mut crate::PathBuf,
+        whiteout: &Whiteout,
+    ) -> Result<(), ArxError> {
+        self.add(
+            current_path.join(escape_bytes(whiteout.path()).as_ref()),
+            Provider::Whiteout,
+        );
+        Ok(())
+    }
+}
@@ -9,19 +9,26 @@ mod common;
 pub mod create;
 mod entry;
 mod error;
+mod fsck;
+mod layers;
 mod tools;
+mod verify;
 pub mod walk;
 
 pub use arx::Arx;
 #[cfg(all(not(windows), feature = "fuse"))]
 pub use arx_fs::{ArxFs, Stats};
 pub use common::{
+    bytes_to_os_path, escape_bytes, path_from_bytes, path_to_bytes, to_os_path, unescape_str,
     AllProperties, Builder, Entry, FromPathError, FromPathErrorKind, FullBuilderTrait, Path,
-    PathBuf, VENDOR_ID,
+    PathBuf, SpecialKind, Xattrs, VENDOR_ID,
 };
 pub use entry::*;
 pub use error::*;
+pub use fsck::{FsckProblem, FsckProblemKind, FsckReport};
+pub use layers::{LayerFilter, Layers};
 pub use tools::{extract_all, ExtractBuilder, FileFilter, Overwrite};
+pub use verify::{VerifyReport, VerifyStatus};
 pub use walk::*;
 
 #[cfg(test)]
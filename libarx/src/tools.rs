@@ -1,17 +1,23 @@
-use core::convert::TryInto;
 use core::ops::{Deref, DerefMut};
-use std::collections::HashSet;
-use std::fs::{create_dir_all, OpenOptions};
+use std::collections::{HashMap, HashSet};
+use std::fs::{create_dir_all, hard_link, OpenOptions};
 use std::io::{ErrorKind, Write};
 #[cfg(unix)]
-use std::os::unix::fs::symlink;
+use std::os::unix::fs::{symlink, OpenOptionsExt};
 #[cfg(windows)]
 use std::os::windows::fs::symlink_file as symlink;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime};
 
+use crate::common::{
+    bytes_to_os_path, decode_rdev, escape_bytes, read_mtime_nsec, read_optional_array, read_time,
+    to_os_path, LocalNames,
+};
 use crate::error::ExtractError;
-use crate::{AllProperties, Arx, ArxFormatError, Builder, Entry, Walker};
+use crate::{
+    AllProperties, Arx, ArxFormatError, BaseError, Builder, Entry, Layers, SpecialKind, Walker,
+    Xattrs,
+};
 use jbk::reader::builder::PropertyBuilderTrait;
 use jbk::reader::ByteSlice;
 use jbk::reader::MayMissPack;
@@ -61,22 +67,96 @@ impl OpenLimited for std::fs::OpenOptions {
     }
 }
 
+/// The metadata of an entry which may be restored on extraction.
+#[derive(Clone)]
+struct Metadata {
+    owner: u32,
+    group: u32,
+    owner_name: Option<Vec<u8>>,
+    group_name: Option<Vec<u8>>,
+    mode: u64,
+    mtime: u64,
+    mtime_nsec: u32,
+    atime: Option<u64>,
+    xattrs: Xattrs,
+}
+
+impl Metadata {
+    fn modified(&self) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::new(self.mtime, self.mtime_nsec)
+    }
+}
+
+struct MetadataBuilder {
+    owner_property: jbk::reader::builder::IntProperty,
+    group_property: jbk::reader::builder::IntProperty,
+    owner_name_property: Option<jbk::reader::builder::ArrayProperty>,
+    group_name_property: Option<jbk::reader::builder::ArrayProperty>,
+    rights_property: jbk::reader::builder::IntProperty,
+    mtime_property: jbk::reader::builder::IntProperty,
+    mtime_nsec_property: Option<jbk::reader::builder::IntProperty>,
+    atime_property: Option<jbk::reader::builder::IntProperty>,
+    xattrs_property: Option<jbk::reader::builder::ArrayProperty>,
+}
+
+impl MetadataBuilder {
+    fn new(properties: &AllProperties) -> Self {
+        Self {
+            owner_property: properties.owner_property.clone(),
+            group_property: properties.group_property.clone(),
+            owner_name_property: properties.owner_name_property.clone(),
+            group_name_property: properties.group_name_property.clone(),
+            rights_property: properties.rigths_property.clone(),
+            mtime_property: properties.mtime_property.clone(),
+            mtime_nsec_property: properties.mtime_nsec_property.clone(),
+            atime_property: properties.atime_property.clone(),
+            xattrs_property: properties.xattrs_property.clone(),
+        }
+    }
+
+    fn create(&self, reader: &ByteSlice) -> jbk::Result<Metadata> {
+        Ok(Metadata {
+            owner: self.owner_property.create(reader)? as u32,
+            group: self.group_property.create(reader)? as u32,
+            owner_name: read_optional_array(&self.owner_name_property, reader)?,
+            group_name: read_optional_array(&self.group_name_property, reader)?,
+            mode: self.rights_property.create(reader)?,
+            mtime: self.mtime_property.create(reader)?,
+            mtime_nsec: read_mtime_nsec(&self.mtime_nsec_property, reader)?,
+            atime: read_time(&self.atime_property, reader)?,
+            xattrs: match &self.xattrs_property {
+                Some(property) => Xattrs::read(property, reader)?,
+                None => Xattrs::new(),
+            },
+        })
+    }
+}
+
 struct FileEntry {
-    path: jbk::SmallString,
+    path: String,
     content: jbk::ContentAddress,
-    mtime: u64,
+    metadata: Metadata,
+    inode: jbk::EntryIdx,
+    nlink: u64,
 }
 
 struct Link {
-    path: jbk::SmallString,
-    target: jbk::SmallString,
-    mtime: u64,
+    path: String,
+    target: Vec<u8>,
+    metadata: Metadata,
+}
+
+struct Dir {
+    path: String,
+    metadata: Metadata,
 }
 
 struct FileBuilder {
     path_property: jbk::reader::builder::ArrayProperty,
     content_address_property: jbk::reader::builder::ContentProperty,
-    mtime_property: jbk::reader::builder::IntProperty,
+    metadata_builder: MetadataBuilder,
+    inode_property: Option<jbk::reader::builder::IntProperty>,
+    nlink_property: Option<jbk::reader::builder::IntProperty>,
 }
 
 impl Builder for FileBuilder {
@@ -86,20 +166,35 @@ impl Builder for FileBuilder {
         Self {
             path_property: properties.path_property.clone(),
             content_address_property: properties.file_content_address_property,
-            mtime_property: properties.mtime_property.clone(),
+            metadata_builder: MetadataBuilder::new(properties),
+            inode_property: properties.file_inode_property.clone(),
+            nlink_property: properties.file_nlink_property.clone(),
         }
     }
 
-    fn create_entry(&self, _idx: jbk::EntryIdx, reader: &ByteSlice) -> jbk::Result<Self::Entry> {
+    fn create_entry(
+        &self,
+        idx: jbk::EntryIdx,
+        reader: &ByteSlice,
+    ) -> Result<Self::Entry, BaseError> {
         let path_prop = self.path_property.create(reader)?;
         let mut path = jbk::SmallBytes::new();
         path_prop.resolve_to_vec(&mut path)?;
         let content = self.content_address_property.create(reader)?;
-        let mtime = self.mtime_property.create(reader)?;
+        let inode = match &self.inode_property {
+            Some(property) => (property.create(reader)? as u32).into(),
+            None => idx,
+        };
+        let nlink = match &self.nlink_property {
+            Some(property) => property.create(reader)?,
+            None => 1,
+        };
         Ok(FileEntry {
-            path: path.try_into()?,
+            path: escape_bytes(&path).into_owned(),
             content,
-            mtime,
+            metadata: self.metadata_builder.create(reader)?,
+            inode,
+            nlink,
         })
     }
 }
@@ -107,7 +202,7 @@ impl Builder for FileBuilder {
 struct LinkBuilder {
     path_property: jbk::reader::builder::ArrayProperty,
     link_property: jbk::reader::builder::ArrayProperty,
-    mtime_property: jbk::reader::builder::IntProperty,
+    metadata_builder: MetadataBuilder,
 }
 
 impl Builder for LinkBuilder {
@@ -117,11 +212,15 @@ impl Builder for LinkBuilder {
         Self {
             path_property: properties.path_property.clone(),
             link_property: properties.link_target_property.clone(),
-            mtime_property: properties.mtime_property.clone(),
+            metadata_builder: MetadataBuilder::new(properties),
         }
     }
 
-    fn create_entry(&self, _idx: jbk::EntryIdx, reader: &ByteSlice) -> jbk::Result<Self::Entry> {
+    fn create_entry(
+        &self,
+        _idx: jbk::EntryIdx,
+        reader: &ByteSlice,
+    ) -> Result<Self::Entry, BaseError> {
         let path_prop = self.path_property.create(reader)?;
         let mut path = jbk::SmallBytes::new();
         path_prop.resolve_to_vec(&mut path)?;
@@ -129,37 +228,94 @@ impl Builder for LinkBuilder {
         let target_prop = self.link_property.create(reader)?;
         let mut target = jbk::SmallBytes::new();
         target_prop.resolve_to_vec(&mut target)?;
-        let mtime = self.mtime_property.create(reader)?;
         Ok(Link {
-            path: path.try_into()?,
-            target: target.try_into()?,
-            mtime,
+            path: escape_bytes(&path).into_owned(),
+            target: target.to_vec(),
+            metadata: self.metadata_builder.create(reader)?,
         })
     }
 }
 
 struct DirBuilder {
     path_property: jbk::reader::builder::ArrayProperty,
+    metadata_builder: MetadataBuilder,
 }
 
 impl Builder for DirBuilder {
-    type Entry = jbk::SmallString;
+    type Entry = Dir;
+
+    fn new(properties: &AllProperties) -> Self {
+        Self {
+            path_property: properties.path_property.clone(),
+            metadata_builder: MetadataBuilder::new(properties),
+        }
+    }
+
+    fn create_entry(
+        &self,
+        _idx: jbk::EntryIdx,
+        reader: &ByteSlice,
+    ) -> Result<Self::Entry, BaseError> {
+        let path_prop = self.path_property.create(reader)?;
+        let mut path = jbk::SmallBytes::new();
+        path_prop.resolve_to_vec(&mut path)?;
+        Ok(Dir {
+            path: escape_bytes(&path).into_owned(),
+            metadata: self.metadata_builder.create(reader)?,
+        })
+    }
+}
+
+struct Special {
+    path: String,
+    kind: SpecialKind,
+    major: u32,
+    minor: u32,
+    metadata: Metadata,
+}
+
+struct SpecialBuilder {
+    path_property: jbk::reader::builder::ArrayProperty,
+    kind_property: Option<jbk::reader::builder::IntProperty>,
+    rdev_property: Option<jbk::reader::builder::IntProperty>,
+    metadata_builder: MetadataBuilder,
+}
+
+impl Builder for SpecialBuilder {
+    type Entry = Special;
 
     fn new(properties: &AllProperties) -> Self {
         Self {
             path_property: properties.path_property.clone(),
+            kind_property: properties.special_kind_property.clone(),
+            rdev_property: properties.special_rdev_property.clone(),
+            metadata_builder: MetadataBuilder::new(properties),
         }
     }
 
-    fn create_entry(&self, _idx: jbk::EntryIdx, reader: &ByteSlice) -> jbk::Result<Self::Entry> {
+    fn create_entry(
+        &self,
+        _idx: jbk::EntryIdx,
+        reader: &ByteSlice,
+    ) -> Result<Self::Entry, BaseError> {
         let path_prop = self.path_property.create(reader)?;
         let mut path = jbk::SmallBytes::new();
         path_prop.resolve_to_vec(&mut path)?;
-        Ok(path.try_into()?)
+        // Properties are always present if the layout contains a special variant.
+        let kind = self.kind_property.as_ref().unwrap().create(reader)?;
+        let kind = SpecialKind::try_from(kind)?;
+        let (major, minor) = decode_rdev(self.rdev_property.as_ref().unwrap().create(reader)?);
+        Ok(Special {
+            path: escape_bytes(&path).into_owned(),
+            kind,
+            major,
+            minor,
+            metadata: self.metadata_builder.create(reader)?,
+        })
     }
 }
 
-type FullBuilder = (FileBuilder, LinkBuilder, DirBuilder);
+type FullBuilder = (FileBuilder, LinkBuilder, DirBuilder, SpecialBuilder);
 
 pub trait FileFilter: Send {
     ///  Should we accept (to extract) path
@@ -205,6 +361,146 @@ pub enum Overwrite {
     Error,
 }
 
+/// Set the extended attributes of an extracted entry.
+///
+/// Failing to set an attribute (not supported by the filesystem, missing privileges for
+/// `security` or `trusted` namespaces, ...) doesn't stop the extraction, it is only reported.
+#[cfg(unix)]
+fn set_xattrs(path: &Path, xattrs: &Xattrs) {
+    use std::os::unix::ffi::OsStrExt;
+    for (name, value) in xattrs.iter() {
+        let name = std::ffi::OsStr::from_bytes(name);
+        if let Err(e) = xattr::set(path, name, value) {
+            log::warn!("Cannot set xattr {name:?} on {}: {e}", path.display());
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn set_xattrs(_path: &Path, _xattrs: &Xattrs) {}
+
+/// Create the special file `path`.
+#[cfg(unix)]
+fn make_special(path: &Path, special: &Special) -> std::io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    let mode = (special.metadata.mode & 0o7777) as libc::mode_t;
+    let mknod = |file_type| {
+        let dev = libc::makedev(special.major as _, special.minor as _);
+        unsafe { libc::mknod(c_path.as_ptr(), mode | file_type, dev) }
+    };
+    let ret = match special.kind {
+        SpecialKind::Fifo => unsafe { libc::mkfifo(c_path.as_ptr(), mode) },
+        SpecialKind::CharDevice => mknod(libc::S_IFCHR),
+        SpecialKind::BlockDevice => mknod(libc::S_IFBLK),
+        SpecialKind::Socket => mknod(libc::S_IFSOCK),
+    };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+fn make_special(_path: &Path, _special: &Special) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        ErrorKind::Unsupported,
+        "Special files are not supported on this platform",
+    ))
+}
+
+/// Which stored metadata are restored on the extracted entries.
+#[derive(Debug, Default, Clone, Copy)]
+struct Restore {
+    xattrs: bool,
+    permissions: bool,
+    owner: bool,
+    numeric_owner: bool,
+    mtime: bool,
+}
+
+impl Restore {
+    /// Restore ownership, permissions and times of the extracted entry `path`.
+    ///
+    /// `owner` is the local (uid, gid) of the entry.
+    /// `is_link` must be true if `path` is a symlink, so the link itself is modified instead
+    /// of its target. Permissions of symlinks are never changed.
+    /// Not being allowed to change the ownership is only reported.
+    #[cfg(unix)]
+    fn apply(
+        &self,
+        path: &Path,
+        is_link: bool,
+        (uid, gid): (u32, u32),
+        metadata: &Metadata,
+    ) -> std::io::Result<()> {
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::fs::PermissionsExt;
+        // Ownership must be changed first as chown may clear setuid and setgid bits.
+        if self.owner {
+            let ret = if is_link {
+                std::os::unix::fs::lchown(path, Some(uid), Some(gid))
+            } else {
+                std::os::unix::fs::chown(path, Some(uid), Some(gid))
+            };
+            if let Err(e) = ret {
+                if e.kind() != ErrorKind::PermissionDenied {
+                    return Err(e);
+                }
+                log::warn!("Cannot change owner of {}: {e}", path.display());
+            }
+        }
+        if self.permissions && !is_link {
+            let permissions = std::fs::Permissions::from_mode((metadata.mode & 0o7777) as u32);
+            std::fs::set_permissions(path, permissions)?;
+        }
+        if self.mtime {
+            let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+            // Access time is left untouched if unknown.
+            let atime = match metadata.atime {
+                Some(atime) => libc::timespec {
+                    tv_sec: (atime / 1_000_000_000) as libc::time_t,
+                    tv_nsec: (atime % 1_000_000_000) as _,
+                },
+                None => libc::timespec {
+                    tv_sec: 0,
+                    tv_nsec: libc::UTIME_OMIT,
+                },
+            };
+            let times = [
+                atime,
+                libc::timespec {
+                    tv_sec: metadata.mtime as libc::time_t,
+                    tv_nsec: metadata.mtime_nsec as _,
+                },
+            ];
+            let flags = if is_link {
+                libc::AT_SYMLINK_NOFOLLOW
+            } else {
+                0
+            };
+            let ret =
+                unsafe { libc::utimensat(libc::AT_FDCWD, c_path.as_ptr(), times.as_ptr(), flags) };
+            if ret != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn apply(
+        &self,
+        _path: &Path,
+        _is_link: bool,
+        _owner: (u32, u32),
+        _metadata: &Metadata,
+    ) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 pub struct Extractor<'a, 'scope, F>
 where
     'a: 'scope,
@@ -218,6 +514,10 @@ where
     base_dir: PathBuf,
     print_progress: bool,
     overwrite: Overwrite,
+    restore: Restore,
+    // Path of the first extracted file of each hard linked inode.
+    hard_links: Mutex<HashMap<jbk::EntryIdx, PathBuf>>,
+    names: LocalNames,
 }
 
 impl<'a, 'scope, F> Extractor<'a, 'scope, F>
@@ -233,8 +533,29 @@ where
         Ok(())
     }
 
+    /// The local (uid, gid) to give to an extracted entry.
+    ///
+    /// Stored owner and group names are mapped to the local ids unless numeric owner is asked.
+    /// Stored ids are used if the names are unknown.
+    fn local_owner(&self, metadata: &Metadata) -> (u32, u32) {
+        if !self.restore.owner || self.restore.numeric_owner {
+            return (metadata.owner, metadata.group);
+        }
+        let uid = metadata
+            .owner_name
+            .as_ref()
+            .and_then(|name| self.names.uid(name))
+            .unwrap_or(metadata.owner);
+        let gid = metadata
+            .group_name
+            .as_ref()
+            .and_then(|name| self.names.gid(name))
+            .unwrap_or(metadata.group);
+        (uid, gid)
+    }
+
     fn abs_path(&self, current_file: &crate::Path) -> PathBuf {
-        current_file.to_path(&self.base_dir)
+        to_os_path(current_file, &self.base_dir)
     }
 
     pub fn extract(&self, path: &crate::Path, recursive: bool) -> Result<(), ExtractError> {
@@ -244,14 +565,16 @@ where
         match &entry {
             Entry::File(e) => self.write_file(e, path),
             Entry::Link(e) => self.write_link(e, path),
-            Entry::Dir(range, _e) => {
-                self.write_dir(path)?;
+            Entry::Special(e) => self.write_special(e, path),
+            // Whiteouts are applied by the layered extraction, there is nothing to write.
+            Entry::Whiteout(_) => Ok(()),
+            Entry::Dir(range, e) => {
+                self.write_dir(e, path)?;
                 if recursive {
                     let mut walker = Walker::new(self.arx, path.to_relative_path_buf());
-                    walker.run_from_range(self, range)
-                } else {
-                    Ok(())
+                    walker.run_from_range(self, range)?;
                 }
+                self.restore_dir(e, path)
             }
         }
     }
@@ -261,10 +584,61 @@ where
         walker.run_from_range(self, &self.root)
     }
 
+    /// Create `path` as a hard link to the already extracted file sharing the same inode.
+    ///
+    /// Return false if no file sharing the inode has been extracted yet.
+    fn write_hard_link(&self, entry: &FileEntry, path: &Path) -> Result<bool, ExtractError> {
+        let hard_links = self.hard_links.lock().unwrap();
+        let original = match hard_links.get(&entry.inode) {
+            Some(original) => original,
+            None => return Ok(false),
+        };
+        if let Err(e) = hard_link(original, path) {
+            match e.kind() {
+                ErrorKind::AlreadyExists => match self.overwrite {
+                    Overwrite::Skip => return Ok(true),
+                    Overwrite::Warn => {
+                        eprintln!("File {} already exists.", path.display());
+                        return Ok(true);
+                    }
+                    Overwrite::Newer => {
+                        let existing_metadata = std::fs::symlink_metadata(path)?;
+                        let existing_time = existing_metadata.modified()?;
+                        let new_time = entry.metadata.modified();
+                        if new_time >= existing_time {
+                            std::fs::remove_file(path)?;
+                            hard_link(original, path)?;
+                        } else {
+                            return Ok(true);
+                        }
+                    }
+                    Overwrite::Overwrite => {
+                        std::fs::remove_file(path)?;
+                        hard_link(original, path)?;
+                    }
+                    Overwrite::Error => {
+                        return Err(ExtractError::FileExists {
+                            path: path.to_path_buf(),
+                        })
+                    }
+                },
+                _ => return Err(e.into()),
+            }
+        }
+        if self.print_progress {
+            println!("{}", path.display());
+        }
+        Ok(true)
+    }
+
     fn write_file(&self, entry: &FileEntry, path: &crate::Path) -> Result<(), ExtractError> {
         self.create_parents(path)?;
         let path = self.abs_path(path);
 
+        if entry.nlink > 1 && self.write_hard_link(entry, &path)? {
+            return Ok(());
+        }
+
         let entry_content = entry.content;
         let print_progress = self.print_progress;
         let arx = self.arx;
@@ -276,14 +650,18 @@ where
                 "Entry Content should point to valid content",
             ))?;
         let error = Arc::clone(&self.err);
+        let restore = self.restore;
+        let metadata = entry.metadata.clone();
+        let owner = self.local_owner(&metadata);
 
         match bytes {
             MayMissPack::FOUND(bytes) => {
-                let mut file = match OpenOptions::new()
-                    .write(true)
-                    .create_new(true)
-                    .open_limited(&path)
-                {
+                let mut options = OpenOptions::new();
+                options.write(true).create_new(true);
+                // Setuid, setgid and sticky bits are not applied.
+                #[cfg(unix)]
+                options.mode((entry.metadata.mode & 0o777) as u32);
+                let mut file = match options.open_limited(&path) {
                     Ok(f) => f,
                     Err(e) => match e.kind() {
                         ErrorKind::AlreadyExists => match self.overwrite {
@@ -295,8 +673,7 @@ where
                             Overwrite::Newer => {
                                 let existing_metadata = std::fs::metadata(&path)?;
                                 let existing_time = existing_metadata.modified()?;
-                                let new_time =
-                                    SystemTime::UNIX_EPOCH + Duration::from_secs(entry.mtime);
+                                let new_time = entry.metadata.modified();
                                 if new_time >= existing_time {
                                     OpenOptions::new()
                                         .write(true)
@@ -315,7 +692,17 @@ where
                         _ => return Err(e.into()),
                     },
                 };
+                if self.restore.xattrs {
+                    set_xattrs(&path, &entry.metadata.xattrs);
+                }
+                if entry.nlink > 1 {
+                    self.hard_links
+                        .lock()
+                        .unwrap()
+                        .insert(entry.inode, path.clone());
+                }
 
+                let file_path = path.clone();
                 self.scope.spawn(move |_scope| {
                     // Don't use std::io::copy as it use an internal buffer where it read data into before writing in file.
                     // If content is compressed, we already have a buffer. Same thing for uncompress as the cluster is probably mmapped.
@@ -330,6 +717,9 @@ where
                                 break;
                             }
                         }
+                        // Metadata must be restored once the content is written
+                        // (writing would change the mtime).
+                        restore.apply(&file_path, false, owner, &metadata)?;
                         Ok(())
                     };
                     if let Err(e) = write_function() {
@@ -357,10 +747,7 @@ where
     fn write_link(&self, link: &Link, path: &crate::Path) -> Result<(), ExtractError> {
         self.create_parents(path)?;
         let abs_path = self.abs_path(path);
-        if let Err(e) = symlink(
-            PathBuf::from(link.target.as_str()),
-            PathBuf::from(&abs_path),
-        ) {
+        if let Err(e) = symlink(bytes_to_os_path(&link.target), PathBuf::from(&abs_path)) {
             match e.kind() {
                 ErrorKind::AlreadyExists => match self.overwrite {
                     Overwrite::Skip => return Ok(()),
@@ -371,45 +758,117 @@ where
                     Overwrite::Newer => {
                         let existing_metadata = std::fs::symlink_metadata(&abs_path)?;
                         let existing_time = existing_metadata.modified()?;
-                        let new_time = SystemTime::UNIX_EPOCH + Duration::from_secs(link.mtime);
+                        let new_time = link.metadata.modified();
                         if new_time >= existing_time {
                             std::fs::remove_file(&abs_path)?;
-                            symlink(
-                                PathBuf::from(link.target.as_str()),
-                                PathBuf::from(&abs_path),
-                            )?;
+                            symlink(bytes_to_os_path(&link.target), PathBuf::from(&abs_path))?;
                         } else {
                             return Ok(());
                         }
                     }
                     Overwrite::Overwrite => {
                         std::fs::remove_file(&abs_path)?;
-                        symlink(
-                            PathBuf::from(link.target.as_str()),
-                            PathBuf::from(&abs_path),
-                        )?;
+                        symlink(bytes_to_os_path(&link.target), PathBuf::from(&abs_path))?;
                     }
                     Overwrite::Error => return Err(e.into()),
                 },
                 _ => return Err(e.into()),
             }
         }
+        if self.restore.xattrs {
+            set_xattrs(&abs_path, &link.metadata.xattrs);
+        }
+        self.restore.apply(
+            &abs_path,
+            true,
+            self.local_owner(&link.metadata),
+            &link.metadata,
+        )?;
         if self.print_progress {
             println!("{}", abs_path.display());
         }
         Ok(())
     }
 
-    fn write_dir(&self, path: &crate::Path) -> Result<(), ExtractError> {
+    fn write_special(&self, special: &Special, path: &crate::Path) -> Result<(), ExtractError> {
+        self.create_parents(path)?;
+        let abs_path = self.abs_path(path);
+        if let Err(e) = make_special(&abs_path, special) {
+            match e.kind() {
+                ErrorKind::AlreadyExists => match self.overwrite {
+                    Overwrite::Skip => return Ok(()),
+                    Overwrite::Warn => {
+                        eprintln!("File {} already exists.", abs_path.display());
+                        return Ok(());
+                    }
+                    Overwrite::Newer => {
+                        let existing_metadata = std::fs::symlink_metadata(&abs_path)?;
+                        let existing_time = existing_metadata.modified()?;
+                        let new_time = special.metadata.modified();
+                        if new_time >= existing_time {
+                            std::fs::remove_file(&abs_path)?;
+                            make_special(&abs_path, special)?;
+                        } else {
+                            return Ok(());
+                        }
+                    }
+                    Overwrite::Overwrite => {
+                        std::fs::remove_file(&abs_path)?;
+                        make_special(&abs_path, special)?;
+                    }
+                    Overwrite::Error => return Err(ExtractError::FileExists { path: abs_path }),
+                },
+                // Creating devices needs privileges we may not have.
+                ErrorKind::PermissionDenied | ErrorKind::Unsupported => {
+                    log::warn!("Cannot create {} {}: {e}", special.kind, abs_path.display());
+                    return Ok(());
+                }
+                _ => return Err(e.into()),
+            }
+        }
+        if self.restore.xattrs {
+            set_xattrs(&abs_path, &special.metadata.xattrs);
+        }
+        self.restore.apply(
+            &abs_path,
+            false,
+            self.local_owner(&special.metadata),
+            &special.metadata,
+        )?;
+        if self.print_progress {
+            println!("{}", abs_path.display());
+        }
+        Ok(())
+    }
+
+    fn write_dir(&self, dir: &Dir, path: &crate::Path) -> Result<(), ExtractError> {
         self.create_parents(path)?;
         let abs_path = self.abs_path(path);
         create_dir_all(&abs_path)?;
+        if self.restore.xattrs {
+            set_xattrs(&abs_path, &dir.metadata.xattrs);
+        }
         if self.print_progress {
             println!("{}", abs_path.display());
         }
         Ok(())
     }
 
+    /// Restore the metadata of an extracted directory.
+    ///
+    /// This must be called after all its children have been extracted, as creating
+    /// them would change the mtime and permissions may forbid their creation.
+    fn restore_dir(&self, dir: &Dir, path: &crate::Path) -> Result<(), ExtractError> {
+        let abs_path = self.abs_path(path);
+        self.restore.apply(
+            &abs_path,
+            false,
+            self.local_owner(&dir.metadata),
+            &dir.metadata,
+        )?;
+        Ok(())
+    }
+
     pub fn finish(self) -> Arc<OnceLock<jbk::Error>> {
         self.err
     }
@@ -433,20 +892,23 @@ where
     fn on_directory_enter(
         &self,
         current_path: &mut crate::PathBuf,
-        path: &jbk::SmallString,
+        dir: &Dir,
     ) -> Result<bool, ExtractError> {
-        current_path.push(path.as_str());
+        current_path.push(dir.path.as_str());
         if !self.filter.accept(current_path) {
             return Ok(!self.filter.early_exit());
         }
-        self.write_dir(current_path)?;
+        self.write_dir(dir, current_path)?;
         Ok(true)
     }
     fn on_directory_exit(
         &self,
         current_path: &mut crate::PathBuf,
-        _path: &jbk::SmallString,
+        dir: &Dir,
     ) -> Result<(), ExtractError> {
+        if self.filter.accept(current_path) {
+            self.restore_dir(dir, current_path)?;
+        }
         current_path.pop();
         Ok(())
     }
@@ -472,6 +934,19 @@ where
         }
         self.write_link(link, &current_path)
     }
+
+    fn on_special(
+        &self,
+        current_path: &mut crate::PathBuf,
+        special: &Special,
+    ) -> Result<(), ExtractError> {
+        let mut current_path = current_path.clone();
+        current_path.push(special.path.as_str());
+        if !self.filter.accept(&current_path) {
+            return Ok(());
+        }
+        self.write_special(special, &current_path)
+    }
 }
 
 pub fn extract_all(
@@ -494,6 +969,7 @@ pub struct ExtractBuilder<'a, F, P> {
     recursive: bool,
     progress: bool,
     overwrite: Overwrite,
+    restore: Restore,
 }
 
 impl<'a> ExtractBuilder<'a, (), ()> {
@@ -505,6 +981,7 @@ impl<'a> ExtractBuilder<'a, (), ()> {
             recursive: true,
             progress: false,
             overwrite: Overwrite::Warn,
+            restore: Restore::default(),
         }
     }
 }
@@ -518,6 +995,7 @@ impl<'a, P> ExtractBuilder<'a, (), P> {
             recursive: self.recursive,
             progress: self.progress,
             overwrite: self.overwrite,
+            restore: self.restore,
         }
     }
 }
@@ -535,6 +1013,7 @@ impl<'a, F> ExtractBuilder<'a, F, ()> {
             recursive,
             progress: self.progress,
             overwrite: self.overwrite,
+            restore: self.restore,
         }
     }
 }
@@ -548,6 +1027,7 @@ impl<'a, F, P> ExtractBuilder<'a, F, P> {
             recursive: self.recursive,
             progress: self.progress,
             overwrite,
+            restore: self.restore,
         }
     }
 }
@@ -561,10 +1041,47 @@ impl<'a, F, P> ExtractBuilder<'a, F, P> {
             recursive: self.recursive,
             progress,
             overwrite: self.overwrite,
+            restore: self.restore,
         }
     }
 }
 
+impl<'a, F, P> ExtractBuilder<'a, F, P> {
+    /// Restore the extended attributes of the extracted entries.
+    pub fn xattrs(mut self, xattrs: bool) -> ExtractBuilder<'a, F, P> {
+        self.restore.xattrs = xattrs;
+        self
+    }
+
+    /// Restore the stored permissions (including setuid, setgid and sticky bits)
+    /// instead of creating entries with the process umask applied.
+    pub fn preserve_permissions(mut self, preserve: bool) -> ExtractBuilder<'a, F, P> {
+        self.restore.permissions = preserve;
+        self
+    }
+
+    /// Restore the stored owner and group of the extracted entries.
+    ///
+    /// Owner and group names are mapped to the local ids (see [`Self::numeric_owner`]).
+    /// This usually needs to be run as root.
+    pub fn same_owner(mut self, same_owner: bool) -> ExtractBuilder<'a, F, P> {
+        self.restore.owner = same_owner;
+        self
+    }
+
+    /// Restore the stored numeric ids instead of mapping owner and group names to local ids.
+    pub fn numeric_owner(mut self, numeric_owner: bool) -> ExtractBuilder<'a, F, P> {
+        self.restore.numeric_owner = numeric_owner;
+        self
+    }
+
+    /// Restore the stored modification time (and access time if known) of the extracted entries.
+    pub fn preserve_mtime(mut self, preserve: bool) -> ExtractBuilder<'a, F, P> {
+        self.restore.mtime = preserve;
+        self
+    }
+}
+
 impl<'a, F> ExtractBuilder<'a, F, ()>
 where
     F: FileFilter,
@@ -574,6 +1091,28 @@ where
     }
 }
 
+impl ExtractBuilder<'_, (), ()> {
+    /// Extract the tree resulting of the stacked `layers` (see [`Layers`]).
+    ///
+    /// Each layer is extracted in turn, starting from the bottom one, skipping
+    /// the entries hidden by the layers above.
+    pub fn extract_layers(self, layers: &Layers) -> Result<(), ExtractError> {
+        for (layer, arx) in layers.layers().iter().enumerate() {
+            ExtractBuilder {
+                outdir: self.outdir,
+                items: (),
+                filter: layers.filter(layer),
+                recursive: self.recursive,
+                progress: self.progress,
+                overwrite: self.overwrite,
+                restore: self.restore,
+            }
+            .extract(arx, None)?;
+        }
+        Ok(())
+    }
+}
+
 impl<'a, F, P> ExtractBuilder<'a, F, &[P]>
 where
     F: FileFilter,
@@ -608,6 +1147,9 @@ where
                 base_dir: self.outdir.to_path_buf(),
                 print_progress: self.progress,
                 overwrite: self.overwrite,
+                restore: self.restore,
+                hard_links: Default::default(),
+                names: Default::default(),
             };
             if self.items.is_empty() {
                 extractor.extract_all()?
@@ -0,0 +1,136 @@
+use crate::{
+    escape_bytes, Arx, ArxError, ArxFormatError, CommonEntry, Dir, FileEntry, FullBuilder, Link,
+    Walker,
+};
+use jbk::reader::MayMissPack;
+use std::cell::RefCell;
+
+/// The verification status of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyStatus {
+    /// The content matches the stored digest.
+    Ok,
+    /// The content doesn't match the stored digest.
+    Mismatch,
+    /// The content is stored in a pack which is not available.
+    MissingPack,
+    /// No digest is stored for this file (archive created without digests).
+    NoDigest,
+}
+
+impl VerifyStatus {
+    pub fn is_error(&self) -> bool {
+        matches!(self, Self::Mismatch | Self::MissingPack)
+    }
+}
+
+impl std::fmt::Display for VerifyStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Ok => "ok",
+                Self::Mismatch => "mismatch",
+                Self::MissingPack => "missing",
+                Self::NoDigest => "unchecked",
+            }
+        )
+    }
+}
+
+/// The result of `Arx::verify`: the status of each file of the archive, in walk order.
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub entries: Vec<(crate::PathBuf, VerifyStatus)>,
+}
+
+impl VerifyReport {
+    /// True if no file is corrupted or missing.
+    pub fn is_ok(&self) -> bool {
+        !self.entries.iter().any(|(_, status)| status.is_error())
+    }
+
+    /// The files which are corrupted or missing.
+    pub fn errors(&self) -> impl Iterator<Item = &(crate::PathBuf, VerifyStatus)> {
+        self.entries.iter().filter(|(_, status)| status.is_error())
+    }
+}
+
+struct Verifier<'a> {
+    arx: &'a Arx,
+    report: RefCell<VerifyReport>,
+}
+
+impl Verifier<'_> {
+    fn check(&self, entry: &FileEntry) -> Result<VerifyStatus, ArxError> {
+        let bytes = match self
+            .arx
+            .get_bytes(entry.content())?
+            .and_then(|m| m.transpose())
+            .ok_or(ArxFormatError("Content address should be valid"))?
+        {
+            MayMissPack::FOUND(bytes) => bytes,
+            MayMissPack::MISSING(_) => return Ok(VerifyStatus::MissingPack),
+        };
+        let Some(digest) = entry.digest() else {
+            return Ok(VerifyStatus::NoDigest);
+        };
+        let mut hasher = blake3::Hasher::new();
+        std::io::copy(&mut bytes.stream(), &mut hasher)?;
+        Ok(if hasher.finalize().as_bytes() == digest {
+            VerifyStatus::Ok
+        } else {
+            VerifyStatus::Mismatch
+        })
+    }
+}
+
+impl crate::walk::Operator<crate::PathBuf, FullBuilder> for Verifier<'_> {
+    type Error = ArxError;
+    fn on_start(&self, _current_path: &mut crate::PathBuf) -> Result<(), ArxError> {
+        Ok(())
+    }
+    fn on_stop(&self, _current_path: &mut crate::PathBuf) -> Result<(), ArxError> {
+        Ok(())
+    }
+    fn on_directory_enter(
+        &self,
+        current_path: &mut crate::PathBuf,
+        dir: &Dir,
+    ) -> Result<bool, ArxError> {
+        current_path.push(escape_bytes(dir.path()).as_ref());
+        Ok(true)
+    }
+    fn on_directory_exit(
+        &self,
+        current_path: &mut crate::PathBuf,
+        _dir: &Dir,
+    ) -> Result<(), ArxError> {
+        current_path.pop();
+        Ok(())
+    }
+    fn on_file(&self, current_path: &mut crate::PathBuf, file: &FileEntry) -> Result<(), ArxError> {
+        let status = self.check(file)?;
+        let path = current_path.join(escape_bytes(file.path()).as_ref());
+        self.report.borrow_mut().entries.push((path, status));
+        Ok(())
+    }
+    fn on_link(&self, _current_path: &mut crate::PathBuf, _link: &Link) -> Result<(), ArxError> {
+        Ok(())
+    }
+}
+
+impl Arx {
+    /// Check the content of every file in the archive against its stored digest.
+    ///
+    /// Corrupted content and missing packs are reported, not returned as errors.
+    pub fn verify(&self) -> Result<VerifyReport, ArxError> {
+        let verifier = Verifier {
+            arx: self,
+            report: Default::default(),
+        };
+        Walker::new(self, Default::default()).run(&verifier)?;
+        Ok(verifier.report.into_inner())
+    }
+}
@@ -28,6 +28,21 @@ pub trait Operator<Context, Builder: FullBuilderTrait> {
         context: &mut Context,
         entry: &<Builder::Entry as EntryDef>::Link,
     ) -> Result<(), Self::Error>;
+    fn on_special(
+        &self,
+        _context: &mut Context,
+        _entry: &<Builder::Entry as EntryDef>::Special,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// Whiteouts are only in patch archives, most operators can ignore them.
+    fn on_whiteout(
+        &self,
+        _context: &mut Context,
+        _entry: &crate::Whiteout,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 
 pub struct Walker<'a, Context> {
@@ -82,6 +97,8 @@ impl<'a, Context> Walker<'a, Context> {
             match entry? {
                 Entry::File(e) => op.on_file(&mut self.context, &e)?,
                 Entry::Link(e) => op.on_link(&mut self.context, &e)?,
+                Entry::Special(e) => op.on_special(&mut self.context, &e)?,
+                Entry::Whiteout(e) => op.on_whiteout(&mut self.context, &e)?,
                 Entry::Dir(range, e) => {
                     if op.on_directory_enter(&mut self.context, &e)? {
                         self._run(&range, builder, op)?;
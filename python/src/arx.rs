@@ -101,6 +101,13 @@ impl Arx {
         Self::get_content_rust(self, content.0)
     }
 
+    /// Get the archive-level metadata as a dict
+    fn metadata(&self) -> PyResult<std::collections::BTreeMap<String, String>> {
+        self.0
+            .metadata()
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    }
+
     fn __iter__(slf: PyRef<'_, Self>) -> PyResult<Py<EntryIter>> {
         let iter = EntryIter::new_from_index(Arc::clone(&slf.0), &slf.0.root_index);
         Py::new(slf.py(), iter)
@@ -1,8 +1,8 @@
 use std::rc::Rc;
 use std::{path::PathBuf, sync::Arc};
 
-use arx::create::{FsAdder, SimpleCreator};
-use pyo3::exceptions::PyRuntimeError;
+use arx::create::{FsAdder, MetadataOverride, ModeSpec, MountPoints, Owner, SimpleCreator};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
 use pyo3::prelude::*;
 
 /// An Arx creator.
@@ -66,10 +66,64 @@ impl Creator {
         }
     }
 
+    /// Set an archive-level metadata.
+    fn set_metadata(&mut self, key: String, value: String) -> PyResult<()> {
+        match self.creator.as_mut() {
+            None => Err(PyRuntimeError::new_err("Creator already finalized")),
+            Some(creator) => {
+                creator.set_metadata(key, value);
+                Ok(())
+            }
+        }
+    }
+
+    /// Force the metadata of all entries added from now on.
+    ///
+    /// `owner` and `group` are given as `ID`, `NAME` or `NAME:ID`, `mode` as a chmod-like
+    /// expression (`644`, `u+rwX,go-w`, ...) and `mtime` in seconds since epoch.
+    #[pyo3(signature=(owner=None, group=None, mode=None, mtime=None))]
+    fn set_metadata_override(
+        &mut self,
+        owner: Option<&str>,
+        group: Option<&str>,
+        mode: Option<&str>,
+        mtime: Option<u64>,
+    ) -> PyResult<()> {
+        let to_py_err = |e: arx::InputError| PyValueError::new_err(e.to_string());
+        let metadata_override = MetadataOverride {
+            owner: owner
+                .map(Owner::parse_user)
+                .transpose()
+                .map_err(to_py_err)?,
+            group: group
+                .map(Owner::parse_group)
+                .transpose()
+                .map_err(to_py_err)?,
+            mode: mode.map(ModeSpec::parse).transpose().map_err(to_py_err)?,
+            mtime,
+        };
+        match self.creator.as_mut() {
+            None => Err(PyRuntimeError::new_err("Creator already finalized")),
+            Some(creator) => {
+                creator.set_metadata_override(metadata_override);
+                Ok(())
+            }
+        }
+    }
+
     /// Add the file `name` to the archive. `name` may be any type of file (directory, symlink, regular file).
     /// Directory are added recursively by default. This cane be avoided by setting `recursive` to `False`
-    #[pyo3(signature=(path, recursive=true, keep_parents=false))]
-    fn add(&mut self, path: PathBuf, recursive: bool, keep_parents: bool) -> PyResult<()> {
+    /// With `one_file_system`, directories on another filesystem are not added,
+    /// unless `keep_mount_points` is set, in which case they are added empty.
+    #[pyo3(signature=(path, recursive=true, keep_parents=false, one_file_system=false, keep_mount_points=false))]
+    fn add(
+        &mut self,
+        path: PathBuf,
+        recursive: bool,
+        keep_parents: bool,
+        one_file_system: bool,
+        keep_mount_points: bool,
+    ) -> PyResult<()> {
         match self.creator.as_mut() {
             None => Err(PyRuntimeError::new_err("Creator already finalized")),
             Some(creator) => {
@@ -78,7 +132,12 @@ impl Creator {
                         "add method must be used inside a context manager",
                     ));
                 }
-                let mut adder = FsAdder::new(creator, keep_parents, false, false);
+                let mount_points = match (one_file_system, keep_mount_points) {
+                    (false, _) => MountPoints::Cross,
+                    (true, false) => MountPoints::Exclude,
+                    (true, true) => MountPoints::EmptyDir,
+                };
+                let mut adder = FsAdder::new(creator, keep_parents, false, false, mount_points);
                 adder
                     .add_from_path(path, recursive)
                     .map_err(|e| PyRuntimeError::new_err(e.to_string()))
@@ -10,7 +10,7 @@ use crate::{content_address::ContentAddress, iterator::EntryIter, stream::Stream
 
 /// An entry i an arx archive.
 ///
-/// Can be a directory, a file or a link.
+/// Can be a directory, a file, a link, a special file or a whiteout.
 #[pyclass]
 pub struct Entry {
     arx: Arc<arx::Arx>,
@@ -28,13 +28,19 @@ impl Entry {
     fn __repr__(&self) -> String {
         match &self.entry {
             arx::Entry::File(e) => {
-                format!("File({})", String::from_utf8(e.path().to_vec()).unwrap())
+                format!("File({})", arx::escape_bytes(e.path()))
             }
             arx::Entry::Link(e) => {
-                format!("Link({})", String::from_utf8(e.path().to_vec()).unwrap())
+                format!("Link({})", arx::escape_bytes(e.path()))
             }
             arx::Entry::Dir(_, e) => {
-                format!("Dir({})", String::from_utf8(e.path().to_vec()).unwrap())
+                format!("Dir({})", arx::escape_bytes(e.path()))
+            }
+            arx::Entry::Special(e) => {
+                format!("Special({})", arx::escape_bytes(e.path()))
+            }
+            arx::Entry::Whiteout(e) => {
+                format!("Whiteout({})", arx::escape_bytes(e.path()))
             }
         }
     }
@@ -46,17 +52,24 @@ impl Entry {
             arx::Entry::File(e) => e.idx().into_u32(),
             arx::Entry::Link(e) => e.idx().into_u32(),
             arx::Entry::Dir(_, e) => e.idx().into_u32(),
+            arx::Entry::Special(e) => e.idx().into_u32(),
+            arx::Entry::Whiteout(e) => e.idx().into_u32(),
         }
     }
 
     /// The path (relative to its parent entry)
+    ///
+    /// Bytes which are not valid utf-8 are escaped in the private use area (U+10FF80..U+10FFFF).
     #[getter]
     fn path(&self) -> PyResult<String> {
         Ok(match &self.entry {
-            arx::Entry::File(e) => String::from_utf8(e.path().to_vec()).unwrap(),
-            arx::Entry::Link(e) => String::from_utf8(e.path().to_vec()).unwrap(),
-            arx::Entry::Dir(_, e) => String::from_utf8(e.path().to_vec()).unwrap(),
-        })
+            arx::Entry::File(e) => arx::escape_bytes(e.path()),
+            arx::Entry::Link(e) => arx::escape_bytes(e.path()),
+            arx::Entry::Dir(_, e) => arx::escape_bytes(e.path()),
+            arx::Entry::Special(e) => arx::escape_bytes(e.path()),
+            arx::Entry::Whiteout(e) => arx::escape_bytes(e.path()),
+        }
+        .into_owned())
     }
 
     /// The index of the parent entry.
@@ -66,6 +79,8 @@ impl Entry {
             arx::Entry::File(e) => e.parent(),
             arx::Entry::Link(e) => e.parent(),
             arx::Entry::Dir(_, e) => e.parent(),
+            arx::Entry::Special(e) => e.parent(),
+            arx::Entry::Whiteout(e) => e.parent(),
         };
         match parent_idx {
             None => Ok(None),
@@ -89,6 +104,8 @@ impl Entry {
             arx::Entry::File(e) => e.owner(),
             arx::Entry::Link(e) => e.owner(),
             arx::Entry::Dir(_, e) => e.owner(),
+            arx::Entry::Special(e) => e.owner(),
+            arx::Entry::Whiteout(e) => e.owner(),
         }
     }
 
@@ -99,16 +116,46 @@ impl Entry {
             arx::Entry::File(e) => e.group(),
             arx::Entry::Link(e) => e.group(),
             arx::Entry::Dir(_, e) => e.group(),
+            arx::Entry::Special(e) => e.group(),
+            arx::Entry::Whiteout(e) => e.group(),
         }
     }
 
-    /// The rigths (int) of the entry.
+    /// The name of the owner of the entry (None if unknown).
+    #[getter]
+    fn owner_name(&self) -> Option<String> {
+        let name = match &self.entry {
+            arx::Entry::File(e) => e.owner_name(),
+            arx::Entry::Link(e) => e.owner_name(),
+            arx::Entry::Dir(_, e) => e.owner_name(),
+            arx::Entry::Special(e) => e.owner_name(),
+            arx::Entry::Whiteout(e) => e.owner_name(),
+        };
+        name.map(|n| String::from_utf8_lossy(n).into_owned())
+    }
+
+    /// The name of the group of the entry (None if unknown).
+    #[getter]
+    fn group_name(&self) -> Option<String> {
+        let name = match &self.entry {
+            arx::Entry::File(e) => e.group_name(),
+            arx::Entry::Link(e) => e.group_name(),
+            arx::Entry::Dir(_, e) => e.group_name(),
+            arx::Entry::Special(e) => e.group_name(),
+            arx::Entry::Whiteout(e) => e.group_name(),
+        };
+        name.map(|n| String::from_utf8_lossy(n).into_owned())
+    }
+
+    /// The rigths (int) of the entry, including setuid, setgid and sticky bits.
     #[getter]
-    fn rights(&self) -> u8 {
+    fn rights(&self) -> u16 {
         match &self.entry {
             arx::Entry::File(e) => e.rights(),
             arx::Entry::Link(e) => e.rights(),
             arx::Entry::Dir(_, e) => e.rights(),
+            arx::Entry::Special(e) => e.rights(),
+            arx::Entry::Whiteout(e) => e.rights(),
         }
     }
 
@@ -119,6 +166,56 @@ impl Entry {
             arx::Entry::File(e) => e.mtime(),
             arx::Entry::Link(e) => e.mtime(),
             arx::Entry::Dir(_, e) => e.mtime(),
+            arx::Entry::Special(e) => e.mtime(),
+            arx::Entry::Whiteout(e) => e.mtime(),
+        }
+    }
+
+    /// The sub-second part of the modification time, in nanoseconds.
+    #[getter]
+    fn mtime_nsec(&self) -> u32 {
+        match &self.entry {
+            arx::Entry::File(e) => e.mtime_nsec(),
+            arx::Entry::Link(e) => e.mtime_nsec(),
+            arx::Entry::Dir(_, e) => e.mtime_nsec(),
+            arx::Entry::Special(e) => e.mtime_nsec(),
+            arx::Entry::Whiteout(e) => e.mtime_nsec(),
+        }
+    }
+
+    /// The access time of the entry, in nanoseconds since epoch (None if unknown).
+    #[getter]
+    fn atime_ns(&self) -> Option<u64> {
+        match &self.entry {
+            arx::Entry::File(e) => e.atime_ns(),
+            arx::Entry::Link(e) => e.atime_ns(),
+            arx::Entry::Dir(_, e) => e.atime_ns(),
+            arx::Entry::Special(e) => e.atime_ns(),
+            arx::Entry::Whiteout(e) => e.atime_ns(),
+        }
+    }
+
+    /// The status change time of the entry, in nanoseconds since epoch (None if unknown).
+    #[getter]
+    fn ctime_ns(&self) -> Option<u64> {
+        match &self.entry {
+            arx::Entry::File(e) => e.ctime_ns(),
+            arx::Entry::Link(e) => e.ctime_ns(),
+            arx::Entry::Dir(_, e) => e.ctime_ns(),
+            arx::Entry::Special(e) => e.ctime_ns(),
+            arx::Entry::Whiteout(e) => e.ctime_ns(),
+        }
+    }
+
+    /// The creation time of the entry, in nanoseconds since epoch (None if unknown).
+    #[getter]
+    fn btime_ns(&self) -> Option<u64> {
+        match &self.entry {
+            arx::Entry::File(e) => e.btime_ns(),
+            arx::Entry::Link(e) => e.btime_ns(),
+            arx::Entry::Dir(_, e) => e.btime_ns(),
+            arx::Entry::Special(e) => e.btime_ns(),
+            arx::Entry::Whiteout(e) => e.btime_ns(),
         }
     }
 
@@ -137,6 +234,16 @@ impl Entry {
         matches!(&self.entry, arx::Entry::Dir(_, _))
     }
 
+    /// Return True if the entry is a special file entry (device, fifo or socket)
+    fn is_special(&self) -> bool {
+        matches!(&self.entry, arx::Entry::Special(_))
+    }
+
+    /// Return True if the entry is a whiteout (only in patch archives)
+    fn is_whiteout(&self) -> bool {
+        matches!(&self.entry, arx::Entry::Whiteout(_))
+    }
+
     /// Get the content address of the file entry.
     ///
     /// Raise an exception if entry is not a file.
@@ -157,6 +264,16 @@ impl Entry {
         }
     }
 
+    /// Get the BLAKE3 digest of the content of the file entry, if stored in the archive.
+    ///
+    /// Raise an exception if entry is not a file.
+    fn get_digest(&self) -> PyResult<Option<Cow<'_, [u8]>>> {
+        match &self.entry {
+            arx::Entry::File(f) => Ok(f.digest().map(Cow::from)),
+            _ => Err(PyTypeError::new_err("Not a file")),
+        }
+    }
+
     /// Get the link target of the link entry.
     ///
     /// Raise an exception if entry is not a link.
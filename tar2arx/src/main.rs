@@ -1,4 +1,4 @@
-use bstr::{BString, ByteVec};
+use bstr::BString;
 use clap::{CommandFactory, Parser, ValueHint};
 
 use anyhow::{anyhow, Result};
@@ -54,6 +54,15 @@ struct Cli {
     #[arg(long, default_value_t = false, action)]
     list_compressions: bool,
 
+    #[command(flatten)]
+    metadata_override: arx::cmd_utils::MetadataOverride,
+
+    #[command(flatten)]
+    compression_rules: arx::cmd_utils::CompressionRules,
+
+    #[command(flatten)]
+    path_transforms: arx::cmd_utils::PathTransforms,
+
     #[arg(long, help_heading = "Advanced")]
     generate_man_page: bool,
 
@@ -129,6 +138,7 @@ impl jbk::creator::Progress for ProgressBar {
 pub struct Converter<R: Read> {
     arx_creator: arx::create::SimpleCreator,
     archive: tar::Archive<R>,
+    compression_policy: Box<dyn arx::create::CompressionPolicy>,
 }
 
 struct TarEntry {
@@ -138,22 +148,94 @@ struct TarEntry {
     gid: u64,
     mode: u64,
     mtime: u64,
+    pax: PaxInfo,
+}
+
+/// Information stored in PAX extended header
+#[derive(Default)]
+struct PaxInfo {
+    /// `SCHILY.xattr.<name>` records
+    xattrs: arx::Xattrs,
+    /// `mtime` record, as seconds and nanoseconds
+    mtime: Option<(u64, u32)>,
+    /// `atime` record, in nanoseconds since epoch (0 if not present)
+    atime: u64,
+    /// `ctime` record, in nanoseconds since epoch (0 if not present)
+    ctime: u64,
+    /// `uname` record (or user name of the header)
+    uname: Option<Vec<u8>>,
+    /// `gname` record (or group name of the header)
+    gname: Option<Vec<u8>>,
+}
+
+/// Parse a PAX time record (`<seconds>[.<fraction>]`) into seconds and nanoseconds.
+///
+/// Times before epoch are not supported.
+fn parse_pax_time(value: &[u8]) -> Option<(u64, u32)> {
+    let value = std::str::from_utf8(value).ok()?;
+    let (secs, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let secs = secs.parse().ok()?;
+    let fraction = fraction.get(..9).unwrap_or(fraction);
+    let nsec = if fraction.is_empty() {
+        0
+    } else {
+        format!("{fraction:0<9}").parse().ok()?
+    };
+    Some((secs, nsec))
+}
+
+fn pax_time_ns(value: &[u8]) -> u64 {
+    parse_pax_time(value).map_or(0, |(secs, nsec)| secs * 1_000_000_000 + nsec as u64)
+}
+
+/// Read the information stored in PAX extended header
+fn read_pax<'a, R: 'a + Read>(entry: &mut tar::Entry<'a, R>) -> Result<PaxInfo, arx::CreatorError> {
+    const XATTR_PREFIX: &[u8] = b"SCHILY.xattr.";
+    let mut pax = PaxInfo::default();
+    if let Some(extensions) = entry.pax_extensions()? {
+        for extension in extensions {
+            let extension = extension?;
+            match extension.key_bytes() {
+                b"mtime" => pax.mtime = parse_pax_time(extension.value_bytes()),
+                b"atime" => pax.atime = pax_time_ns(extension.value_bytes()),
+                b"ctime" => pax.ctime = pax_time_ns(extension.value_bytes()),
+                b"uname" => pax.uname = Some(extension.value_bytes().to_vec()),
+                b"gname" => pax.gname = Some(extension.value_bytes().to_vec()),
+                key => {
+                    if let Some(name) = key.strip_prefix(XATTR_PREFIX) {
+                        pax.xattrs.insert(name, extension.value_bytes());
+                    }
+                }
+            }
+        }
+    }
+    Ok(pax)
 }
 
 impl TarEntry {
     pub fn new<'a, R: 'a + Read>(
         mut entry: tar::Entry<'a, R>,
         adder: &mut impl ContentAdder,
+        compression_policy: &dyn arx::create::CompressionPolicy,
     ) -> Result<Option<Self>, arx::CreatorError> {
+        let mut pax = read_pax(&mut entry)?;
         let header = entry.header();
         // GnuHeader tar may store the user/group name and not the uid/gid.
-        // Store 0 for now.
+        // Store 0 as id, names are mapped back to local ids at extraction.
+        if pax.uname.is_none() {
+            pax.uname = header.username_bytes().map(<[u8]>::to_vec);
+        }
+        if pax.gname.is_none() {
+            pax.gname = header.groupname_bytes().map(<[u8]>::to_vec);
+        }
         let uid = header.uid().unwrap_or_default();
         let gid = header.gid().unwrap_or_default();
-        let mtime = header.mtime()?;
+        let mtime = match pax.mtime {
+            Some((secs, _)) => secs,
+            None => header.mtime()?,
+        };
         let mode = header.mode()? as u64;
-        let path = arx::PathBuf::from_path(entry.path()?)
-            .unwrap_or_else(|_| panic!("Entry path must be utf-8"));
+        let path = arx::path_from_bytes(&entry.path_bytes());
         Ok(match header.entry_type() {
             tar::EntryType::Directory => Some(Self {
                 path,
@@ -162,19 +244,54 @@ impl TarEntry {
                 gid,
                 mtime,
                 mode,
+                pax,
             }),
             tar::EntryType::Symlink => {
-                let target = entry.link_name()?.unwrap();
+                let target = entry
+                    .link_name_bytes()
+                    .ok_or_else(|| arx::InputError(format!("Link {path} has no target")))?;
+                Some(Self {
+                    path,
+                    kind: arx::create::EntryKind::Link(BString::new(target.into_owned())),
+                    uid,
+                    gid,
+                    mtime,
+                    mode,
+                    pax,
+                })
+            }
+            tar::EntryType::Link => {
+                let target = entry
+                    .link_name_bytes()
+                    .ok_or_else(|| arx::InputError(format!("Link {path} has no target")))?;
+                Some(Self {
+                    path,
+                    kind: arx::create::EntryKind::HardLink(arx::path_from_bytes(&target)),
+                    uid,
+                    gid,
+                    mtime,
+                    mode,
+                    pax,
+                })
+            }
+            tar::EntryType::Char | tar::EntryType::Block | tar::EntryType::Fifo => {
+                let kind = match header.entry_type() {
+                    tar::EntryType::Char => arx::SpecialKind::CharDevice,
+                    tar::EntryType::Block => arx::SpecialKind::BlockDevice,
+                    _ => arx::SpecialKind::Fifo,
+                };
                 Some(Self {
                     path,
-                    kind: arx::create::EntryKind::Link(BString::new(
-                        Vec::from_path_buf(target.into_owned())
-                            .unwrap_or_else(|target| panic!("{target:?} must be utf8")),
-                    )),
+                    kind: arx::create::EntryKind::Special(
+                        kind,
+                        header.device_major()?.unwrap_or_default(),
+                        header.device_minor()?.unwrap_or_default(),
+                    ),
                     uid,
                     gid,
                     mtime,
                     mode,
+                    pax,
                 })
             }
             /* GNULongName, GNULongLink and XHeader should already be handled by entries iterator
@@ -194,6 +311,7 @@ impl TarEntry {
                         gid,
                         mtime,
                         mode,
+                        pax,
                     })
                 } else {
                     //Handle everything else as normal file
@@ -201,7 +319,7 @@ impl TarEntry {
                     let size = entry.read_to_end(&mut data)?;
                     let content_address = adder.add_content(
                         Box::new(std::io::Cursor::new(data)),
-                        jbk::creator::CompHint::Detect,
+                        compression_policy.comp_hint(&path),
                     )?;
                     Some(Self {
                         path,
@@ -210,6 +328,7 @@ impl TarEntry {
                         gid,
                         mtime,
                         mode,
+                        pax,
                     })
                 }
             }
@@ -231,12 +350,30 @@ impl arx::create::EntryTrait for TarEntry {
     fn gid(&self) -> u64 {
         self.gid
     }
+    fn owner_name(&self) -> Option<&[u8]> {
+        self.pax.uname.as_deref().filter(|n| !n.is_empty())
+    }
+    fn group_name(&self) -> Option<&[u8]> {
+        self.pax.gname.as_deref().filter(|n| !n.is_empty())
+    }
     fn mode(&self) -> u64 {
         self.mode
     }
     fn mtime(&self) -> u64 {
         self.mtime
     }
+    fn mtime_nsec(&self) -> u32 {
+        self.pax.mtime.map_or(0, |(_, nsec)| nsec)
+    }
+    fn atime_ns(&self) -> u64 {
+        self.pax.atime
+    }
+    fn ctime_ns(&self) -> u64 {
+        self.pax.ctime
+    }
+    fn xattrs(&self) -> Option<&arx::Xattrs> {
+        Some(&self.pax.xattrs)
+    }
 }
 
 impl<R: Read> Converter<R> {
@@ -259,9 +396,33 @@ impl<R: Read> Converter<R> {
         Ok(Self {
             arx_creator,
             archive,
+            compression_policy: Box::new(arx::create::DetectCompression),
         })
     }
 
+    /// Set an archive-level metadata of the created archive.
+    pub fn set_metadata(&mut self, key: &str, value: &str) {
+        self.arx_creator.set_metadata(key, value)
+    }
+
+    /// Force the metadata of the converted entries.
+    pub fn set_metadata_override(&mut self, metadata_override: arx::create::MetadataOverride) {
+        self.arx_creator.set_metadata_override(metadata_override)
+    }
+
+    /// Rewrite the paths of the converted entries.
+    pub fn set_path_transforms(&mut self, path_transforms: arx::create::PathTransforms) {
+        self.arx_creator.set_path_transforms(path_transforms)
+    }
+
+    /// Use `policy` to choose if the converted contents must be compressed.
+    pub fn set_compression_policy(
+        &mut self,
+        policy: impl arx::create::CompressionPolicy + 'static,
+    ) {
+        self.compression_policy = Box::new(policy);
+    }
+
     fn finalize(self) -> Result<(), arx::CreatorError> {
         self.arx_creator.finalize()
     }
@@ -270,7 +431,11 @@ impl<R: Read> Converter<R> {
         let iter = self.archive.entries()?;
         for entry in iter {
             let entry = entry?;
-            if let Some(entry) = TarEntry::new(entry, self.arx_creator.adder())? {
+            if let Some(entry) = TarEntry::new(
+                entry,
+                self.arx_creator.adder(),
+                self.compression_policy.as_ref(),
+            )? {
                 self.arx_creator.add_entry(&entry)?;
             }
         }
@@ -325,6 +490,8 @@ fn main() -> Result<()> {
         p
     });
 
+    let source = args.tar_file.clone().unwrap_or_else(|| "-".into());
+
     let input: Box<dyn Read> = match args.tar_file {
         None => Box::new(std::io::stdin()),
         Some(p) => {
@@ -355,7 +522,7 @@ fn main() -> Result<()> {
         .0;
 
     let archive = tar::Archive::new(input_stream);
-    let converter = Converter::new(
+    let mut converter = Converter::new(
         archive,
         &outfile,
         match args.concat_mode {
@@ -365,5 +532,19 @@ fn main() -> Result<()> {
         args.compression,
         progress_bar,
     )?;
+    converter.set_metadata(
+        "creator",
+        concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION")),
+    );
+    converter.set_metadata("source", &source);
+    converter.set_metadata_override(args.metadata_override.into());
+    let compression_rules = arx::create::CompressionRules::try_from(args.compression_rules)?;
+    if !compression_rules.is_empty() {
+        converter.set_compression_policy(compression_rules);
+    }
+    let path_transforms = arx::create::PathTransforms::from(args.path_transforms);
+    if !path_transforms.is_empty() {
+        converter.set_path_transforms(path_transforms);
+    }
     Ok(converter.run()?)
 }
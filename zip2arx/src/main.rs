@@ -60,6 +60,15 @@ struct Cli {
     #[arg(long, default_value_t = false, action)]
     list_compressions: bool,
 
+    #[command(flatten)]
+    metadata_override: arx::cmd_utils::MetadataOverride,
+
+    #[command(flatten)]
+    compression_rules: arx::cmd_utils::CompressionRules,
+
+    #[command(flatten)]
+    path_transforms: arx::cmd_utils::PathTransforms,
+
     #[arg(long, help_heading = "Advanced")]
     generate_man_page: bool,
 
@@ -133,6 +142,7 @@ pub struct Converter<R: Read + Seek> {
     archive_path: PathBuf,
     archive: zip::ZipArchive<R>,
     progress: Arc<ProgressBar>,
+    compression_policy: Box<dyn arx::create::CompressionPolicy>,
 }
 
 struct ZipEntry {
@@ -140,42 +150,80 @@ struct ZipEntry {
     kind: arx::create::EntryKind,
     mode: u64,
     mtime: u64,
+    mtime_nsec: u32,
+    atime: u64,
+    btime: u64,
+}
+
+const NSEC_PER_SEC: u64 = 1_000_000_000;
+
+/// Convert a NTFS time (100ns intervals since 1601-01-01) into nanoseconds since epoch.
+fn ntfs_to_unix_ns(time: u64) -> u64 {
+    const NTFS_EPOCH_OFFSET: u64 = 116_444_736_000_000_000;
+    time.saturating_sub(NTFS_EPOCH_OFFSET).saturating_mul(100)
+}
+
+/// Convert an extended timestamp (seconds since epoch) into nanoseconds since epoch.
+///
+/// Times before epoch are clamped to epoch.
+fn timestamp_to_unix_ns(ts: i32) -> u64 {
+    u64::try_from(ts).unwrap_or(0).saturating_mul(NSEC_PER_SEC)
 }
 
 impl ZipEntry {
     pub fn new<R: std::io::Read>(
         mut entry: zip::read::ZipFile<'_, R>,
         adder: &mut impl ContentAdder,
+        compression_policy: &dyn arx::create::CompressionPolicy,
         archive_path: &Path,
     ) -> Result<Self, arx::CreatorError> {
+        // Times in nanoseconds since epoch
         let mut mtime = None;
+        let mut atime = 0;
+        let mut btime = 0;
         for extra_field in entry.extra_data_fields() {
             match extra_field {
                 zip::ExtraField::ExtendedTimestamp(ex_timestamp) => {
-                    mtime = ex_timestamp.mod_time().map(|ts| ts as u64)
+                    mtime = ex_timestamp.mod_time().map(timestamp_to_unix_ns);
+                    atime = ex_timestamp.ac_time().map_or(0, timestamp_to_unix_ns);
+                    btime = ex_timestamp.cr_time().map_or(0, timestamp_to_unix_ns);
+                }
+                zip::ExtraField::Ntfs(ntfs) => {
+                    mtime = Some(ntfs_to_unix_ns(ntfs.mtime()));
+                    atime = ntfs_to_unix_ns(ntfs.atime());
+                    btime = ntfs_to_unix_ns(ntfs.ctime());
                 }
-                zip::ExtraField::Ntfs(ntfs) => mtime = Some(ntfs.mtime()),
             }
         }
-        let mtime = match mtime {
-            Some(ts) => ts,
-            None => entry
-                .last_modified()
-                .map(|ts| time::OffsetDateTime::try_from(ts).unwrap().unix_timestamp() as u64)
-                .unwrap_or(0),
+        let (mtime, mtime_nsec) = match mtime {
+            Some(ts) => (ts / NSEC_PER_SEC, (ts % NSEC_PER_SEC) as u32),
+            None => (
+                entry
+                    .last_modified()
+                    .map(|ts| time::OffsetDateTime::try_from(ts).unwrap().unix_timestamp() as u64)
+                    .unwrap_or(0),
+                0,
+            ),
         };
         let mode = entry.unix_mode().unwrap_or(0o644) as u64;
         let path = entry
             .enclosed_name()
             .ok_or(arx::InputError("Invalid path".into()))?;
-        let path =
-            arx::PathBuf::from_path(&path).unwrap_or_else(|_| panic!("{path:?} must be utf8"));
+        // Names are decoded (from utf-8 or cp437) by the zip crate, so they are valid unicode.
+        // They are escaped anyway as they may contain the escape chars.
+        let path = path
+            .to_str()
+            .map(|p| arx::path_from_bytes(p.as_bytes()))
+            .ok_or_else(|| arx::InputError(format!("Invalid path {}", path.display())))?;
 
         Ok(if entry.is_dir() {
             Self {
                 path,
                 kind: arx::create::EntryKind::Dir,
                 mtime,
+                mtime_nsec,
+                atime,
+                btime,
                 mode,
             }
         } else {
@@ -185,19 +233,22 @@ impl ZipEntry {
                     entry.data_start(),
                     Some(entry.size()),
                 )?;
-                adder.add_content(Box::new(reader), jbk::creator::CompHint::Detect)?
+                adder.add_content(Box::new(reader), compression_policy.comp_hint(&path))?
             } else {
                 let mut data = vec![];
                 entry.read_to_end(&mut data)?;
                 adder.add_content(
                     Box::new(std::io::Cursor::new(data)),
-                    jbk::creator::CompHint::Detect,
+                    compression_policy.comp_hint(&path),
                 )?
             };
             Self {
                 path,
                 kind: arx::create::EntryKind::File(entry.size().into(), content_address),
                 mtime,
+                mtime_nsec,
+                atime,
+                btime,
                 mode,
             }
         })
@@ -224,6 +275,15 @@ impl arx::create::EntryTrait for ZipEntry {
     fn mtime(&self) -> u64 {
         self.mtime
     }
+    fn mtime_nsec(&self) -> u32 {
+        self.mtime_nsec
+    }
+    fn atime_ns(&self) -> u64 {
+        self.atime
+    }
+    fn btime_ns(&self) -> u64 {
+        self.btime
+    }
 }
 
 impl<R: Read + Seek> Converter<R> {
@@ -234,22 +294,49 @@ impl<R: Read + Seek> Converter<R> {
         concat_mode: jbk::creator::ConcatMode,
     ) -> Result<Self, arx::CreatorError> {
         let progress = Arc::new(ProgressBar::new(&archive));
-        let arx_creator = arx::create::SimpleCreator::new(
+        let mut arx_creator = arx::create::SimpleCreator::new(
             outfile,
             concat_mode,
             Arc::clone(&progress) as Arc<dyn jbk::creator::Progress>,
             Rc::new(()),
             jbk::creator::Compression::zstd(),
         )?;
+        arx_creator.set_metadata(
+            "creator",
+            concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION")),
+        );
+        arx_creator.set_metadata("source", archive_path.to_string_lossy());
+        if !archive.comment().is_empty() {
+            arx_creator.set_metadata("comment", String::from_utf8_lossy(archive.comment()));
+        }
 
         Ok(Self {
             arx_creator,
             archive,
             archive_path,
             progress,
+            compression_policy: Box::new(arx::create::DetectCompression),
         })
     }
 
+    /// Force the metadata of the converted entries.
+    pub fn set_metadata_override(&mut self, metadata_override: arx::create::MetadataOverride) {
+        self.arx_creator.set_metadata_override(metadata_override)
+    }
+
+    /// Rewrite the paths of the converted entries.
+    pub fn set_path_transforms(&mut self, path_transforms: arx::create::PathTransforms) {
+        self.arx_creator.set_path_transforms(path_transforms)
+    }
+
+    /// Use `policy` to choose if the converted contents must be compressed.
+    pub fn set_compression_policy(
+        &mut self,
+        policy: impl arx::create::CompressionPolicy + 'static,
+    ) {
+        self.compression_policy = Box::new(policy);
+    }
+
     fn finalize(self) -> Result<(), arx::CreatorError> {
         self.arx_creator.finalize()
     }
@@ -258,7 +345,12 @@ impl<R: Read + Seek> Converter<R> {
         for idx in 0..self.archive.len() {
             self.progress.entries.inc(1);
             let entry = self.archive.by_index(idx).unwrap();
-            let entry = ZipEntry::new(entry, self.arx_creator.adder(), &self.archive_path)?;
+            let entry = ZipEntry::new(
+                entry,
+                self.arx_creator.adder(),
+                self.compression_policy.as_ref(),
+                &self.archive_path,
+            )?;
             self.arx_creator.add_entry(&entry)?;
         }
         self.finalize()
@@ -294,7 +386,7 @@ fn main() -> Result<(), arx::CreatorError> {
 
     let file = std::fs::File::open(args.zip_file.as_ref().unwrap())?;
     let archive = zip::ZipArchive::new(file).unwrap();
-    let converter = Converter::new(
+    let mut converter = Converter::new(
         archive,
         args.zip_file.unwrap(),
         args.outfile.as_ref().unwrap(),
@@ -303,5 +395,14 @@ fn main() -> Result<(), arx::CreatorError> {
             Some(e) => e.into(),
         },
     )?;
+    converter.set_metadata_override(args.metadata_override.into());
+    let compression_rules = arx::create::CompressionRules::try_from(args.compression_rules)?;
+    if !compression_rules.is_empty() {
+        converter.set_compression_policy(compression_rules);
+    }
+    let path_transforms = arx::create::PathTransforms::from(args.path_transforms);
+    if !path_transforms.is_empty() {
+        converter.set_path_transforms(path_transforms);
+    }
     converter.run()
 }